@@ -1,6 +1,7 @@
 use anyhow::{anyhow, bail, Result};
 use aruco_locator::{ArucoDetector, ArucoDetectorConfig};
 use geometry_msgs::msg::{Point, Pose, PoseWithCovariance, Quaternion};
+use hollow_board_config::validate_aruco_pattern;
 use noisy_float::prelude::*;
 use opencv::{core::CV_8UC3, prelude::*};
 use rclrs::{log_error, log_info, log_warn, *};
@@ -329,9 +330,9 @@ impl ArucoLocatorNode {
 
     /// Load ArUco pattern from config file
     fn load_aruco_pattern() -> Result<aruco_config::MultiArucoPattern> {
-        Ok(Json5Path::open_and_take(&PathBuf::from(
-            ARUCO_PATTERN_CONFIG,
-        ))?)
+        let pattern = Json5Path::open_and_take(&PathBuf::from(ARUCO_PATTERN_CONFIG))?;
+        validate_aruco_pattern(&pattern)?;
+        Ok(pattern)
     }
 
     /// Process the incoming image
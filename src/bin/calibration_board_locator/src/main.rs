@@ -5,7 +5,7 @@ use anyhow::{anyhow, Result};
 use aruco_config::MultiArucoPattern;
 use geometry_msgs::msg::{Point, Pose, PoseWithCovariance, Quaternion, Vector3};
 use hollow_board_detector::{
-    Config as BoardDetectorConfig, Detection as BoardDetection, Detector as BoardDetector,
+    tracker::TrackedBoard, BoardTracker, Config as BoardDetectorConfig, Detector as BoardDetector,
 };
 use nalgebra as na;
 use once_cell::sync::Lazy;
@@ -61,15 +61,17 @@ impl CalibrationBoardLocatorNode {
         // Load configurations
         let board_detector_config = Self::load_board_detector_config(&board_detector_file_param)?;
         let aruco_pattern_config = Self::load_aruco_pattern_config(&aruco_pattern_file_param)?;
+        board_detector_config.validate(&aruco_pattern_config)?;
 
         let bbox = Self::load_bbox_config(&bbox_file_param)?;
         let bbox = Arc::new(Mutex::new(bbox));
 
-        // Create detector
-        let detector = Arc::new(BoardDetector::new(
+        // Create detector. The tracker keeps the board poses across
+        // point cloud messages.
+        let tracker = Arc::new(Mutex::new(BoardTracker::new(BoardDetector::new(
             board_detector_config,
             aruco_pattern_config,
-        ));
+        ))));
 
         // Create publisher for detections
         let detection_publisher =
@@ -80,7 +82,7 @@ impl CalibrationBoardLocatorNode {
         let pointcloud_subscription = node.create_subscription::<PointCloud2, _>(
             "input_pointcloud",
             move |msg: PointCloud2| {
-                Self::pointcloud_callback(msg, &detector, &detection_publisher_shared, &bbox);
+                Self::pointcloud_callback(msg, &tracker, &detection_publisher_shared, &bbox);
             },
         )?;
 
@@ -117,14 +119,17 @@ impl CalibrationBoardLocatorNode {
         Self::load_json5_file(&path)
     }
 
-    fn load_bbox_config(file_path: &str) -> Result<BBox> {
+    fn load_bbox_config(file_path: &str) -> Result<Option<BBox>> {
         if file_path.is_empty() {
-            log_info!(LOGGER_NAME, "Using default bounding box configuration");
-            return Ok(BBox::default());
+            log_info!(
+                LOGGER_NAME,
+                "No bounding box is given. Board candidates are proposed automatically"
+            );
+            return Ok(None);
         }
 
         let path = PathBuf::from(file_path);
-        Self::load_json5_file(&path)
+        Self::load_json5_file(&path).map(Some)
     }
 
     fn load_json5_file<T>(path: &PathBuf) -> Result<T>
@@ -138,11 +143,11 @@ impl CalibrationBoardLocatorNode {
 
     fn pointcloud_callback(
         msg: PointCloud2,
-        detector: &Arc<BoardDetector>,
+        tracker: &Arc<Mutex<BoardTracker>>,
         publisher: &Publisher<Detection3DArray>,
-        bbox: &Arc<Mutex<BBox>>,
+        bbox: &Arc<Mutex<Option<BBox>>>,
     ) {
-        let result = Self::process_pointcloud(&msg, detector, bbox);
+        let result = Self::process_pointcloud(&msg, tracker, bbox);
 
         let detection_array = match result {
             Ok(detection_array) => detection_array,
@@ -159,40 +164,59 @@ impl CalibrationBoardLocatorNode {
 
     fn process_pointcloud(
         msg: &PointCloud2,
-        detector: &Arc<BoardDetector>,
-        bbox: &Arc<Mutex<BBox>>,
+        tracker: &Arc<Mutex<BoardTracker>>,
+        bbox: &Arc<Mutex<Option<BBox>>>,
     ) -> Result<Detection3DArray> {
         // Convert PointCloud2 to nalgebra points
         let points = Self::convert_pointcloud2_to_points(msg)?;
 
-        // Filter points using bbox
+        let mut tracker_guard = tracker
+            .lock()
+            .map_err(|e| anyhow!("Failed to lock tracker: {e}"))?;
         let bbox_guard = bbox
             .lock()
             .map_err(|e| anyhow!("Failed to lock bbox: {e}"))?;
-        let active_points: Vec<_> = points
-            .iter()
-            .filter(|pt| bbox_guard.contains_point(pt))
-            .cloned()
-            .collect();
-        drop(bbox_guard);
 
-        if active_points.is_empty() {
-            log_warn!(LOGGER_NAME, "No points within bounding box");
-            return Ok(Detection3DArray {
-                header: msg.header.clone(),
-                detections: Vec::new(),
-            });
-        }
+        // Detect calibration boards starting from the previous poses.
+        // Points are cropped by the bbox, or the board candidates are
+        // proposed and tried one by one if no bbox is given.
+        let result = match &*bbox_guard {
+            Some(bbox) => {
+                let active_points: Vec<_> = points
+                    .iter()
+                    .filter(|pt| bbox.contains_point(pt))
+                    .cloned()
+                    .collect();
+
+                if active_points.is_empty() {
+                    log_warn!(LOGGER_NAME, "No points within bounding box");
+                    return Ok(Detection3DArray {
+                        header: msg.header.clone(),
+                        detections: Vec::new(),
+                    });
+                }
+                tracker_guard.track(&active_points)
+            }
+            None => tracker_guard.track_proposed(&points),
+        };
+        drop(bbox_guard);
 
-        // Detect calibration board
-        let detection: Option<BoardDetection> = detector.detect(&active_points)?;
+        let tracked_boards: Vec<TrackedBoard> = match result {
+            Ok(tracked_boards) => tracked_boards,
+            Err(failure) => {
+                log_warn!(LOGGER_NAME, "No calibration board detected: {failure}");
+                vec![]
+            }
+        };
+        drop(tracker_guard);
 
-        let mut detections = Vec::new();
-        if let Some(board_detection) = detection {
-            let detection_3d =
-                Self::convert_board_detection_to_detection3d(&board_detection, &msg.header)?;
-            detections.push(detection_3d);
-        }
+        let detections = tracked_boards
+            .iter()
+            .enumerate()
+            .map(|(index, tracked_board)| {
+                Self::convert_board_detection_to_detection3d(tracked_board, index, &msg.header)
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(Detection3DArray {
             header: msg.header.clone(),
@@ -216,24 +240,25 @@ impl CalibrationBoardLocatorNode {
     }
 
     fn convert_board_detection_to_detection3d(
-        board_detection: &BoardDetection,
+        tracked_board: &TrackedBoard,
+        index: usize,
         header: &std_msgs::msg::Header,
     ) -> Result<Detection3D> {
-        // Extract pose information from board detection
-        let board_model = &board_detection.board_model;
+        let board_detection = &tracked_board.detection;
 
-        // Create pose from board model pose
+        // Create pose from the smoothed board pose
+        let board_pose = &tracked_board.smoothed_pose;
         let pose = Pose {
             position: Point {
-                x: board_model.pose.translation.x,
-                y: board_model.pose.translation.y,
-                z: board_model.pose.translation.z,
+                x: board_pose.translation.x,
+                y: board_pose.translation.y,
+                z: board_pose.translation.z,
             },
             orientation: Quaternion {
-                x: board_model.pose.rotation.i,
-                y: board_model.pose.rotation.j,
-                z: board_model.pose.rotation.k,
-                w: board_model.pose.rotation.w,
+                x: board_pose.rotation.i,
+                y: board_pose.rotation.j,
+                z: board_pose.rotation.k,
+                w: board_pose.rotation.w,
             },
         };
 
@@ -248,23 +273,37 @@ impl CalibrationBoardLocatorNode {
             },
         };
 
+        // Row-major covariance. The first element is set to -1 if the
+        // covariance is unknown.
+        let mut covariance = [0.0; 36];
+        match &board_detection.pose_covariance {
+            Some(pose_covariance) => {
+                covariance.copy_from_slice(pose_covariance.transpose().as_slice());
+            }
+            None => covariance[0] = -1.0,
+        }
+
         // Create object hypothesis
         let hypothesis = ObjectHypothesisWithPose {
             hypothesis: vision_msgs::msg::ObjectHypothesis {
                 class_id: "calibration_board".to_string(),
-                score: 1.0, // Confidence score
-            },
-            pose: PoseWithCovariance {
-                pose,
-                covariance: [0.0; 36], // Zero covariance for now
+                score: board_detection.score,
             },
+            pose: PoseWithCovariance { pose, covariance },
+        };
+
+        // The first board keeps the ID used before multiple boards were
+        // supported.
+        let id = match index {
+            0 => "calibration_board".to_string(),
+            _ => format!("calibration_board_{index}"),
         };
 
         Ok(Detection3D {
             header: header.clone(),
             results: vec![hypothesis],
             bbox,
-            id: "calibration_board".to_string(),
+            id,
         })
     }
 }
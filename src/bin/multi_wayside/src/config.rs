@@ -18,7 +18,18 @@ pub struct PcapConfig {
     pub sensor: SensorType,
     pub file_path: AbsPathBuf,
     pub frame_selected: Option<usize>,
-    pub filter: generic_point_filter::Filter,
+    /// The filter that crops the points around the board. The board
+    /// candidates are proposed automatically if it is not set.
+    #[serde(default)]
+    pub filter: Option<generic_point_filter::Filter>,
+    /// The number of frames starting from the selected frame whose
+    /// points are accumulated for detection. It detects on the selected
+    /// frame only if it is not set.
+    #[serde(default)]
+    pub accumulate_frames: Option<usize>,
+    /// The voxel size in meters to merge accumulated points.
+    #[serde(default)]
+    pub voxel_size: Option<f64>,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize)]
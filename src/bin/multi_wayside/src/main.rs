@@ -3,12 +3,15 @@ mod fuse_gui;
 mod select_gui;
 mod utils;
 
-use crate::{config::Config, utils::p32_to_p30_vec};
+use crate::{
+    config::{Config, PcapConfig},
+    utils::p32_to_p30_vec,
+};
 use anyhow::{anyhow, Context, Result};
 use chrono::{offset::Local, DateTime};
 use clap::Parser;
 use generic_point_filter::Pt64;
-use hollow_board_detector::Detection;
+use hollow_board_detector::{BoardTracker, Detection, FrameAccumulator};
 use kiss3d::{
     camera::ArcBall,
     event::Key,
@@ -59,6 +62,7 @@ fn main() -> Result<()> {
     let opts = Opts::parse();
 
     let config: Config = Json5Path::open_and_take(&opts.config)?;
+    config.board_detector.validate(&config.aruco_pattern)?;
 
     eprintln!("Start detection on pcap file 1 ...");
     let res_1 = pcap_to_pose(&config, PcapNumber::First);
@@ -122,10 +126,10 @@ fn pcap_to_pose(config: &Config, pcap_number: PcapNumber) -> Result<ResultStruct
         }
     };
 
-    let board_detector = hollow_board_detector::Detector::new(
+    let mut board_tracker = BoardTracker::new(hollow_board_detector::Detector::new(
         (*config.board_detector).clone(),
         (*config.aruco_pattern).clone(),
-    );
+    ));
 
     let mut gui = select_gui::Gui::new(pcap_config.clone());
     let mut filtered_points = vec![];
@@ -151,15 +155,23 @@ fn pcap_to_pose(config: &Config, pcap_number: PcapNumber) -> Result<ResultStruct
             })
             .unzip_n_vec();
 
-        filtered_points = preprocess_points(&points_in_lidar_point_format, &pcap_config.filter);
+        // collect following frames to accumulate points on the static board
+        let num_frames = pcap_config.accumulate_frames.unwrap_or(1).max(1);
+        let mut frames = vec![points_in_lidar_point_format.clone()];
+        for frame in frame_iter.take(num_frames - 1) {
+            let points: Vec<LidarPoint> = frame?
+                .into_indexed_point_iter()
+                .map(|((row, col), point)| transform_point(row, col, point))
+                .collect();
+            frames.push(points);
+        }
 
-        // transform Vec<LidarPoint> into Vec<na::Point3>
-        let points_in_point3_format: Vec<na::Point3<f64>> = filtered_points
-            .iter()
-            .map(|point| point.xyz.into())
-            .collect();
+        filtered_points =
+            preprocess_points(&points_in_lidar_point_format, pcap_config.filter.as_ref());
+
+        let points_in_point3_format = accumulate_points(&frames, pcap_config);
 
-        let det = board_detector.detect(&points_in_point3_format)?;
+        let det = detect_board(&mut board_tracker, &points_in_point3_format, pcap_config);
 
         let mut window = Window::new_with_size(
             &format!("detection_result_with_frame{}", frame_selected),
@@ -186,13 +198,9 @@ fn pcap_to_pose(config: &Config, pcap_number: PcapNumber) -> Result<ResultStruct
                     PcapNumber::Second => &config.pcap2_config,
                 };
                 filtered_points =
-                    preprocess_points(&points_in_lidar_point_format, &pcap_config.filter);
-                // transform Vec<LidarPoint> into Vec<na::Point3>
-                let points_in_point3_format: Vec<na::Point3<f64>> = filtered_points
-                    .iter()
-                    .map(|point| point.xyz.into())
-                    .collect();
-                let det = board_detector.detect(&points_in_point3_format)?;
+                    preprocess_points(&points_in_lidar_point_format, pcap_config.filter.as_ref());
+                let points_in_point3_format = accumulate_points(&frames, pcap_config);
+                let det = detect_board(&mut board_tracker, &points_in_point3_format, pcap_config);
                 gui.det = det;
                 gui.pcap_config = pcap_config.clone();
                 gui.points_in_point3_format = p32_to_p30_vec(&points_in_point3_format);
@@ -219,10 +227,54 @@ fn pcap_to_pose(config: &Config, pcap_number: PcapNumber) -> Result<ResultStruct
     Ok(result)
 }
 
+/// Filters the points of each frame and merges them using a
+/// [FrameAccumulator].
+fn accumulate_points(frames: &[Vec<LidarPoint>], pcap_config: &PcapConfig) -> Vec<na::Point3<f64>> {
+    let mut accumulator = FrameAccumulator::new(frames.len(), pcap_config.voxel_size);
+    for frame in frames {
+        // transform Vec<LidarPoint> into Vec<na::Point3>
+        let points: Vec<na::Point3<f64>> = preprocess_points(frame, pcap_config.filter.as_ref())
+            .iter()
+            .map(|point| point.xyz.into())
+            .collect();
+        accumulator.push(&points);
+    }
+    accumulator.points()
+}
+
+/// Detects the board starting from the previous detection, and
+/// reports the failure reason if not found. The board candidates are
+/// proposed automatically if the points are not filtered.
+fn detect_board(
+    board_tracker: &mut BoardTracker,
+    points: &[na::Point3<f64>],
+    pcap_config: &PcapConfig,
+) -> Option<Detection> {
+    let result = match pcap_config.filter {
+        Some(_) => board_tracker.track(points),
+        None => board_tracker.track_proposed(points),
+    };
+
+    match result {
+        Ok(boards) => boards.into_iter().next().map(|board| board.detection),
+        Err(failure) => {
+            eprintln!("No board detected: {failure}");
+            None
+        }
+    }
+}
+
+/// Keeps the points within the filter, or all points if no filter is
+/// given.
 fn preprocess_points(
     points: &[LidarPoint],
-    filter: &generic_point_filter::Filter,
+    filter: Option<&generic_point_filter::Filter>,
 ) -> Vec<LidarPoint> {
+    let filter = match filter {
+        Some(filter) => filter,
+        None => return points.to_vec(),
+    };
+
     let points: Vec<_> = points
         .iter()
         .cloned()
@@ -111,7 +111,9 @@ impl State for Gui {
         );
 
         // draw filter
-        self.pcap_config.filter.render_kiss3d(window);
+        if let Some(filter) = &self.pcap_config.filter {
+            filter.render_kiss3d(window);
+        }
 
         // draw detection
         if let Some(ref val) = &self.det {
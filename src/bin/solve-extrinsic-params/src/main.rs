@@ -1,13 +1,14 @@
-use anyhow::{ensure, Context};
-use aruco_config::MultiArucoPattern;
+use anyhow::{anyhow, ensure, Context};
+use aruco_config::{CalibrationPattern, MultiArucoPattern, PatternGeometry};
 use aruco_detector::multi_aruco::ImageMarker;
 use clap::Parser;
 use cv_convert::prelude::*;
-use hollow_board_config::BoardModel;
+use hollow_board_config::{validate_calibration_pattern, BoardModel};
 use itertools::{izip, Itertools};
 use once_cell::sync::Lazy;
 use opencv::core::{Point2d, Point2f, Point3d};
 use pnp_solver::{PnpMethod, PnpSolver};
+use serde::Deserialize;
 use serde_types::{CameraIntrinsics, DistortionCoefs, Isometry3D, MrptCalibration};
 use std::{
     borrow::{Cow, Cow::*},
@@ -15,14 +16,31 @@ use std::{
     path::PathBuf,
 };
 
-static DEFAULT_ARUCO_PATTERN: Lazy<MultiArucoPattern> = Lazy::new(|| {
+static DEFAULT_ARUCO_PATTERN: Lazy<CalibrationPattern> = Lazy::new(|| {
     let text = include_str!(concat!(
         env!("CARGO_MANIFEST_DIR"),
         "/config/aruco_pattern.json5"
     ));
-    json5::from_str(text).unwrap()
+    load_pattern(text).unwrap()
 });
 
+/// A calibration pattern file. Files without the `kind` field are
+/// multi-ArUco patterns written before other pattern kinds existed.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PatternFile {
+    Tagged(CalibrationPattern),
+    MultiAruco(MultiArucoPattern),
+}
+
+fn load_pattern(text: &str) -> Result<CalibrationPattern, anyhow::Error> {
+    let pattern = match json5::from_str(text)? {
+        PatternFile::Tagged(pattern) => pattern,
+        PatternFile::MultiAruco(pattern) => CalibrationPattern::MultiAruco(pattern),
+    };
+    Ok(pattern)
+}
+
 #[derive(Debug, Parser)]
 struct Opts {
     /// The file storing intrinsic camera parameters.
@@ -44,7 +62,9 @@ struct Opts {
     #[clap(long, value_delimiter(','))]
     pub arucos: Vec<PathBuf>,
 
-    /// The file that describes the ArUco marker pattern.
+    /// The file that describes the calibration pattern. The pattern kind
+    /// is selected by the `kind` field, and files without it are read
+    /// as multi-ArUco patterns.
     #[clap(long)]
     pub aruco_pattern_file: Option<PathBuf>,
 
@@ -76,15 +96,17 @@ fn main() -> Result<(), anyhow::Error> {
         boards.len(),
         arucos.len()
     );
-    let aruco_pattern: Cow<'_, MultiArucoPattern> = match &aruco_pattern_file {
+    let aruco_pattern: Cow<'_, CalibrationPattern> = match &aruco_pattern_file {
         Some(path) => {
             let text = fs::read_to_string(path)
                 .with_context(|| format!("unable to open file '{}'", path.display()))?;
-            let pattern = json5::from_str(&text)?;
+            let pattern = load_pattern(&text)
+                .with_context(|| format!("invalid pattern file '{}'", path.display()))?;
             Owned(pattern)
         }
         None => Borrowed(&*DEFAULT_ARUCO_PATTERN),
     };
+    validate_calibration_pattern(&aruco_pattern)?;
     let mrpt_calib: MrptCalibration = {
         let yaml_text = fs::read_to_string(&intrinsics_file)
             .with_context(|| format!("unable to open file '{}'", intrinsics_file.display()))?;
@@ -104,6 +126,9 @@ fn main() -> Result<(), anyhow::Error> {
                     .with_context(|| format!("unable to open file '{}'", board_file.display()))?;
                 json5::from_str(&json5_text)?
             };
+            board
+                .validate_pattern(&*aruco_pattern)
+                .with_context(|| format!("invalid board file '{}'", board_file.display()))?;
             let markers: Vec<ImageMarker> = {
                 let json5_text = fs::read_to_string(&aruco_file)
                     .with_context(|| format!("unable to open file '{}'", aruco_file.display()))?;
@@ -116,33 +141,32 @@ fn main() -> Result<(), anyhow::Error> {
     let point_pairs: Vec<(Point3d, Point2d)> = detection_pairs
         .into_iter()
         .map(|(board, markers)| {
-            let object_points: Vec<Point3d> = board
-                .multi_marker_corners(&aruco_pattern)
-                .into_iter()
-                .flatten()
-                .map(|p| -> Point3d { p.to_cv() })
-                .collect();
-            let image_points: Vec<Point2d> = markers
+            // the marker corners are ordered like the marker IDs in the
+            // pattern, and image markers are matched by their IDs
+            let marker_ids = aruco_pattern.marker_ids();
+            let marker_corners = board.pattern_marker_corners(&*aruco_pattern);
+
+            let point_pairs: Vec<(Point3d, Point2d)> = markers
                 .into_iter()
-                .flat_map(|marker| {
-                    let corners: Vec<_> = marker
-                        .corners
+                .map(|marker| {
+                    let index = marker_ids
                         .iter()
-                        .map(|corner| {
-                            let point2f: Point2f = corner.to_cv();
-                            let point2d: Point2d = point2f.to().unwrap();
-                            point2d
-                        })
-                        .collect();
-                    corners
+                        .position(|&id| id as i32 == marker.id)
+                        .ok_or_else(|| {
+                            anyhow!("marker {} is not in the calibration pattern", marker.id)
+                        })?;
+                    let object_points = marker_corners[index]
+                        .iter()
+                        .map(|p| -> Point3d { p.to_cv() });
+                    let image_points = marker.corners.iter().map(|corner| {
+                        let point2f: Point2f = corner.to_cv();
+                        let point2d: Point2d = point2f.to().unwrap();
+                        point2d
+                    });
+                    anyhow::Ok(izip!(object_points, image_points).collect_vec())
                 })
-                .collect();
-
-            ensure!(
-                object_points.len() == image_points.len(),
-                "the number of object points does not match the number of image points"
-            );
-            let point_pairs = izip!(object_points, image_points);
+                .flatten_ok()
+                .try_collect()?;
             anyhow::Ok(point_pairs)
         })
         .flatten_ok()
@@ -1,5 +1,7 @@
 mod dictionary;
 mod multi_aruco;
+mod pattern;
 
 pub use dictionary::*;
 pub use multi_aruco::*;
+pub use pattern::*;
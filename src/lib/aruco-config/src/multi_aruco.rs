@@ -13,27 +13,92 @@ pub struct MultiArucoPattern {
     pub board_border_size: Length,
     pub marker_square_size_ratio: R64,
     pub num_squares_per_side: u32,
+    /// The numbers of marker rows and columns of a rectangular grid.
+    /// The grid has `num_squares_per_side` squares on each side if it
+    /// is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marker_grid: Option<MarkerGrid>,
     pub border_bits: u32,
 }
 
+/// The numbers of rows and columns of a marker grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkerGrid {
+    pub rows: u32,
+    pub cols: u32,
+}
+
 impl MultiArucoPattern {
     pub fn paper_size(&self) -> Length {
         self.board_size
     }
 
+    /// The number of marker rows. Rows are stacked along the y axis of
+    /// the paper.
+    pub fn num_rows(&self) -> u32 {
+        match self.marker_grid {
+            Some(grid) => grid.rows,
+            None => self.num_squares_per_side,
+        }
+    }
+
+    /// The number of marker columns. Columns are stacked along the x
+    /// axis of the paper.
+    pub fn num_cols(&self) -> u32 {
+        match self.marker_grid {
+            Some(grid) => grid.cols,
+            None => self.num_squares_per_side,
+        }
+    }
+
+    pub fn num_markers(&self) -> usize {
+        self.num_rows() as usize * self.num_cols() as usize
+    }
+
+    /// The row and the column of the marker at an index of
+    /// `marker_ids`. Markers are ordered row by row.
+    pub fn marker_grid_position(&self, index: usize) -> (usize, usize) {
+        let num_cols = self.num_cols() as usize;
+        (index / num_cols, index % num_cols)
+    }
+
+    /// The square size, with which the longer side of the grid spans
+    /// the paper within the border. A rectangular grid leaves blank
+    /// strips along the shorter side, see [Self::square_origin].
     pub fn square_size(&self) -> Length {
         let Self {
             board_size,
             board_border_size,
-            num_squares_per_side,
             ..
         } = *self;
-        (board_size - board_border_size * 2.0) / num_squares_per_side as f64
+        let num_squares = self.num_rows().max(self.num_cols());
+        (board_size - board_border_size * 2.0) / num_squares as f64
     }
 
     pub fn marker_size(&self) -> Length {
         self.square_size() * self.marker_square_size_ratio.raw()
     }
+
+    /// The corner of the square at a grid position that is the closest
+    /// to the paper origin. The grid is centered on the paper, so a
+    /// rectangular grid leaves blank strips of equal width at both ends
+    /// of its shorter side.
+    pub fn square_origin(&self, row: usize, col: usize) -> [Length; 2] {
+        let square_size = self.square_size();
+        let num_squares = self.num_rows().max(self.num_cols());
+        let origin = |num: u32, index: usize| {
+            self.board_border_size + square_size * ((num_squares - num) as f64 / 2.0 + index as f64)
+        };
+        [origin(self.num_cols(), col), origin(self.num_rows(), row)]
+    }
+
+    /// The center of the square at a grid position relative to the
+    /// paper center, which is also the grid center.
+    pub fn square_center_offset(&self, row: usize, col: usize) -> [Length; 2] {
+        let [x, y] = self.square_origin(row, col);
+        let offset = self.square_size() / 2.0 - self.board_size / 2.0;
+        [x + offset, y + offset]
+    }
 }
 
 #[cfg(feature = "with-opencv")]
@@ -51,8 +116,6 @@ mod with_opencv {
                 ref marker_ids,
                 dictionary,
                 board_size,
-                board_border_size,
-                num_squares_per_side,
                 border_bits,
                 ..
             } = *self;
@@ -61,7 +124,6 @@ mod with_opencv {
             let marker_size_pixels = (self.marker_size().as_inches() * dpi) as i32;
             let square_size_pixels = (self.square_size().as_inches() * dpi) as i32;
             let board_size_pixels = (board_size.as_inches() * dpi) as i32;
-            let board_border_size_pixels = (board_border_size.as_inches() * dpi) as i32;
 
             let init_image = Mat::new_rows_cols_with_default(
                 board_size_pixels,
@@ -74,14 +136,11 @@ mod with_opencv {
                 .iter()
                 .enumerate()
                 .map(|(index, &marker_id)| -> Result<_> {
-                    let row = index / num_squares_per_side as usize;
-                    let col = index % num_squares_per_side as usize;
-                    let x_offset = board_border_size_pixels
-                        + square_size_pixels * col as i32
-                        + (square_size_pixels - marker_size_pixels) / 2;
-                    let y_offset = board_border_size_pixels
-                        + square_size_pixels * row as i32
-                        + (square_size_pixels - marker_size_pixels) / 2;
+                    let (row, col) = self.marker_grid_position(index);
+                    let [x, y] = self.square_origin(row, col);
+                    let marker_border_pixels = (square_size_pixels - marker_size_pixels) / 2;
+                    let x_offset = (x.as_inches() * dpi) as i32 + marker_border_pixels;
+                    let y_offset = (y.as_inches() * dpi) as i32 + marker_border_pixels;
 
                     let mut marker_image = Mat::default();
                     opencv_dictionary.draw_marker(
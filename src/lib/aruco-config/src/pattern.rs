@@ -0,0 +1,305 @@
+//! Calibration pattern kinds and their geometry.
+//!
+//! A pattern is printed on a square paper. The paper frame has its
+//! origin at a paper corner, the x axis along the grid columns and the
+//! y axis along the grid rows, and the paper lies on the z = 0 plane.
+//! All points returned by [PatternGeometry] are in meters.
+
+use crate::{ArucoDictionary, MarkerGrid, MultiArucoPattern};
+use measurements::Length;
+use nalgebra as na;
+use noisy_float::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+/// The geometry of a printed calibration pattern.
+pub trait PatternGeometry: Debug {
+    /// The dictionary of the markers.
+    fn dictionary(&self) -> ArucoDictionary;
+
+    /// The side length of the paper, including the white border.
+    fn paper_size(&self) -> Length;
+
+    /// The pitch of the grid on which the markers are placed.
+    fn square_size(&self) -> Length;
+
+    /// The side length of each marker.
+    fn marker_size(&self) -> Length;
+
+    /// The marker IDs in the order of [PatternGeometry::marker_corners].
+    fn marker_ids(&self) -> Vec<u32>;
+
+    /// The corners of each marker in the paper frame. The corners of
+    /// a marker at `(x, y)` with size `s` are ordered as `(x, y + s)`,
+    /// `(x + s, y + s)`, `(x + s, y)` and `(x, y)`.
+    fn marker_corners(&self) -> Vec<[na::Point3<f64>; 4]>;
+
+    /// The points located with sub-pixel accuracy on images, which
+    /// are the marker corners unless the pattern has other features.
+    fn feature_points(&self) -> Vec<na::Point3<f64>> {
+        self.marker_corners().into_iter().flatten().collect()
+    }
+}
+
+/// A ChArUco board, which is a chessboard with markers in its white
+/// squares.
+///
+/// Like OpenCV, the square at the paper origin is black, and the
+/// markers take consecutive IDs row by row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CharucoPattern {
+    pub dictionary: ArucoDictionary,
+    /// The numbers of rows and columns of chessboard squares.
+    pub squares: MarkerGrid,
+    /// The side length of the paper, including the white border.
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub paper_size: Length,
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub border_size: Length,
+    pub marker_square_size_ratio: R64,
+    /// The ID of the first marker.
+    #[serde(default)]
+    pub first_marker_id: u32,
+    pub border_bits: u32,
+}
+
+impl CharucoPattern {
+    /// The row and the column of each white square, row by row.
+    fn white_squares(&self) -> impl Iterator<Item = (u32, u32)> {
+        let MarkerGrid { rows, cols } = self.squares;
+        (0..rows)
+            .flat_map(move |row| (0..cols).map(move |col| (row, col)))
+            .filter(|(row, col)| (row + col) % 2 == 1)
+    }
+}
+
+impl PatternGeometry for CharucoPattern {
+    fn dictionary(&self) -> ArucoDictionary {
+        self.dictionary
+    }
+
+    fn paper_size(&self) -> Length {
+        self.paper_size
+    }
+
+    fn square_size(&self) -> Length {
+        let num_squares = self.squares.rows.max(self.squares.cols);
+        (self.paper_size - self.border_size * 2.0) / num_squares as f64
+    }
+
+    fn marker_size(&self) -> Length {
+        self.square_size() * self.marker_square_size_ratio.raw()
+    }
+
+    fn marker_ids(&self) -> Vec<u32> {
+        let num_markers = self.white_squares().count() as u32;
+        (self.first_marker_id..self.first_marker_id + num_markers).collect()
+    }
+
+    fn marker_corners(&self) -> Vec<[na::Point3<f64>; 4]> {
+        let square_size = self.square_size().as_meters();
+        let marker_size = self.marker_size().as_meters();
+        let origin = self.border_size.as_meters() + (square_size - marker_size) / 2.0;
+
+        self.white_squares()
+            .map(|(row, col)| {
+                square_corners(
+                    origin + square_size * col as f64,
+                    origin + square_size * row as f64,
+                    marker_size,
+                )
+            })
+            .collect()
+    }
+
+    /// The inner chessboard corners, row by row, in the order of
+    /// ChArUco corner IDs.
+    fn feature_points(&self) -> Vec<na::Point3<f64>> {
+        let MarkerGrid { rows, cols } = self.squares;
+        let square_size = self.square_size().as_meters();
+        let border_size = self.border_size.as_meters();
+
+        (1..rows)
+            .flat_map(|row| {
+                (1..cols).map(move |col| {
+                    na::Point3::new(
+                        border_size + square_size * col as f64,
+                        border_size + square_size * row as f64,
+                        0.0,
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// An AprilGrid as used by Kalibr, which is a grid of tags with small
+/// black squares at the gaps between tag corners.
+///
+/// The tags take consecutive IDs row by row. The paper size follows
+/// from the tag size, the spacing and the border.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AprilGridPattern {
+    /// The tag family, which is usually `DICT_APRILTAG_36h11`.
+    pub dictionary: ArucoDictionary,
+    /// The numbers of tag rows and columns.
+    pub tag_grid: MarkerGrid,
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub tag_size: Length,
+    /// The gap between adjacent tags as a ratio to the tag size.
+    pub tag_spacing_ratio: R64,
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub border_size: Length,
+    /// The ID of the first tag.
+    #[serde(default)]
+    pub first_tag_id: u32,
+}
+
+impl AprilGridPattern {
+    fn tag_spacing(&self) -> Length {
+        self.tag_size * self.tag_spacing_ratio.raw()
+    }
+}
+
+impl PatternGeometry for AprilGridPattern {
+    fn dictionary(&self) -> ArucoDictionary {
+        self.dictionary
+    }
+
+    fn paper_size(&self) -> Length {
+        let num_tags = self.tag_grid.rows.max(self.tag_grid.cols);
+        self.border_size * 2.0 + self.tag_spacing() + self.square_size() * num_tags as f64
+    }
+
+    fn square_size(&self) -> Length {
+        self.tag_size + self.tag_spacing()
+    }
+
+    fn marker_size(&self) -> Length {
+        self.tag_size
+    }
+
+    fn marker_ids(&self) -> Vec<u32> {
+        let num_tags = self.tag_grid.rows * self.tag_grid.cols;
+        (self.first_tag_id..self.first_tag_id + num_tags).collect()
+    }
+
+    fn marker_corners(&self) -> Vec<[na::Point3<f64>; 4]> {
+        let MarkerGrid { rows, cols } = self.tag_grid;
+        let square_size = self.square_size().as_meters();
+        let tag_size = self.tag_size.as_meters();
+        let origin = (self.border_size + self.tag_spacing()).as_meters();
+
+        (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                square_corners(
+                    origin + square_size * col as f64,
+                    origin + square_size * row as f64,
+                    tag_size,
+                )
+            })
+            .collect()
+    }
+}
+
+impl PatternGeometry for MultiArucoPattern {
+    fn dictionary(&self) -> ArucoDictionary {
+        self.dictionary
+    }
+
+    fn paper_size(&self) -> Length {
+        MultiArucoPattern::paper_size(self)
+    }
+
+    fn square_size(&self) -> Length {
+        MultiArucoPattern::square_size(self)
+    }
+
+    fn marker_size(&self) -> Length {
+        MultiArucoPattern::marker_size(self)
+    }
+
+    fn marker_ids(&self) -> Vec<u32> {
+        self.marker_ids.clone()
+    }
+
+    fn marker_corners(&self) -> Vec<[na::Point3<f64>; 4]> {
+        let square_size = MultiArucoPattern::square_size(self).as_meters();
+        let marker_size = MultiArucoPattern::marker_size(self).as_meters();
+        let marker_border = (square_size - marker_size) / 2.0;
+
+        (0..self.num_markers())
+            .map(|index| {
+                let (row, col) = self.marker_grid_position(index);
+                let [x, y] = self.square_origin(row, col);
+                square_corners(
+                    x.as_meters() + marker_border,
+                    y.as_meters() + marker_border,
+                    marker_size,
+                )
+            })
+            .collect()
+    }
+}
+
+/// One of the supported calibration patterns, selected by the `kind`
+/// field in configuration files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CalibrationPattern {
+    MultiAruco(MultiArucoPattern),
+    Charuco(CharucoPattern),
+    AprilGrid(AprilGridPattern),
+}
+
+impl CalibrationPattern {
+    fn geometry(&self) -> &dyn PatternGeometry {
+        match self {
+            Self::MultiAruco(pattern) => pattern,
+            Self::Charuco(pattern) => pattern,
+            Self::AprilGrid(pattern) => pattern,
+        }
+    }
+}
+
+impl PatternGeometry for CalibrationPattern {
+    fn dictionary(&self) -> ArucoDictionary {
+        self.geometry().dictionary()
+    }
+
+    fn paper_size(&self) -> Length {
+        self.geometry().paper_size()
+    }
+
+    fn square_size(&self) -> Length {
+        self.geometry().square_size()
+    }
+
+    fn marker_size(&self) -> Length {
+        self.geometry().marker_size()
+    }
+
+    fn marker_ids(&self) -> Vec<u32> {
+        self.geometry().marker_ids()
+    }
+
+    fn marker_corners(&self) -> Vec<[na::Point3<f64>; 4]> {
+        self.geometry().marker_corners()
+    }
+
+    fn feature_points(&self) -> Vec<na::Point3<f64>> {
+        self.geometry().feature_points()
+    }
+}
+
+/// The corners of a square at `(x, y)` in the order of
+/// [PatternGeometry::marker_corners].
+fn square_corners(x: f64, y: f64, size: f64) -> [na::Point3<f64>; 4] {
+    [
+        na::Point3::new(x, y + size, 0.0),
+        na::Point3::new(x + size, y + size, 0.0),
+        na::Point3::new(x + size, y, 0.0),
+        na::Point3::new(x, y, 0.0),
+    ]
+}
@@ -0,0 +1,130 @@
+use aruco_config::{
+    ArucoDictionary, CalibrationPattern, MarkerGrid, MultiArucoPattern, PatternGeometry,
+};
+use measurements::Length;
+use nalgebra as na;
+use noisy_float::prelude::*;
+
+fn assert_near(point: &na::Point3<f64>, [x, y]: [f64; 2]) {
+    let expected = na::Point3::new(x, y, 0.0);
+    assert!((point - expected).norm() < 1e-9, "{point} != {expected}");
+}
+
+fn multi_aruco_pattern() -> MultiArucoPattern {
+    MultiArucoPattern {
+        marker_ids: vec![696, 64, 306, 195],
+        dictionary: ArucoDictionary::DICT_5X5_1000,
+        board_size: Length::from_millimeters(500.0),
+        board_border_size: Length::from_millimeters(10.0),
+        marker_square_size_ratio: r64(0.8),
+        num_squares_per_side: 2,
+        marker_grid: None,
+        border_bits: 1,
+    }
+}
+
+#[test]
+fn multi_aruco_layout() {
+    // a 2x2 grid with 240mm squares and 192mm markers
+    let pattern = CalibrationPattern::MultiAruco(multi_aruco_pattern());
+    assert!((pattern.square_size().as_meters() - 0.24).abs() < 1e-9);
+    assert!((pattern.marker_size().as_meters() - 0.192).abs() < 1e-9);
+    assert_eq!(pattern.marker_ids(), [696, 64, 306, 195]);
+
+    let corners = pattern.marker_corners();
+    assert_eq!(corners.len(), 4);
+    // the corners are ordered as (x, y + s), (x + s, y + s), (x + s, y)
+    // and (x, y)
+    assert_near(&corners[0][0], [0.034, 0.226]);
+    assert_near(&corners[0][1], [0.226, 0.226]);
+    assert_near(&corners[0][2], [0.226, 0.034]);
+    assert_near(&corners[0][3], [0.034, 0.034]);
+    // markers are ordered row by row
+    assert_near(&corners[1][3], [0.274, 0.034]);
+    assert_near(&corners[2][3], [0.034, 0.274]);
+    assert_near(&corners[3][1], [0.466, 0.466]);
+    assert_eq!(pattern.feature_points().len(), 16);
+}
+
+#[test]
+fn multi_aruco_grid_is_centered() {
+    // a 2x3 grid has 160mm squares, and the two rows leave 80mm blank
+    // strips at both ends of the columns
+    let pattern = MultiArucoPattern {
+        marker_ids: (0..6).collect(),
+        marker_grid: Some(MarkerGrid { rows: 2, cols: 3 }),
+        ..multi_aruco_pattern()
+    };
+    let [x, y] = pattern.square_origin(0, 0);
+    assert!((x.as_meters() - 0.01).abs() < 1e-9);
+    assert!((y.as_meters() - 0.09).abs() < 1e-9);
+
+    // the square centers are symmetric about the paper center
+    let [x, y] = pattern.square_center_offset(0, 0);
+    assert!((x.as_meters() + 0.16).abs() < 1e-9);
+    assert!((y.as_meters() + 0.08).abs() < 1e-9);
+    let [x, y] = pattern.square_center_offset(1, 2);
+    assert!((x.as_meters() - 0.16).abs() < 1e-9);
+    assert!((y.as_meters() - 0.08).abs() < 1e-9);
+
+    let corners = pattern.marker_corners();
+    assert_near(&corners[0][3], [0.026, 0.106]);
+    assert_near(&corners[5][1], [0.474, 0.394]);
+}
+
+#[test]
+fn charuco_layout() {
+    // a 5x5 ChArUco board has 96mm squares, 12 markers in the white
+    // squares and 4x4 inner chessboard corners
+    let charuco: CalibrationPattern = json5::from_str(
+        r#"{
+            "kind": "charuco",
+            "dictionary": "DICT_5X5_1000",
+            "squares": { "rows": 5, "cols": 5 },
+            "paper_size": "500mm",
+            "border_size": "10mm",
+            "marker_square_size_ratio": 0.75,
+            "border_bits": 1,
+        }"#,
+    )
+    .unwrap();
+    assert!((charuco.square_size().as_meters() - 0.096).abs() < 1e-9);
+    assert_eq!(charuco.marker_ids(), (0..12).collect::<Vec<_>>());
+
+    let corners = charuco.marker_corners();
+    assert_eq!(corners.len(), 12);
+    // the first marker is in the second square of the first row
+    assert_near(&corners[0][3], [0.01 + 0.096 + 0.012, 0.01 + 0.012]);
+    assert_near(&corners[0][1], [0.01 + 0.096 + 0.084, 0.01 + 0.084]);
+
+    let feature_points = charuco.feature_points();
+    assert_eq!(feature_points.len(), 16);
+    assert_near(&feature_points[0], [0.106, 0.106]);
+    assert_near(&feature_points[15], [0.394, 0.394]);
+}
+
+#[test]
+fn april_grid_layout() {
+    // a 4x4 AprilGrid with 80mm tags and 24mm gaps
+    let april_grid: CalibrationPattern = json5::from_str(
+        r#"{
+            "kind": "april_grid",
+            "dictionary": "DICT_APRILTAG_36h11",
+            "tag_grid": { "rows": 4, "cols": 4 },
+            "tag_size": "80mm",
+            "tag_spacing_ratio": 0.3,
+            "border_size": "10mm",
+            "first_tag_id": 100,
+        }"#,
+    )
+    .unwrap();
+    assert!((april_grid.paper_size().as_meters() - 0.46).abs() < 1e-9);
+    assert_eq!(april_grid.marker_ids(), (100..116).collect::<Vec<_>>());
+
+    let corners = april_grid.marker_corners();
+    assert_eq!(corners.len(), 16);
+    assert_near(&corners[0][3], [0.034, 0.034]);
+    assert_near(&corners[5][3], [0.138, 0.138]);
+    assert_near(&corners[15][1], [0.426, 0.426]);
+    assert_eq!(april_grid.feature_points().len(), 64);
+}
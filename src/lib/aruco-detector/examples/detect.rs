@@ -26,6 +26,7 @@ fn main() -> Result<()> {
         board_border_size: Length::from_millimeters(10.0),
         marker_square_size_ratio: r64(0.8),
         num_squares_per_side: 2,
+        marker_grid: None,
         border_bits: 1,
     };
     let detector = Builder {
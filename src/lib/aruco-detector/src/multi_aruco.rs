@@ -141,10 +141,7 @@ impl PoseEstimation {
             image_det: ImageDetection { pattern, .. },
             ..
         } = &self;
-        let MultiArucoPattern {
-            num_squares_per_side,
-            ..
-        } = *pattern;
+        let (num_rows, num_cols) = (pattern.num_rows(), pattern.num_cols());
         let Params {
             max_icp_iterations,
             icp_pose_weight_threshold,
@@ -155,12 +152,12 @@ impl PoseEstimation {
             max_icp_iterations >= 1,
             "max_icp_iterations must be positive, but get 0"
         );
-        let square_size = pattern.square_size();
 
+        // the square centers relative to the paper center, where the
+        // grid is centered like on the board model
         let init_source_points = || {
-            iproduct!(0..num_squares_per_side, 0..num_squares_per_side).map(|(row, col)| {
-                let x = (col as f64 - num_squares_per_side as f64 / 2.0 + 0.5) * square_size;
-                let y = (row as f64 - num_squares_per_side as f64 / 2.0 + 0.5) * square_size;
+            iproduct!(0..num_rows, 0..num_cols).map(|(row, col)| {
+                let [x, y] = pattern.square_center_offset(row as usize, col as usize);
                 Point3::new(x.as_meters(), y.as_meters(), 0.0)
             })
         };
@@ -291,22 +288,12 @@ impl Builder {
             camera_intrinsic,
         } = self;
 
-        let MultiArucoPattern {
-            board_size,
-            board_border_size,
-            num_squares_per_side,
-            marker_square_size_ratio,
-            ref marker_ids,
-            ..
-        } = pattern;
-
-        let square_size = (board_size - board_border_size * 2.0) / num_squares_per_side as f64;
-        let marker_size = square_size * marker_square_size_ratio.raw();
-        let marker_ids: IndexSet<u32> = marker_ids.iter().cloned().collect();
+        let marker_size = pattern.marker_size();
+        let marker_ids: IndexSet<u32> = pattern.marker_ids.iter().cloned().collect();
 
         // check if marker IDs are unique
         ensure!(
-            marker_ids.len() == num_squares_per_side.pow(2) as usize,
+            marker_ids.len() == pattern.num_markers(),
             "ArUco IDs must be unique"
         );
 
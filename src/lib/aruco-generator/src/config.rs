@@ -1,5 +1,7 @@
 use anyhow::{ensure, Result};
-use aruco_config::ArucoDictionary;
+use aruco_config::{ArucoDictionary, CharucoPattern, MarkerGrid};
+use measurements::Length;
+use noisy_float::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
@@ -82,6 +84,26 @@ pub struct MultipleArucosConfig {
     pub output_path: Option<String>,
 }
 
+impl SingleCharucoConfig {
+    /// The pattern description of the board, which shares its geometry
+    /// with the detectors.
+    pub fn pattern(&self) -> CharucoPattern {
+        let squares_per_side = self.squares_per_side as u32;
+        CharucoPattern {
+            dictionary: self.dictionary,
+            squares: MarkerGrid {
+                rows: squares_per_side,
+                cols: squares_per_side,
+            },
+            paper_size: Length::from_millimeters(self.paper_size_mm),
+            border_size: Length::from_millimeters(self.margin_size_mm),
+            marker_square_size_ratio: r64(self.marker_to_square_length_ratio),
+            first_marker_id: 0,
+            border_bits: self.border_bits as u32,
+        }
+    }
+}
+
 /// Configuration for marker ID selection
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -1,5 +1,5 @@
 use anyhow::{ensure, Result};
-use aruco_config::{ArucoDictionary, MultiArucoPattern};
+use aruco_config::{ArucoDictionary, MultiArucoPattern, PatternGeometry};
 use console::Term;
 use dialoguer::{Confirm, Input, Select};
 use indexmap::IndexSet;
@@ -51,15 +51,15 @@ impl ArucoGenerator {
         let dictionary = &config.dictionary;
         let squares_per_side = config.squares_per_side as i32;
         let border_bits = config.border_bits as i32;
-        let marker_to_square_length_ratio = config.marker_to_square_length_ratio;
         let paper_size_mm = config.paper_size_mm;
         let margin_size_mm = config.margin_size_mm;
         let dpi = config.dpi;
         let output_path = &config.output_path;
 
         let mut board = {
-            let square_length = (paper_size_mm - margin_size_mm * 2.0) / squares_per_side as f64;
-            let marker_length = square_length * marker_to_square_length_ratio;
+            let pattern = config.pattern();
+            let square_length = pattern.square_size().as_millimeters();
+            let marker_length = pattern.marker_size().as_millimeters();
             println!("square length = {} mm", square_length);
             println!("marker length = {} mm", marker_length);
 
@@ -156,6 +156,7 @@ impl ArucoGenerator {
             board_border_size: Length::from_millimeters(board_border_size_mm),
             marker_square_size_ratio: r64(marker_square_size_ratio),
             num_squares_per_side,
+            marker_grid: None,
             border_bits,
         };
 
@@ -0,0 +1,239 @@
+//! Closed-form correspondence search on a target.
+//!
+//! [CorrespondenceIndex] is built once for a target and answers queries
+//! at any pose. Points are transformed into the target frame once, and
+//! the holes that may contain a point are looked up in a uniform grid
+//! over the target rectangle. Queries do not allocate.
+
+use crate::target::{circle_point, TargetCircle, TargetGeometry};
+use nalgebra as na;
+use std::borrow::Borrow;
+
+/// The number of grid cells along the longer side of the target.
+const GRID_CELLS: usize = 32;
+
+/// The region on the target plane where a point falls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetRegion {
+    /// On the target material.
+    Face,
+    /// Outside the target border.
+    OuterEdge,
+    /// Inside the hole at the index of [TargetGeometry::holes].
+    Hole(usize),
+}
+
+/// A data point paired with its corresponding point on the target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Correspondence {
+    /// The data point in the target frame.
+    pub local_point: na::Point3<f64>,
+    /// The corresponding point on the target plane. It follows
+    /// [TargetGeometry::corresponding_point].
+    pub local_target: na::Point2<f64>,
+    pub region: TargetRegion,
+}
+
+impl Correspondence {
+    /// The distance from the data point to the corresponding point.
+    pub fn distance(&self) -> f64 {
+        let target = na::Point3::new(self.local_target.x, self.local_target.y, 0.0);
+        (self.local_point - target).norm()
+    }
+
+    /// Transforms the corresponding point by the target pose.
+    pub fn target_point(&self, pose: &na::Isometry3<f64>) -> na::Point3<f64> {
+        pose * na::Point3::new(self.local_target.x, self.local_target.y, 0.0)
+    }
+}
+
+/// The target geometry precomputed for correspondence queries.
+#[derive(Debug, Clone)]
+pub struct CorrespondenceIndex {
+    width: f64,
+    height: f64,
+    holes: Vec<TargetCircle>,
+    cell_size: f64,
+    num_cols: usize,
+    num_rows: usize,
+    /// The range in `cell_holes` of each cell in row-major order.
+    cell_ranges: Vec<(usize, usize)>,
+    /// The indices of holes that overlap each cell.
+    cell_holes: Vec<usize>,
+}
+
+impl CorrespondenceIndex {
+    pub fn new<S>(target: &S) -> Self
+    where
+        S: TargetGeometry + ?Sized,
+    {
+        let (width, height) = (target.width(), target.height());
+        let holes = target.holes();
+
+        let cell_size = match width.max(height) / GRID_CELLS as f64 {
+            size if size > 0.0 => size,
+            _ => 1.0,
+        };
+        let num_cols = ((width / cell_size).ceil() as usize).max(1);
+        let num_rows = ((height / cell_size).ceil() as usize).max(1);
+
+        let mut cell_ranges = Vec::with_capacity(num_cols * num_rows);
+        let mut cell_holes = vec![];
+
+        for row in 0..num_rows {
+            for col in 0..num_cols {
+                let min = na::Point2::new(col as f64 * cell_size, row as f64 * cell_size);
+                let max = min + na::Vector2::repeat(cell_size);
+                let start = cell_holes.len();
+
+                // a hole overlaps the cell if the closest cell point to
+                // the hole center is inside the hole
+                let overlapping = holes.iter().enumerate().filter(|(_, hole)| {
+                    let closest = na::Point2::new(
+                        hole.center.x.clamp(min.x, max.x),
+                        hole.center.y.clamp(min.y, max.y),
+                    );
+                    (closest - hole.center).norm() < hole.radius
+                });
+                cell_holes.extend(overlapping.map(|(index, _)| index));
+                cell_ranges.push((start, cell_holes.len()));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            holes,
+            cell_size,
+            num_cols,
+            num_rows,
+            cell_ranges,
+            cell_holes,
+        }
+    }
+
+    /// Finds the region of a point on the target plane.
+    pub fn region(&self, point: &na::Point2<f64>) -> TargetRegion {
+        if !self.is_within_border(point) {
+            return TargetRegion::OuterEdge;
+        }
+        match self.hole_at(point) {
+            Some(index) => TargetRegion::Hole(index),
+            None => TargetRegion::Face,
+        }
+    }
+
+    /// Finds the corresponding point of a point on the target plane
+    /// like [TargetGeometry::corresponding_point], along with the
+    /// region of the point.
+    pub fn corresponding_point(&self, point: &na::Point2<f64>) -> (na::Point2<f64>, TargetRegion) {
+        if !self.is_within_border(point) {
+            let border_point = na::Point2::new(
+                point.x.clamp(0.0, self.width),
+                point.y.clamp(0.0, self.height),
+            );
+            return (border_point, TargetRegion::OuterEdge);
+        }
+
+        match self.hole_at(point) {
+            Some(index) => (
+                circle_point(&self.holes[index], point),
+                TargetRegion::Hole(index),
+            ),
+            None => (*point, TargetRegion::Face),
+        }
+    }
+
+    /// Finds the closest point on the border or the hole circles like
+    /// [TargetGeometry::closest_boundary_point].
+    pub fn closest_boundary_point(&self, point: &na::Point2<f64>) -> na::Point2<f64> {
+        let (width, height) = (self.width, self.height);
+        let clamped = na::Point2::new(point.x.clamp(0.0, width), point.y.clamp(0.0, height));
+
+        // the clamped point is on the border if the point is outside,
+        // otherwise the point is moved to the nearest side
+        let border_point = if clamped != *point {
+            clamped
+        } else {
+            let sides = [
+                (point.y, na::Point2::new(point.x, 0.0)),
+                (width - point.x, na::Point2::new(width, point.y)),
+                (height - point.y, na::Point2::new(point.x, height)),
+                (point.x, na::Point2::new(0.0, point.y)),
+            ];
+            sides
+                .into_iter()
+                .min_by(|(lhs, _), (rhs, _)| lhs.total_cmp(rhs))
+                .unwrap()
+                .1
+        };
+
+        let border_distance = (border_point - point).norm();
+        self.holes
+            .iter()
+            .map(|hole| {
+                let distance = ((point - hole.center).norm() - hole.radius).abs();
+                (distance, hole)
+            })
+            .fold(
+                (border_distance, border_point),
+                |(min_distance, closest), (distance, hole)| {
+                    if distance < min_distance {
+                        (distance, circle_point(hole, point))
+                    } else {
+                        (min_distance, closest)
+                    }
+                },
+            )
+            .1
+    }
+
+    /// Pairs a data point in the target frame with its corresponding
+    /// point.
+    pub fn find_correspondence(&self, local_point: &na::Point3<f64>) -> Correspondence {
+        let (local_target, region) = self.corresponding_point(&local_point.xy());
+        Correspondence {
+            local_point: *local_point,
+            local_target,
+            region,
+        }
+    }
+
+    /// Finds the correspondences of data points for the target at the
+    /// pose.
+    ///
+    /// The output is cleared and refilled in the order of the points,
+    /// so a buffer can be reused across iterations without allocation.
+    pub fn find_correspondences_into<P>(
+        &self,
+        pose: &na::Isometry3<f64>,
+        points: impl IntoIterator<Item = P>,
+        correspondences: &mut Vec<Correspondence>,
+    ) where
+        P: Borrow<na::Point3<f64>>,
+    {
+        let inverse_pose = pose.inverse();
+        correspondences.clear();
+        correspondences.extend(
+            points
+                .into_iter()
+                .map(|point| self.find_correspondence(&(inverse_pose * point.borrow()))),
+        );
+    }
+
+    fn is_within_border(&self, point: &na::Point2<f64>) -> bool {
+        (0.0..=self.width).contains(&point.x) && (0.0..=self.height).contains(&point.y)
+    }
+
+    /// Finds the hole containing a point within the target border.
+    fn hole_at(&self, point: &na::Point2<f64>) -> Option<usize> {
+        let col = ((point.x / self.cell_size) as usize).min(self.num_cols - 1);
+        let row = ((point.y / self.cell_size) as usize).min(self.num_rows - 1);
+        let (start, end) = self.cell_ranges[row * self.num_cols + col];
+
+        self.cell_holes[start..end].iter().copied().find(|&index| {
+            let hole = &self.holes[index];
+            (point - hole.center).norm() < hole.radius
+        })
+    }
+}
@@ -0,0 +1,500 @@
+//! Mesh and point sample export of posed targets.
+//!
+//! The target face is triangulated on the target plane by ear
+//! clipping, with each hole approximated by a polygon. The triangles
+//! are split at the border of the marker paper, so the paper forms a
+//! separate submesh. Meshes and point samples are written as ASCII PLY
+//! or Wavefront OBJ files, which can be opened in CloudCompare.
+
+use crate::{BoardModel, CorrespondenceIndex, TargetCircle, TargetGeometry, TargetRegion};
+use nalgebra as na;
+use std::{
+    collections::HashMap,
+    f64::consts::TAU,
+    io::{self, Write},
+};
+
+/// The vertex colors of the bare board and the marker paper in PLY
+/// files.
+const BOARD_COLOR: [u8; 3] = [255, 178, 0];
+const MARKER_PAPER_COLOR: [u8; 3] = [255, 255, 255];
+
+/// Vertices closer than this distance in meters are merged.
+const EPS_VERTEX: f64 = 1e-9;
+
+/// A triangle mesh in the world frame.
+#[derive(Debug, Clone, Default)]
+pub struct TriangleMesh {
+    pub vertices: Vec<na::Point3<f64>>,
+    /// The vertex indices of each triangle, counterclockwise when seen
+    /// from the +z side of the target.
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl TriangleMesh {
+    /// The total area of the triangles.
+    pub fn area(&self) -> f64 {
+        self.triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                let [a, b, c] = [self.vertices[a], self.vertices[b], self.vertices[c]];
+                (b - a).cross(&(c - a)).norm() / 2.0
+            })
+            .sum()
+    }
+}
+
+/// The triangulated face of a posed target.
+#[derive(Debug, Clone, Default)]
+pub struct TargetMesh {
+    /// The target face except the marker paper.
+    pub board: TriangleMesh,
+    /// The marker paper at the origin corner.
+    pub marker_paper: TriangleMesh,
+}
+
+impl TargetMesh {
+    /// Writes the mesh as an OBJ file with the board and the marker
+    /// paper as separate objects.
+    pub fn write_obj<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        writeln!(writer, "# calibration target mesh")?;
+
+        let mut offset = 1;
+        for (name, mesh) in [("board", &self.board), ("marker_paper", &self.marker_paper)] {
+            writeln!(writer, "o {name}")?;
+            for vertex in &mesh.vertices {
+                writeln!(writer, "v {} {} {}", vertex.x, vertex.y, vertex.z)?;
+            }
+            for [a, b, c] in &mesh.triangles {
+                writeln!(writer, "f {} {} {}", a + offset, b + offset, c + offset)?;
+            }
+            offset += mesh.vertices.len();
+        }
+
+        Ok(())
+    }
+
+    /// Writes the mesh as an ASCII PLY file. The board and the marker
+    /// paper are told apart by vertex colors.
+    pub fn write_ply<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let meshes = [
+            (&self.board, BOARD_COLOR),
+            (&self.marker_paper, MARKER_PAPER_COLOR),
+        ];
+        let num_vertices: usize = meshes.iter().map(|(mesh, _)| mesh.vertices.len()).sum();
+        let num_faces: usize = meshes.iter().map(|(mesh, _)| mesh.triangles.len()).sum();
+
+        write_ply_header(&mut writer, num_vertices, Some(num_faces))?;
+        for (mesh, [red, green, blue]) in meshes {
+            for vertex in &mesh.vertices {
+                let [x, y, z]: [f64; 3] = vertex.coords.into();
+                writeln!(writer, "{x} {y} {z} {red} {green} {blue}")?;
+            }
+        }
+
+        let mut offset = 0;
+        for (mesh, _) in meshes {
+            for [a, b, c] in &mesh.triangles {
+                writeln!(writer, "3 {} {} {}", a + offset, b + offset, c + offset)?;
+            }
+            offset += mesh.vertices.len();
+        }
+
+        Ok(())
+    }
+}
+
+/// Points evenly sampled on the face of a posed target.
+#[derive(Debug, Clone, Default)]
+pub struct TargetSamples {
+    pub points: Vec<na::Point3<f64>>,
+    /// Whether each point is on the marker paper.
+    pub on_marker_paper: Vec<bool>,
+}
+
+impl TargetSamples {
+    /// Writes the points as an OBJ file of vertices only.
+    pub fn write_obj<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        writeln!(writer, "# calibration target samples")?;
+        for point in &self.points {
+            writeln!(writer, "v {} {} {}", point.x, point.y, point.z)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the points as an ASCII PLY file. The board and the marker
+    /// paper are told apart by colors.
+    pub fn write_ply<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        write_ply_header(&mut writer, self.points.len(), None)?;
+        for (point, &on_marker_paper) in self.points.iter().zip(&self.on_marker_paper) {
+            let [red, green, blue] = if on_marker_paper {
+                MARKER_PAPER_COLOR
+            } else {
+                BOARD_COLOR
+            };
+            writeln!(
+                writer,
+                "{} {} {} {red} {green} {blue}",
+                point.x, point.y, point.z
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> BoardModel<S>
+where
+    S: TargetGeometry,
+{
+    /// Triangulates the target face at the target pose. Each hole is
+    /// approximated by a polygon of `hole_segments` sides.
+    pub fn to_mesh(&self, hole_segments: usize) -> TargetMesh {
+        let paper_size = self.marker_paper_size.as_meters();
+        let paper_clips = [(1.0, 0.0, paper_size), (0.0, 1.0, paper_size)];
+        let board_clips = [
+            // right of the paper
+            vec![(-1.0, 0.0, -paper_size)],
+            // above the paper
+            vec![(1.0, 0.0, paper_size), (0.0, -1.0, -paper_size)],
+        ];
+
+        let mut board = MeshBuilder::default();
+        let mut marker_paper = MeshBuilder::default();
+
+        for triangle in triangulate_face(&self.board_shape, hole_segments.max(3)) {
+            marker_paper.add_polygon(&clip_polygon(&triangle, &paper_clips));
+            for clips in &board_clips {
+                board.add_polygon(&clip_polygon(&triangle, clips));
+            }
+        }
+
+        TargetMesh {
+            board: board.build(&self.pose),
+            marker_paper: marker_paper.build(&self.pose),
+        }
+    }
+
+    /// Samples points on the target face in a square grid of the
+    /// spacing at the target pose. No points are sampled if the spacing
+    /// is not positive.
+    pub fn sample_points(&self, spacing: f64) -> TargetSamples {
+        if spacing.is_nan() || spacing <= 0.0 {
+            return TargetSamples::default();
+        }
+
+        let index = CorrespondenceIndex::new(&self.board_shape);
+        let paper_size = self.marker_paper_size.as_meters();
+        let (width, height) = (self.board_shape.width(), self.board_shape.height());
+        let num_cols = (width / spacing).floor() as usize;
+        let num_rows = (height / spacing).floor() as usize;
+
+        let mut samples = TargetSamples::default();
+        for row in 0..num_rows {
+            for col in 0..num_cols {
+                // samples are centered in grid cells
+                let point =
+                    na::Point2::new((col as f64 + 0.5) * spacing, (row as f64 + 0.5) * spacing);
+                if index.region(&point) != TargetRegion::Face {
+                    continue;
+                }
+                samples.points.push(self.target_point(&point));
+                samples
+                    .on_marker_paper
+                    .push(point.x <= paper_size && point.y <= paper_size);
+            }
+        }
+        samples
+    }
+}
+
+/// Collects polygons on the target plane into a triangle mesh.
+#[derive(Default)]
+struct MeshBuilder {
+    vertices: Vec<na::Point2<f64>>,
+    vertex_ids: HashMap<(i64, i64), usize>,
+    triangles: Vec<[usize; 3]>,
+}
+
+impl MeshBuilder {
+    /// Adds a convex polygon by a triangle fan. Degenerate triangles
+    /// are skipped.
+    fn add_polygon(&mut self, polygon: &[na::Point2<f64>]) {
+        if polygon.len() < 3 {
+            return;
+        }
+        let first = self.vertex_id(&polygon[0]);
+        for pair in polygon[1..].windows(2) {
+            if cross(&polygon[0], &pair[0], &pair[1]) <= EPS_VERTEX * EPS_VERTEX {
+                continue;
+            }
+            let triangle = [first, self.vertex_id(&pair[0]), self.vertex_id(&pair[1])];
+            self.triangles.push(triangle);
+        }
+    }
+
+    fn vertex_id(&mut self, point: &na::Point2<f64>) -> usize {
+        let key = (
+            (point.x / EPS_VERTEX).round() as i64,
+            (point.y / EPS_VERTEX).round() as i64,
+        );
+        let vertices = &mut self.vertices;
+        *self.vertex_ids.entry(key).or_insert_with(|| {
+            vertices.push(*point);
+            vertices.len() - 1
+        })
+    }
+
+    fn build(self, pose: &na::Isometry3<f64>) -> TriangleMesh {
+        TriangleMesh {
+            vertices: self
+                .vertices
+                .iter()
+                .map(|point| pose * na::Point3::new(point.x, point.y, 0.0))
+                .collect(),
+            triangles: self.triangles,
+        }
+    }
+}
+
+/// Triangulates the target face with the holes cut out on the target
+/// plane.
+fn triangulate_face<S>(target: &S, hole_segments: usize) -> Vec<[na::Point2<f64>; 3]>
+where
+    S: TargetGeometry + ?Sized,
+{
+    let (width, height) = (target.width(), target.height());
+
+    // the outer border is counterclockwise and the holes are clockwise
+    let mut polygon = vec![
+        na::Point2::new(0.0, 0.0),
+        na::Point2::new(width, 0.0),
+        na::Point2::new(width, height),
+        na::Point2::new(0.0, height),
+    ];
+
+    // holes are bridged to the border from the rightmost one, so that
+    // a bridge never crosses a hole that is not bridged yet
+    let mut holes = target.holes();
+    holes.sort_by(|lhs, rhs| (rhs.center.x + rhs.radius).total_cmp(&(lhs.center.x + lhs.radius)));
+    for hole in &holes {
+        polygon = bridge_hole(&polygon, &hole_polygon(hole, hole_segments));
+    }
+
+    ear_clip(&polygon)
+}
+
+/// Approximates a hole by a clockwise polygon starting at its rightmost
+/// vertex.
+fn hole_polygon(hole: &TargetCircle, segments: usize) -> Vec<na::Point2<f64>> {
+    (0..segments)
+        .map(|index| {
+            let angle = -TAU * index as f64 / segments as f64;
+            hole.center + na::Vector2::new(angle.cos(), angle.sin()) * hole.radius
+        })
+        .collect()
+}
+
+/// Joins a hole to the polygon by a pair of coincident edges from the
+/// rightmost hole vertex to a visible polygon vertex.
+fn bridge_hole(polygon: &[na::Point2<f64>], hole: &[na::Point2<f64>]) -> Vec<na::Point2<f64>> {
+    let num_points = polygon.len();
+    let hole_start = hole[0];
+
+    // cast a ray from the hole to +x and find the closest edge
+    let hit = (0..num_points)
+        .filter_map(|index| {
+            let start = polygon[index];
+            let end = polygon[(index + 1) % num_points];
+            if (start.y - hole_start.y) * (end.y - hole_start.y) > 0.0 || start.y == end.y {
+                return None;
+            }
+            let ratio = (hole_start.y - start.y) / (end.y - start.y);
+            let x = start.x + (end.x - start.x) * ratio;
+            (x >= hole_start.x).then_some((x, index))
+        })
+        .min_by(|(lhs, _), (rhs, _)| lhs.total_cmp(rhs));
+    let (hit_x, edge) = match hit {
+        Some(hit) => hit,
+        None => return polygon.to_vec(),
+    };
+    let hit_point = na::Point2::new(hit_x, hole_start.y);
+
+    // the hit vertex or the edge end farther along the ray is visible
+    // unless a reflex vertex lies in between, in which case the one
+    // closest in angle to the ray is visible
+    let candidate = {
+        let next = (edge + 1) % num_points;
+        if polygon[edge] == hit_point {
+            edge
+        } else if polygon[next] == hit_point || polygon[next].x > polygon[edge].x {
+            next
+        } else {
+            edge
+        }
+    };
+    let visible = (0..num_points)
+        .filter(|&index| index != candidate && is_reflex(polygon, index))
+        .filter(|&index| {
+            let point = &polygon[index];
+            point != &polygon[candidate]
+                && contains(&[hole_start, hit_point, polygon[candidate]], point)
+        })
+        .min_by(|&lhs, &rhs| {
+            let angle = |index: usize| {
+                let vec = polygon[index] - hole_start;
+                (vec.y.abs().atan2(vec.x), vec.norm())
+            };
+            let (lhs, rhs) = (angle(lhs), angle(rhs));
+            lhs.0.total_cmp(&rhs.0).then(lhs.1.total_cmp(&rhs.1))
+        })
+        .unwrap_or(candidate);
+
+    let mut bridged = Vec::with_capacity(num_points + hole.len() + 2);
+    bridged.extend_from_slice(&polygon[..=visible]);
+    bridged.extend_from_slice(hole);
+    bridged.push(hole_start);
+    bridged.extend_from_slice(&polygon[visible..]);
+    bridged
+}
+
+/// Triangulates a counterclockwise simple polygon by ear clipping.
+fn ear_clip(polygon: &[na::Point2<f64>]) -> Vec<[na::Point2<f64>; 3]> {
+    let mut indices: Vec<usize> = (0..polygon.len()).collect();
+    let mut triangles = vec![];
+
+    while indices.len() > 3 {
+        let num_indices = indices.len();
+        let corner = |pos: usize| {
+            [
+                polygon[indices[(pos + num_indices - 1) % num_indices]],
+                polygon[indices[pos]],
+                polygon[indices[(pos + 1) % num_indices]],
+            ]
+        };
+
+        let ear = (0..num_indices).find(|&pos| {
+            let triangle = corner(pos);
+            if cross(&triangle[0], &triangle[1], &triangle[2]) <= 0.0 {
+                return false;
+            }
+            // no other vertex lies in the ear
+            !indices.iter().any(|&index| {
+                let point = &polygon[index];
+                !triangle.contains(point) && contains(&triangle, point)
+            })
+        });
+
+        match ear {
+            Some(pos) => {
+                triangles.push(corner(pos));
+                indices.remove(pos);
+            }
+            None => {
+                // drop a collinear vertex, or give up on a degenerate
+                // polygon
+                let collinear = (0..num_indices).find(|&pos| {
+                    let [prev, curr, next] = corner(pos);
+                    cross(&prev, &curr, &next).abs() <= EPS_VERTEX * EPS_VERTEX
+                });
+                match collinear {
+                    Some(pos) => {
+                        indices.remove(pos);
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    if let [a, b, c] = indices[..] {
+        if cross(&polygon[a], &polygon[b], &polygon[c]) > 0.0 {
+            triangles.push([polygon[a], polygon[b], polygon[c]]);
+        }
+    }
+    triangles
+}
+
+/// Clips a convex polygon by half-planes `a * x + b * y <= c` using
+/// Sutherland–Hodgman algorithm.
+fn clip_polygon(
+    polygon: &[na::Point2<f64>],
+    half_planes: &[(f64, f64, f64)],
+) -> Vec<na::Point2<f64>> {
+    half_planes
+        .iter()
+        .fold(polygon.to_vec(), |polygon, &(a, b, c)| {
+            let offset = |point: &na::Point2<f64>| a * point.x + b * point.y - c;
+            let num_points = polygon.len();
+            let mut clipped = Vec::with_capacity(num_points + 1);
+
+            for index in 0..num_points {
+                let curr = polygon[index];
+                let next = polygon[(index + 1) % num_points];
+                let (curr_offset, next_offset) = (offset(&curr), offset(&next));
+                if curr_offset <= 0.0 {
+                    clipped.push(curr);
+                }
+                if (curr_offset <= 0.0) != (next_offset <= 0.0) {
+                    let ratio = curr_offset / (curr_offset - next_offset);
+                    clipped.push(curr + (next - curr) * ratio);
+                }
+            }
+            clipped
+        })
+}
+
+fn write_ply_header<W>(
+    writer: &mut W,
+    num_vertices: usize,
+    num_faces: Option<usize>,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "element vertex {num_vertices}")?;
+    for axis in ["x", "y", "z"] {
+        writeln!(writer, "property double {axis}")?;
+    }
+    for channel in ["red", "green", "blue"] {
+        writeln!(writer, "property uchar {channel}")?;
+    }
+    if let Some(num_faces) = num_faces {
+        writeln!(writer, "element face {num_faces}")?;
+        writeln!(writer, "property list uchar int vertex_indices")?;
+    }
+    writeln!(writer, "end_header")
+}
+
+fn is_reflex(polygon: &[na::Point2<f64>], index: usize) -> bool {
+    let num_points = polygon.len();
+    let prev = polygon[(index + num_points - 1) % num_points];
+    let next = polygon[(index + 1) % num_points];
+    cross(&prev, &polygon[index], &next) < 0.0
+}
+
+/// Tests if a point is inside or on a triangle of either orientation.
+fn contains(triangle: &[na::Point2<f64>; 3], point: &na::Point2<f64>) -> bool {
+    let [a, b, c] = triangle;
+    let signs = [cross(a, b, point), cross(b, c, point), cross(c, a, point)];
+    signs.iter().all(|&sign| sign >= 0.0) || signs.iter().all(|&sign| sign <= 0.0)
+}
+
+/// The z component of `(curr - prev) x (next - curr)`, positive if the
+/// points turn counterclockwise.
+fn cross(prev: &na::Point2<f64>, curr: &na::Point2<f64>, next: &na::Point2<f64>) -> f64 {
+    (curr - prev).perp(&(next - curr))
+}
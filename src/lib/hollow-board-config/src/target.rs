@@ -0,0 +1,298 @@
+//! Calibration target geometries.
+//!
+//! A target is a rectangle on the xy-plane of its own frame. The origin
+//! is at a corner, and the x and y axes run along the width and the
+//! height. Circular holes may be cut through the target. All lengths
+//! returned by [TargetGeometry] are in meters.
+
+use crate::BoardShape;
+use measurements::Length;
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Debug, sync::Arc};
+
+/// Two lengths are considered equal below this difference in meters.
+pub(crate) const EPS_LENGTH: f64 = 1e-9;
+
+/// A circle on the target plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetCircle {
+    /// The circle center in the target frame.
+    pub center: na::Point2<f64>,
+    pub radius: f64,
+}
+
+/// The geometry of a rectangular calibration target with circular
+/// holes.
+pub trait TargetGeometry: Debug {
+    /// The target size along the x axis.
+    fn width(&self) -> f64;
+
+    /// The target size along the y axis.
+    fn height(&self) -> f64;
+
+    /// The holes on the target. Holes do not overlap each other or the
+    /// target border.
+    fn holes(&self) -> Vec<TargetCircle>;
+
+    /// The corners in order of the origin, the +x corner, the far
+    /// corner and the +y corner.
+    fn corners(&self) -> [na::Point2<f64>; 4] {
+        let (width, height) = (self.width(), self.height());
+        [
+            na::Point2::new(0.0, 0.0),
+            na::Point2::new(width, 0.0),
+            na::Point2::new(width, height),
+            na::Point2::new(0.0, height),
+        ]
+    }
+
+    /// The center of the target rectangle.
+    fn center(&self) -> na::Point2<f64> {
+        na::Point2::new(self.width() / 2.0, self.height() / 2.0)
+    }
+
+    /// The feature points that can be located on the target, which are
+    /// the corners followed by the hole centers.
+    fn feature_points(&self) -> Vec<na::Point2<f64>> {
+        self.corners()
+            .into_iter()
+            .chain(self.holes().into_iter().map(|hole| hole.center))
+            .collect()
+    }
+
+    /// The number of in-plane rotations about the center that map the
+    /// target border onto itself. Holes are not taken into account.
+    fn symmetry_order(&self) -> usize {
+        if (self.width() - self.height()).abs() <= EPS_LENGTH {
+            4
+        } else {
+            2
+        }
+    }
+
+    /// Finds the corresponding point of a point on the target plane.
+    ///
+    /// A point outside the border corresponds to the closest point on
+    /// the border. A point inside a hole corresponds to the closest
+    /// point on the hole circle. Otherwise the point corresponds to
+    /// itself.
+    fn corresponding_point(&self, point: &na::Point2<f64>) -> na::Point2<f64> {
+        let border_point = na::Point2::new(
+            point.x.clamp(0.0, self.width()),
+            point.y.clamp(0.0, self.height()),
+        );
+        if border_point != *point {
+            return border_point;
+        }
+
+        self.holes()
+            .into_iter()
+            .find(|hole| (point - hole.center).norm() < hole.radius)
+            .map(|hole| circle_point(&hole, point))
+            .unwrap_or(*point)
+    }
+
+    /// Finds the closest point on the border or the hole circles.
+    fn closest_boundary_point(&self, point: &na::Point2<f64>) -> na::Point2<f64> {
+        let corners = self.corners();
+        let edge_points = (0..4).map(|idx| {
+            let start = corners[idx];
+            let end = corners[(idx + 1) % 4];
+            let dir = end - start;
+            let ratio = ((point - start).dot(&dir) / dir.norm_squared()).clamp(0.0, 1.0);
+            start + dir * ratio
+        });
+        let circle_points = self
+            .holes()
+            .into_iter()
+            .map(|hole| circle_point(&hole, point));
+
+        edge_points
+            .chain(circle_points)
+            .min_by(|lhs, rhs| {
+                let lhs = (lhs - point).norm_squared();
+                let rhs = (rhs - point).norm_squared();
+                lhs.total_cmp(&rhs)
+            })
+            .unwrap()
+    }
+}
+
+impl<T> TargetGeometry for Arc<T>
+where
+    T: TargetGeometry + ?Sized,
+{
+    fn width(&self) -> f64 {
+        (**self).width()
+    }
+
+    fn height(&self) -> f64 {
+        (**self).height()
+    }
+
+    fn holes(&self) -> Vec<TargetCircle> {
+        (**self).holes()
+    }
+
+    fn symmetry_order(&self) -> usize {
+        (**self).symmetry_order()
+    }
+
+    fn corresponding_point(&self, point: &na::Point2<f64>) -> na::Point2<f64> {
+        (**self).corresponding_point(point)
+    }
+
+    fn closest_boundary_point(&self, point: &na::Point2<f64>) -> na::Point2<f64> {
+        (**self).closest_boundary_point(point)
+    }
+}
+
+/// The square hollow board with three holes.
+///
+/// The holes are ordered as the left, the right and the top hole.
+impl TargetGeometry for BoardShape {
+    fn width(&self) -> f64 {
+        self.board_width.as_meters()
+    }
+
+    fn height(&self) -> f64 {
+        self.board_width.as_meters()
+    }
+
+    fn holes(&self) -> Vec<TargetCircle> {
+        let half_width = self.board_width.as_meters() / 2.0;
+        let shift = self.hole_center_shift.as_meters();
+        let radius = self.hole_radius.as_meters();
+
+        [(shift, -shift), (-shift, shift), (shift, shift)]
+            .into_iter()
+            .map(|(dx, dy)| TargetCircle {
+                center: na::Point2::new(half_width + dx, half_width + dy),
+                radius,
+            })
+            .collect()
+    }
+}
+
+/// A circular hole on a [HoledRectangle].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoleShape {
+    /// The x coordinate of the hole center from the origin corner.
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub x: Length,
+    /// The y coordinate of the hole center from the origin corner.
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub y: Length,
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub radius: Length,
+}
+
+/// A rectangular board with any number of circular holes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoledRectangle {
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub width: Length,
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub height: Length,
+    pub holes: Vec<HoleShape>,
+}
+
+impl TargetGeometry for HoledRectangle {
+    fn width(&self) -> f64 {
+        self.width.as_meters()
+    }
+
+    fn height(&self) -> f64 {
+        self.height.as_meters()
+    }
+
+    fn holes(&self) -> Vec<TargetCircle> {
+        self.holes
+            .iter()
+            .map(|hole| TargetCircle {
+                center: na::Point2::new(hole.x.as_meters(), hole.y.as_meters()),
+                radius: hole.radius.as_meters(),
+            })
+            .collect()
+    }
+}
+
+/// A plain rectangular board without holes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rectangle {
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub width: Length,
+    #[serde(with = "newslab_serde_measurements::length")]
+    pub height: Length,
+}
+
+impl TargetGeometry for Rectangle {
+    fn width(&self) -> f64 {
+        self.width.as_meters()
+    }
+
+    fn height(&self) -> f64 {
+        self.height.as_meters()
+    }
+
+    fn holes(&self) -> Vec<TargetCircle> {
+        vec![]
+    }
+}
+
+/// One of the supported target geometries, selected by the `kind`
+/// field in configuration files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TargetShape {
+    HollowBoard(BoardShape),
+    HoledRectangle(HoledRectangle),
+    Rectangle(Rectangle),
+}
+
+impl TargetShape {
+    fn geometry(&self) -> &dyn TargetGeometry {
+        match self {
+            Self::HollowBoard(shape) => shape,
+            Self::HoledRectangle(shape) => shape,
+            Self::Rectangle(shape) => shape,
+        }
+    }
+}
+
+impl TargetGeometry for TargetShape {
+    fn width(&self) -> f64 {
+        self.geometry().width()
+    }
+
+    fn height(&self) -> f64 {
+        self.geometry().height()
+    }
+
+    fn holes(&self) -> Vec<TargetCircle> {
+        self.geometry().holes()
+    }
+
+    fn symmetry_order(&self) -> usize {
+        self.geometry().symmetry_order()
+    }
+
+    fn corresponding_point(&self, point: &na::Point2<f64>) -> na::Point2<f64> {
+        self.geometry().corresponding_point(point)
+    }
+
+    fn closest_boundary_point(&self, point: &na::Point2<f64>) -> na::Point2<f64> {
+        self.geometry().closest_boundary_point(point)
+    }
+}
+
+/// Finds the point on the circle in the direction of the point from
+/// the circle center.
+pub(crate) fn circle_point(circle: &TargetCircle, point: &na::Point2<f64>) -> na::Point2<f64> {
+    let radial = match na::Unit::try_new(point - circle.center, 1e-12) {
+        Some(radial) => radial,
+        None => na::Vector2::x_axis(),
+    };
+    circle.center + radial.scale(circle.radius)
+}
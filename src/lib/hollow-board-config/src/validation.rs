@@ -0,0 +1,361 @@
+//! Geometric validation of target and marker pattern configurations.
+//!
+//! Mistyped lengths in configuration files are usually still valid
+//! numbers, so they deserialize fine and only show up as wrong board
+//! poses. The checks here find targets whose holes do not fit on the
+//! board or cut into the marker paper, and marker patterns that do not
+//! fit the marker paper.
+
+use crate::{target::EPS_LENGTH, BoardModel, TargetGeometry};
+use aruco_config::{CalibrationPattern, MultiArucoPattern, PatternGeometry};
+use measurements::Length;
+use nalgebra as na;
+use std::fmt;
+
+/// A geometric inconsistency in a configuration. Lengths are in meters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// A length that must be positive is zero or negative.
+    NonPositiveLength { name: &'static str, length: f64 },
+    /// A hole extends past the target border by the overshoot.
+    HoleOutsideBoard { hole: usize, overshoot: f64 },
+    /// Two holes overlap by the overlap depth.
+    HolesOverlap {
+        first: usize,
+        second: usize,
+        overlap: f64,
+    },
+    /// The marker paper does not fit on the target.
+    MarkerPaperExceedsBoard {
+        paper_size: f64,
+        width: f64,
+        height: f64,
+    },
+    /// A hole cuts into the marker paper at the origin corner by the
+    /// overlap depth.
+    HoleOverlapsMarkerPaper { hole: usize, overlap: f64 },
+    /// The marker paper size of a board model differs from the paper
+    /// size of the marker pattern.
+    MarkerPaperSizeMismatch { model_size: f64, pattern_size: f64 },
+    /// The white borders of the marker paper leave no room for markers.
+    MarkerBorderTooWide { paper_size: f64, border_size: f64 },
+    /// The marker to square size ratio is not within (0, 1].
+    InvalidMarkerRatio { ratio: f64 },
+    /// The number of marker IDs does not fill the marker grid.
+    MarkerCountMismatch {
+        num_markers: usize,
+        num_squares: usize,
+    },
+    /// The marker grid has no rows or no columns.
+    EmptyMarkerGrid { num_rows: u32, num_cols: u32 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mm = |meters: f64| meters * 1000.0;
+
+        match *self {
+            Self::NonPositiveLength { name, length } => {
+                write!(f, "{name} must be positive, but it is {:.1}mm", mm(length))
+            }
+            Self::HoleOutsideBoard { hole, overshoot } => write!(
+                f,
+                "hole {hole} extends {:.1}mm past the board border",
+                mm(overshoot)
+            ),
+            Self::HolesOverlap {
+                first,
+                second,
+                overlap,
+            } => write!(
+                f,
+                "holes {first} and {second} overlap by {:.1}mm",
+                mm(overlap)
+            ),
+            Self::MarkerPaperExceedsBoard {
+                paper_size,
+                width,
+                height,
+            } => write!(
+                f,
+                "the {:.1}mm marker paper does not fit on the {:.1}mm x {:.1}mm board",
+                mm(paper_size),
+                mm(width),
+                mm(height)
+            ),
+            Self::HoleOverlapsMarkerPaper { hole, overlap } => write!(
+                f,
+                "hole {hole} cuts {:.1}mm into the marker paper",
+                mm(overlap)
+            ),
+            Self::MarkerPaperSizeMismatch {
+                model_size,
+                pattern_size,
+            } => write!(
+                f,
+                "the board model has a {:.1}mm marker paper, \
+                 but the marker pattern has a {:.1}mm paper",
+                mm(model_size),
+                mm(pattern_size)
+            ),
+            Self::MarkerBorderTooWide {
+                paper_size,
+                border_size,
+            } => write!(
+                f,
+                "the {:.1}mm paper border leaves no room for markers on the {:.1}mm paper",
+                mm(border_size),
+                mm(paper_size)
+            ),
+            Self::InvalidMarkerRatio { ratio } => write!(
+                f,
+                "the marker to square size ratio {ratio} is not within (0, 1]"
+            ),
+            Self::MarkerCountMismatch {
+                num_markers,
+                num_squares,
+            } => write!(
+                f,
+                "{num_markers} marker IDs are given for {num_squares} marker squares"
+            ),
+            Self::EmptyMarkerGrid { num_rows, num_cols } => {
+                write!(f, "the {num_rows}x{num_cols} marker grid is empty")
+            }
+        }
+    }
+}
+
+/// The violations found in a configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violations(pub Vec<Violation>);
+
+impl Violations {
+    fn into_result(self) -> Result<(), Self> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for Violations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid target configuration")?;
+        for violation in &self.0 {
+            write!(f, "\n  - {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Violations {}
+
+/// Checks that the holes fit on the target without overlapping each
+/// other, and that the marker paper at the origin corner fits on the
+/// target without overlapping any hole.
+pub fn check_target<S>(target: &S, marker_paper_size: Length) -> Vec<Violation>
+where
+    S: TargetGeometry + ?Sized,
+{
+    let (width, height) = (target.width(), target.height());
+    let paper_size = marker_paper_size.as_meters();
+    let holes = target.holes();
+    let mut violations = vec![];
+
+    let lengths = [
+        ("target width", width),
+        ("target height", height),
+        ("marker paper size", paper_size),
+    ];
+    let hole_radii = holes.iter().map(|hole| ("hole radius", hole.radius));
+    for (name, length) in lengths.into_iter().chain(hole_radii) {
+        if length <= 0.0 {
+            violations.push(Violation::NonPositiveLength { name, length });
+        }
+    }
+
+    for (index, hole) in holes.iter().enumerate() {
+        let overshoot = [
+            hole.radius - hole.center.x,
+            hole.center.x + hole.radius - width,
+            hole.radius - hole.center.y,
+            hole.center.y + hole.radius - height,
+        ]
+        .into_iter()
+        .fold(f64::NEG_INFINITY, f64::max);
+
+        if overshoot > EPS_LENGTH {
+            violations.push(Violation::HoleOutsideBoard {
+                hole: index,
+                overshoot,
+            });
+        }
+    }
+
+    for (first, lhs) in holes.iter().enumerate() {
+        for (second, rhs) in holes.iter().enumerate().skip(first + 1) {
+            let overlap = lhs.radius + rhs.radius - (lhs.center - rhs.center).norm();
+            if overlap > EPS_LENGTH {
+                violations.push(Violation::HolesOverlap {
+                    first,
+                    second,
+                    overlap,
+                });
+            }
+        }
+    }
+
+    if paper_size > width + EPS_LENGTH || paper_size > height + EPS_LENGTH {
+        violations.push(Violation::MarkerPaperExceedsBoard {
+            paper_size,
+            width,
+            height,
+        });
+    }
+
+    for (index, hole) in holes.iter().enumerate() {
+        // the distance from the hole center to the closest paper point
+        let closest = na::Point2::new(
+            hole.center.x.clamp(0.0, paper_size.max(0.0)),
+            hole.center.y.clamp(0.0, paper_size.max(0.0)),
+        );
+        let overlap = hole.radius - (hole.center - closest).norm();
+        if overlap > EPS_LENGTH {
+            violations.push(Violation::HoleOverlapsMarkerPaper {
+                hole: index,
+                overlap,
+            });
+        }
+    }
+
+    violations
+}
+
+/// Checks that the markers of a pattern fit on its paper.
+pub fn check_aruco_pattern(pattern: &MultiArucoPattern) -> Vec<Violation> {
+    let MultiArucoPattern {
+        ref marker_ids,
+        board_size,
+        board_border_size,
+        marker_square_size_ratio,
+        ..
+    } = *pattern;
+    let paper_size = board_size.as_meters();
+    let border_size = board_border_size.as_meters();
+    let ratio = marker_square_size_ratio.raw();
+    let mut violations = vec![];
+
+    if paper_size <= 0.0 {
+        violations.push(Violation::NonPositiveLength {
+            name: "marker paper size",
+            length: paper_size,
+        });
+    }
+    if border_size < 0.0 || paper_size - 2.0 * border_size <= 0.0 {
+        violations.push(Violation::MarkerBorderTooWide {
+            paper_size,
+            border_size,
+        });
+    }
+    if ratio <= 0.0 || ratio > 1.0 {
+        violations.push(Violation::InvalidMarkerRatio { ratio });
+    }
+
+    let (num_rows, num_cols) = (pattern.num_rows(), pattern.num_cols());
+    if num_rows == 0 || num_cols == 0 {
+        violations.push(Violation::EmptyMarkerGrid { num_rows, num_cols });
+    }
+
+    let num_squares = pattern.num_markers();
+    if marker_ids.len() != num_squares {
+        violations.push(Violation::MarkerCountMismatch {
+            num_markers: marker_ids.len(),
+            num_squares,
+        });
+    }
+
+    violations
+}
+
+/// Checks a calibration pattern. Multi-ArUco patterns are checked like
+/// [check_aruco_pattern], and the other kinds only need positive paper
+/// and marker sizes.
+pub fn check_calibration_pattern(pattern: &CalibrationPattern) -> Vec<Violation> {
+    let pattern = match pattern {
+        CalibrationPattern::MultiAruco(pattern) => return check_aruco_pattern(pattern),
+        pattern => pattern,
+    };
+
+    let lengths = [
+        ("marker paper size", pattern.paper_size().as_meters()),
+        ("marker size", pattern.marker_size().as_meters()),
+    ];
+    lengths
+        .into_iter()
+        .filter(|&(_, length)| length <= 0.0)
+        .map(|(name, length)| Violation::NonPositiveLength { name, length })
+        .collect()
+}
+
+/// Checks a calibration pattern like [check_calibration_pattern].
+pub fn validate_calibration_pattern(pattern: &CalibrationPattern) -> Result<(), Violations> {
+    Violations(check_calibration_pattern(pattern)).into_result()
+}
+
+/// Checks a marker pattern like [check_aruco_pattern].
+pub fn validate_aruco_pattern(pattern: &MultiArucoPattern) -> Result<(), Violations> {
+    Violations(check_aruco_pattern(pattern)).into_result()
+}
+
+/// Checks a target and a marker pattern, and that the pattern can be
+/// placed on the target as the marker paper of a [BoardModel].
+pub fn validate_target<S>(target: &S, pattern: &MultiArucoPattern) -> Result<(), Violations>
+where
+    S: TargetGeometry + ?Sized,
+{
+    let mut violations = check_target(target, pattern.paper_size());
+    violations.extend(check_aruco_pattern(pattern));
+    Violations(violations).into_result()
+}
+
+impl<S> BoardModel<S>
+where
+    S: TargetGeometry,
+{
+    /// Checks the target and the marker pattern like [validate_target],
+    /// and that the marker paper size agrees with the pattern.
+    pub fn validate(&self, pattern: &MultiArucoPattern) -> Result<(), Violations> {
+        let mut violations = check_aruco_pattern(pattern);
+        violations.extend(self.check_placement(pattern));
+        Violations(violations).into_result()
+    }
+
+    /// Checks the target against the paper of any calibration pattern,
+    /// and that the marker paper size agrees with the pattern. The
+    /// pattern itself is checked by [validate_calibration_pattern].
+    pub fn validate_pattern<P>(&self, pattern: &P) -> Result<(), Violations>
+    where
+        P: PatternGeometry + ?Sized,
+    {
+        Violations(self.check_placement(pattern)).into_result()
+    }
+
+    fn check_placement<P>(&self, pattern: &P) -> Vec<Violation>
+    where
+        P: PatternGeometry + ?Sized,
+    {
+        let mut violations = check_target(&self.board_shape, pattern.paper_size());
+
+        let model_size = self.marker_paper_size.as_meters();
+        let pattern_size = pattern.paper_size().as_meters();
+        if (model_size - pattern_size).abs() > EPS_LENGTH {
+            violations.push(Violation::MarkerPaperSizeMismatch {
+                model_size,
+                pattern_size,
+            });
+        }
+
+        violations
+    }
+}
@@ -0,0 +1,44 @@
+#![allow(dead_code)]
+
+use aruco_config::{ArucoDictionary, MultiArucoPattern};
+use hollow_board_config::{BoardModel, BoardShape, TargetGeometry};
+use measurements::Length;
+use nalgebra as na;
+use noisy_float::prelude::*;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+pub fn board_model(pose: na::Isometry3<f64>) -> BoardModel {
+    BoardModel {
+        pose,
+        marker_paper_size: Length::from_millimeters(500.0),
+        board_shape: BoardShape {
+            board_width: Length::from_millimeters(1000.0),
+            hole_radius: Length::from_millimeters(150.0),
+            hole_center_shift: Length::from_millimeters(200.0),
+        },
+    }
+}
+
+/// Places the board as a diamond facing the -x direction with its
+/// center at the given point.
+pub fn diamond_pose(center: na::Point3<f64>) -> na::Isometry3<f64> {
+    let target = board_model(na::Isometry3::identity()).board_shape;
+    let rotation = na::UnitQuaternion::from_euler_angles(0.0, -FRAC_PI_2, 0.0)
+        * na::UnitQuaternion::from_euler_angles(0.0, 0.0, -FRAC_PI_4);
+    let target_center = target.center();
+    let translation = center - rotation * na::Point3::new(target_center.x, target_center.y, 0.0);
+    na::Isometry3::from_parts(translation.into(), rotation)
+}
+
+pub fn pattern() -> MultiArucoPattern {
+    MultiArucoPattern {
+        marker_ids: vec![696, 64, 306, 195],
+        dictionary: ArucoDictionary::DICT_5X5_1000,
+        board_size: Length::from_millimeters(500.0),
+        board_border_size: Length::from_millimeters(10.0),
+        marker_square_size_ratio: r64(0.8),
+        num_squares_per_side: 2,
+        marker_grid: None,
+        border_bits: 1,
+    }
+}
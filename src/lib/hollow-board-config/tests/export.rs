@@ -0,0 +1,90 @@
+mod common;
+
+use common::{board_model, diamond_pose};
+use hollow_board_config::{CorrespondenceIndex, TargetGeometry, TargetRegion};
+use nalgebra as na;
+use std::f64::consts::TAU;
+
+#[test]
+fn export_mesh_with_holes_cut_out() {
+    let truth = board_model(diamond_pose(na::Point3::new(5.0, 0.3, 0.1)));
+    let hole_segments = 64;
+    let mesh = truth.to_mesh(hole_segments);
+
+    // the holes are regular polygons inscribed in the circles
+    let hole_area: f64 = truth
+        .board_shape
+        .holes()
+        .iter()
+        .map(|hole| {
+            let segments = hole_segments as f64;
+            segments * hole.radius.powi(2) * (TAU / segments).sin() / 2.0
+        })
+        .sum();
+    let face_area = truth.board_shape.width() * truth.board_shape.height() - hole_area;
+    let area = mesh.board.area() + mesh.marker_paper.area();
+    assert!((area - face_area).abs() < 1e-9, "mesh area {area}");
+
+    let paper_size = truth.marker_paper_size.as_meters();
+    assert!((mesh.marker_paper.area() - paper_size.powi(2)).abs() < 1e-9);
+
+    // all vertices are on the posed target plane
+    let inverse_pose = truth.pose.inverse();
+    for vertex in mesh
+        .board
+        .vertices
+        .iter()
+        .chain(&mesh.marker_paper.vertices)
+    {
+        assert!((inverse_pose * vertex).z.abs() < 1e-9);
+    }
+
+    let mut ply = vec![];
+    mesh.write_ply(&mut ply).unwrap();
+    let ply = String::from_utf8(ply).unwrap();
+    let num_vertices = mesh.board.vertices.len() + mesh.marker_paper.vertices.len();
+    let num_triangles = mesh.board.triangles.len() + mesh.marker_paper.triangles.len();
+    assert!(ply.contains(&format!("element vertex {num_vertices}\n")));
+    assert!(ply.contains(&format!("element face {num_triangles}\n")));
+
+    let mut obj = vec![];
+    mesh.write_obj(&mut obj).unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+    assert_eq!(
+        obj.lines().filter(|line| line.starts_with("v ")).count(),
+        num_vertices
+    );
+    assert_eq!(
+        obj.lines().filter(|line| line.starts_with("f ")).count(),
+        num_triangles
+    );
+}
+
+#[test]
+fn sample_points_on_target_face() {
+    let truth = board_model(diamond_pose(na::Point3::new(5.0, 0.3, 0.1)));
+    let spacing = 0.01;
+    let samples = truth.sample_points(spacing);
+    assert_eq!(samples.points.len(), samples.on_marker_paper.len());
+
+    let index = CorrespondenceIndex::new(&truth.board_shape);
+    let inverse_pose = truth.pose.inverse();
+    for point in &samples.points {
+        let local = inverse_pose * point;
+        assert!(local.z.abs() < 1e-9);
+        assert_eq!(index.region(&local.xy()), TargetRegion::Face);
+    }
+
+    // the sample density matches the face area
+    let mesh = truth.to_mesh(64);
+    let face_area = mesh.board.area() + mesh.marker_paper.area();
+    let expected = face_area / spacing.powi(2);
+    let num_samples = samples.points.len() as f64;
+    assert!((num_samples - expected).abs() / expected < 0.02);
+
+    let num_on_paper = samples.on_marker_paper.iter().filter(|&&on| on).count() as f64;
+    let paper_size = truth.marker_paper_size.as_meters();
+    assert!((num_on_paper * spacing.powi(2) - paper_size.powi(2)).abs() < 1e-9);
+
+    assert!(truth.sample_points(0.0).points.is_empty());
+}
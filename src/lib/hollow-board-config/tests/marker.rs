@@ -0,0 +1,115 @@
+mod common;
+
+use aruco_config::{CalibrationPattern, MarkerGrid, MultiArucoPattern};
+use common::{board_model, diamond_pose, pattern};
+use nalgebra as na;
+
+#[test]
+fn multi_marker_corners_on_grids() {
+    let model = board_model(diamond_pose(na::Point3::new(5.0, 0.3, 0.1)));
+    let inverse_pose = model.pose.inverse();
+    let local_corners = |pattern: &MultiArucoPattern| -> Vec<Vec<na::Point2<f64>>> {
+        model
+            .multi_marker_corners(pattern)
+            .into_iter()
+            .map(|corners| {
+                corners
+                    .into_iter()
+                    .map(|corner| (inverse_pose * corner).xy())
+                    .collect()
+            })
+            .collect()
+    };
+    let assert_marker = |marker: &[na::Point2<f64>], [x, y]: [f64; 2], size: f64| {
+        let expected = [[x, y + size], [x + size, y + size], [x + size, y], [x, y]];
+        for (corner, [ex, ey]) in marker.iter().zip(expected) {
+            assert!((corner - na::Point2::new(ex, ey)).norm() < 1e-9);
+        }
+    };
+
+    // the 2x2 grid on the 500mm paper with 10mm border has 240mm
+    // squares, and the markers are centered in the squares
+    let corners = local_corners(&pattern());
+    assert_eq!(corners.len(), 4);
+    let bottom_corners = [
+        [0.034, 0.034],
+        [0.274, 0.034],
+        [0.034, 0.274],
+        [0.274, 0.274],
+    ];
+    for (marker, bottom) in corners.iter().zip(bottom_corners) {
+        assert_marker(marker, bottom, 0.192);
+    }
+
+    // the 2x3 grid has 160mm squares, and the rows are shifted by half
+    // a square to center the grid on the paper
+    let pattern = MultiArucoPattern {
+        marker_ids: (0..6).collect(),
+        marker_grid: Some(MarkerGrid { rows: 2, cols: 3 }),
+        ..pattern()
+    };
+    let corners = local_corners(&pattern);
+    assert_eq!(corners.len(), 6);
+    for (index, marker) in corners.iter().enumerate() {
+        let (row, col) = (index / 3, index % 3);
+        let bottom = [0.026 + 0.16 * col as f64, 0.106 + 0.16 * row as f64];
+        assert_marker(marker, bottom, 0.128);
+    }
+}
+
+#[test]
+fn multi_marker_grid_is_centered_on_paper() {
+    let model = board_model(diamond_pose(na::Point3::new(5.0, 0.3, 0.1)));
+    let pattern = MultiArucoPattern {
+        marker_ids: (0..6).collect(),
+        marker_grid: Some(MarkerGrid { rows: 2, cols: 3 }),
+        ..pattern()
+    };
+    let corners = model.multi_marker_corners(&pattern);
+
+    // the ArUco detector poses the grid by the square centers relative
+    // to the paper center, which must be the marker centers on the model
+    let paper_center = model.marker_center();
+    for (index, marker) in corners.iter().enumerate() {
+        let (row, col) = pattern.marker_grid_position(index);
+        let [x, y] = pattern.square_center_offset(row, col);
+        let expected = paper_center
+            + model.board_x_axis().scale(x.as_meters())
+            + model.board_y_axis().scale(y.as_meters());
+        let center = marker
+            .iter()
+            .fold(na::Vector3::zeros(), |sum, corner| sum + corner.coords)
+            / 4.0;
+        assert!((center - expected.coords).norm() < 1e-9);
+    }
+
+    let grid_center = corners
+        .iter()
+        .flatten()
+        .fold(na::Vector3::zeros(), |sum, corner| sum + corner.coords)
+        / (corners.len() * 4) as f64;
+    assert!((grid_center - paper_center.coords).norm() < 1e-9);
+}
+
+#[test]
+fn pattern_marker_corners_match_multi_marker_corners() {
+    // the extrinsic solver pairs image markers with these corners, so
+    // the corners of a multi-ArUco pattern must not change
+    let model = board_model(diamond_pose(na::Point3::new(5.0, 0.3, 0.1)));
+    let grid_pattern = MultiArucoPattern {
+        marker_ids: (0..6).collect(),
+        marker_grid: Some(MarkerGrid { rows: 2, cols: 3 }),
+        ..pattern()
+    };
+
+    for pattern in [pattern(), grid_pattern] {
+        let expected = model.multi_marker_corners(&pattern);
+        let corners = model.pattern_marker_corners(&CalibrationPattern::MultiAruco(pattern));
+        assert_eq!(corners.len(), expected.len());
+        for (corners, expected) in corners.iter().zip(&expected) {
+            for (corner, expected) in corners.iter().zip(expected) {
+                assert!((corner - expected).norm() < 1e-9);
+            }
+        }
+    }
+}
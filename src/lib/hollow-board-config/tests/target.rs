@@ -0,0 +1,90 @@
+mod common;
+
+use common::{board_model, diamond_pose};
+use hollow_board_config::{BoardModel, CorrespondenceIndex, TargetGeometry, TargetRegion};
+use nalgebra as na;
+
+#[test]
+fn hollow_board_geometry_matches_model() {
+    let model = board_model(diamond_pose(na::Point3::new(5.0, 0.1, 0.2)));
+
+    let corners = [
+        model.bottom_corner(),
+        model.left_corner(),
+        model.top_corner(),
+        model.right_corner(),
+    ];
+    for (lhs, rhs) in model.corner_points().iter().zip(corners) {
+        assert!((lhs - rhs).norm() < 1e-9);
+    }
+
+    let hole_centers = [
+        model.left_circle_center(),
+        model.right_circle_center(),
+        model.top_circle_center(),
+    ];
+    for (index, center) in hole_centers.into_iter().enumerate() {
+        assert!((model.hole_center(index).unwrap() - center).norm() < 1e-9);
+    }
+    assert_eq!(model.hole_center(3), None);
+    assert_eq!(model.feature_points().len(), 7);
+}
+
+#[test]
+fn correspondence_index_matches_geometry() {
+    let model = board_model(diamond_pose(na::Point3::new(5.0, 0.1, 0.2)));
+    let target = &model.board_shape;
+    let holes = target.holes();
+    let index = CorrespondenceIndex::new(target);
+
+    // sample the target plane beyond the border
+    let samples: Vec<_> = (0..=60)
+        .flat_map(|row| {
+            (0..=60)
+                .map(move |col| na::Point2::new(-0.1 + col as f64 * 0.02, -0.1 + row as f64 * 0.02))
+        })
+        .collect();
+    for &point in &samples {
+        let (corresponding, region) = index.corresponding_point(&point);
+        assert!((corresponding - target.corresponding_point(&point)).norm() < 1e-12);
+
+        let boundary = index.closest_boundary_point(&point);
+        let expected = target.closest_boundary_point(&point);
+        assert!(((boundary - point).norm() - (expected - point).norm()).abs() < 1e-12);
+
+        let expected_region = if corresponding != point {
+            match holes
+                .iter()
+                .position(|hole| (point - hole.center).norm() < hole.radius)
+            {
+                Some(hole) => TargetRegion::Hole(hole),
+                None => TargetRegion::OuterEdge,
+            }
+        } else {
+            TargetRegion::Face
+        };
+        assert_eq!(region, expected_region, "point {point}");
+    }
+
+    // the index is reused at another pose on points scattered around
+    // the target plane
+    let pose = model.pose * na::Isometry3::translation(0.05, -0.02, 0.01);
+    let points: Vec<_> = samples
+        .iter()
+        .enumerate()
+        .map(|(idx, point)| {
+            model.pose * na::Point3::new(point.x, point.y, 0.01 * (idx as f64).sin())
+        })
+        .collect();
+    let mut correspondences = vec![];
+    index.find_correspondences_into(&pose, &points, &mut correspondences);
+    let moved = BoardModel {
+        pose,
+        ..model.clone()
+    };
+    let expected = moved.find_correspondences(&points);
+    assert_eq!(correspondences.len(), expected.len());
+    for (correspondence, (_, expected)) in correspondences.iter().zip(expected) {
+        assert!((correspondence.target_point(&pose) - expected).norm() < 1e-12);
+    }
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use aruco_config::{CalibrationPattern, MarkerGrid, MultiArucoPattern};
+use common::{board_model, diamond_pose, pattern};
+use hollow_board_config::{
+    check_target, validate_aruco_pattern, validate_calibration_pattern, validate_target,
+    BoardShape, HoleShape, HoledRectangle, Violation, Violations,
+};
+use measurements::Length;
+use nalgebra as na;
+use noisy_float::prelude::*;
+
+fn board_shape(board_width: f64, hole_radius: f64, hole_center_shift: f64) -> BoardShape {
+    BoardShape {
+        board_width: Length::from_millimeters(board_width),
+        hole_radius: Length::from_millimeters(hole_radius),
+        hole_center_shift: Length::from_millimeters(hole_center_shift),
+    }
+}
+
+#[test]
+fn holes_outside_board() {
+    // the hole center shift is mistyped in centimeters
+    let target = board_shape(1000.0, 150.0, 2000.0);
+    let violations = check_target(&target, Length::from_millimeters(500.0));
+
+    let outside: Vec<usize> = violations
+        .iter()
+        .filter_map(|violation| match *violation {
+            Violation::HoleOutsideBoard { hole, overshoot } => {
+                assert!((overshoot - 1.65).abs() < 1e-9);
+                Some(hole)
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(outside, [0, 1, 2]);
+}
+
+#[test]
+fn holes_overlap_each_other_and_marker_paper() {
+    let target = board_shape(1000.0, 250.0, 200.0);
+    let violations = check_target(&target, Length::from_millimeters(500.0));
+
+    // the top hole is 400mm from the others
+    let overlapping: Vec<(usize, usize)> = violations
+        .iter()
+        .filter_map(|violation| match *violation {
+            Violation::HolesOverlap {
+                first,
+                second,
+                overlap,
+            } => {
+                assert!((overlap - 0.1).abs() < 1e-9);
+                Some((first, second))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(overlapping, [(0, 2), (1, 2)]);
+
+    // the left and the right hole are 200mm from the paper
+    let cut_holes: Vec<usize> = violations
+        .iter()
+        .filter_map(|violation| match *violation {
+            Violation::HoleOverlapsMarkerPaper { hole, overlap } => {
+                assert!((overlap - 0.05).abs() < 1e-9);
+                Some(hole)
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(cut_holes, [0, 1]);
+}
+
+#[test]
+fn marker_paper_exceeds_board() {
+    let target = HoledRectangle {
+        width: Length::from_millimeters(1200.0),
+        height: Length::from_millimeters(400.0),
+        holes: vec![HoleShape {
+            x: Length::from_millimeters(900.0),
+            y: Length::from_millimeters(200.0),
+            radius: Length::from_millimeters(100.0),
+        }],
+    };
+    let violations = check_target(&target, Length::from_millimeters(500.0));
+    assert!(matches!(
+        violations[..],
+        [Violation::MarkerPaperExceedsBoard { height, .. }] if (height - 0.4).abs() < 1e-9
+    ));
+}
+
+#[test]
+fn invalid_aruco_pattern() {
+    let aruco_pattern = MultiArucoPattern {
+        marker_ids: vec![696, 64, 306],
+        board_border_size: Length::from_millimeters(300.0),
+        marker_square_size_ratio: r64(1.5),
+        num_squares_per_side: 3,
+        ..pattern()
+    };
+
+    let Violations(violations) = validate_aruco_pattern(&aruco_pattern).unwrap_err();
+    assert!(matches!(
+        violations[..],
+        [
+            Violation::MarkerBorderTooWide { .. },
+            Violation::InvalidMarkerRatio { .. },
+            Violation::MarkerCountMismatch {
+                num_markers: 3,
+                num_squares: 9,
+            },
+        ]
+    ));
+
+    // rectangular grids are counted by rows and columns
+    let aruco_pattern = MultiArucoPattern {
+        marker_ids: (0..6).collect(),
+        marker_grid: Some(MarkerGrid { rows: 2, cols: 3 }),
+        ..pattern()
+    };
+    validate_target(&board_shape(1000.0, 150.0, 200.0), &aruco_pattern).unwrap();
+
+    let aruco_pattern = MultiArucoPattern {
+        marker_ids: vec![],
+        marker_grid: Some(MarkerGrid { rows: 0, cols: 3 }),
+        ..pattern()
+    };
+    let Violations(violations) = validate_aruco_pattern(&aruco_pattern).unwrap_err();
+    assert_eq!(
+        violations,
+        [Violation::EmptyMarkerGrid {
+            num_rows: 0,
+            num_cols: 3
+        }]
+    );
+}
+
+#[test]
+fn board_model_paper_size_mismatch() {
+    let model = board_model(diamond_pose(na::Point3::new(5.0, 0.0, 0.0)));
+    let aruco_pattern = MultiArucoPattern {
+        board_size: Length::from_millimeters(400.0),
+        ..pattern()
+    };
+
+    let error = model.validate(&aruco_pattern).unwrap_err();
+    assert!(matches!(
+        error.0[..],
+        [Violation::MarkerPaperSizeMismatch { pattern_size, .. }]
+            if (pattern_size - 0.4).abs() < 1e-9
+    ));
+    assert!(error.to_string().contains("500.0mm marker paper"));
+}
+
+#[test]
+fn validate_calibration_patterns() {
+    let model = board_model(diamond_pose(na::Point3::new(5.0, 0.0, 0.0)));
+
+    // multi-ArUco patterns are checked like before
+    let multi_aruco = CalibrationPattern::MultiAruco(MultiArucoPattern {
+        marker_ids: vec![1, 2, 3],
+        ..pattern()
+    });
+    let Violations(violations) = validate_calibration_pattern(&multi_aruco).unwrap_err();
+    assert!(matches!(
+        violations[..],
+        [Violation::MarkerCountMismatch {
+            num_markers: 3,
+            num_squares: 4
+        }]
+    ));
+
+    // a 400mm ChArUco paper does not match the 500mm paper of the model
+    let charuco: CalibrationPattern = json5::from_str(
+        r#"{
+            "kind": "charuco",
+            "dictionary": "DICT_5X5_1000",
+            "squares": { "rows": 5, "cols": 5 },
+            "paper_size": "400mm",
+            "border_size": "10mm",
+            "marker_square_size_ratio": 0.75,
+            "border_bits": 1,
+        }"#,
+    )
+    .unwrap();
+    validate_calibration_pattern(&charuco).unwrap();
+    let Violations(violations) = model.validate_pattern(&charuco).unwrap_err();
+    assert!(matches!(
+        violations[..],
+        [Violation::MarkerPaperSizeMismatch { pattern_size, .. }]
+            if (pattern_size - 0.4).abs() < 1e-9
+    ));
+}
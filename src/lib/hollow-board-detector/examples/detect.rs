@@ -1,16 +1,25 @@
 use anyhow::Result;
 use aruco_config::MultiArucoPattern;
 use clap::Parser;
-use hollow_board_detector::{Config, Detector};
+use hollow_board_detector::{Config, DetectionTrace, Detector};
 use nalgebra as na;
 use pcd_rs::DynReader;
-use std::path::PathBuf;
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
 
 /// Detect the hollow-board from a point cloud.
 #[derive(Parser)]
 struct Args {
     /// The path to the input .pcd point cloud file.
     pub input_file: PathBuf,
+
+    /// Save the detection trace to this file. It is written in
+    /// MessagePack if the extension is .msgpack, or in JSON otherwise.
+    #[arg(long)]
+    pub trace: Option<PathBuf>,
 }
 
 fn main() -> Result<()> {
@@ -19,6 +28,7 @@ fn main() -> Result<()> {
     // Configure and build the detector
     let aruco_pattern: MultiArucoPattern = json5::from_str(include_str!("aruco_pattern.json5"))?;
     let config: Config = json5::from_str(include_str!("board_detector.json5"))?;
+    config.validate(&aruco_pattern)?;
     let detector = Detector::new(config, aruco_pattern);
 
     // Load the point cloud file.
@@ -31,14 +41,30 @@ fn main() -> Result<()> {
         .collect();
 
     // Perform detection
-    let detection = detector.detect(&points)?;
+    let detection = match &args.trace {
+        Some(trace_file) => {
+            let trace = detector.detect_traced(&points);
+            save_trace(&trace, trace_file)?;
+            trace.result
+        }
+        None => detector.detect(&points),
+    };
 
     // Show the detection results
-    if let Some(detection) = detection {
-        println!("{:?}", detection.board_model);
-    } else {
-        println!("No board detected");
+    match detection {
+        Ok(detection) => println!("{:?}", detection.board_model),
+        Err(failure) => println!("No board detected: {failure}"),
     }
 
     Ok(())
 }
+
+fn save_trace(trace: &DetectionTrace, path: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    if path.extension().is_some_and(|ext| ext == "msgpack") {
+        trace.write_msgpack(&mut writer)?;
+    } else {
+        trace.write_json(&mut writer)?;
+    }
+    Ok(())
+}
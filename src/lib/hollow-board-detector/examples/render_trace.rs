@@ -0,0 +1,120 @@
+use anyhow::Result;
+use clap::Parser;
+use hollow_board_config::BoardModel;
+use hollow_board_detector::DetectionTrace;
+use kiss3d::{
+    light::Light,
+    nalgebra as na30,
+    window::{State, Window},
+};
+use nalgebra as na;
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+/// Replay a detection trace saved by the detect example.
+#[derive(Parser)]
+struct Args {
+    /// The trace file in JSON, or in MessagePack if the extension is
+    /// .msgpack.
+    pub trace_file: PathBuf,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    // Load the trace
+    let reader = BufReader::new(File::open(&args.trace_file)?);
+    let trace: DetectionTrace = if args
+        .trace_file
+        .extension()
+        .is_some_and(|ext| ext == "msgpack")
+    {
+        DetectionTrace::read_msgpack(reader)?
+    } else {
+        DetectionTrace::read_json(reader)?
+    };
+
+    // Color the inliers of accepted planes in green and the others in red
+    let planes: Vec<_> = trace
+        .attempts
+        .iter()
+        .map(|attempt| {
+            let color = match &attempt.failure {
+                Some(failure) => {
+                    println!("Plane rejected: {failure}");
+                    na30::Point3::new(1.0, 0.0, 0.0)
+                }
+                None => na30::Point3::new(0.0, 1.0, 0.0),
+            };
+            (convert_points(&attempt.plane.inlier_points), color)
+        })
+        .collect();
+
+    // Replay the board poses of fitting iterations
+    let board_models = match trace.result {
+        Ok(detection) => {
+            let mut board_models = detection.icp_data.board_model_history();
+            board_models.push(detection.board_model);
+            board_models
+        }
+        Err(failure) => {
+            println!("No board detected: {failure}");
+            vec![]
+        }
+    };
+
+    let mut window = Window::new("Detection trace");
+    window.set_light(Light::StickToCamera);
+
+    let state = Gui {
+        points: convert_points(&trace.points),
+        planes,
+        board_models,
+        step: 0,
+    };
+    window.render_loop(state);
+
+    Ok(())
+}
+
+fn convert_points(points: &[na::Point3<f64>]) -> Vec<na30::Point3<f32>> {
+    points
+        .iter()
+        .map(|point| {
+            let point: [f64; 3] = (*point).into();
+            na30::convert(na30::Point3::from(point))
+        })
+        .collect()
+}
+
+struct Gui {
+    points: Vec<na30::Point3<f32>>,
+    planes: Vec<(Vec<na30::Point3<f32>>, na30::Point3<f32>)>,
+    board_models: Vec<BoardModel>,
+    step: usize,
+}
+
+impl State for Gui {
+    fn step(&mut self, window: &mut Window) {
+        // Draw points
+        self.points.iter().for_each(|point| {
+            window.draw_point(point, &na30::Point3::new(0.5, 0.5, 0.5));
+        });
+
+        // Draw plane inliers
+        self.planes.iter().for_each(|(points, color)| {
+            points
+                .iter()
+                .for_each(|point| window.draw_point(point, color));
+        });
+
+        // Draw the board one fitting iteration per frame, and keep the
+        // final board once the replay ends
+        if let Some(board_model) = self
+            .board_models
+            .get(self.step.min(self.board_models.len().saturating_sub(1)))
+        {
+            board_model.render_kiss3d(window);
+        }
+        self.step += 1;
+    }
+}
@@ -0,0 +1,147 @@
+//! Multi-frame point accumulation for static boards.
+//!
+//! A sparse LiDAR hits only a few scan rings on the board in a frame.
+//! If the board is held still, the points of consecutive frames can be
+//! merged before detection to cover more of the holes and edges.
+
+use hollow_board_config::{BoardModel, CorrespondenceIndex, TargetGeometry};
+use nalgebra as na;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+/// Accumulates points over the latest frames.
+#[derive(Debug, Clone)]
+pub struct FrameAccumulator {
+    /// The maximum number of kept frames. The oldest frame is dropped
+    /// when a new frame comes.
+    pub max_frames: usize,
+    /// The voxel size in meters used to merge nearby points. Points
+    /// are kept as they are if it is `None`.
+    pub voxel_size: Option<f64>,
+    region: Option<BoardRegion>,
+    frames: VecDeque<Vec<na::Point3<f64>>>,
+}
+
+#[derive(Debug, Clone)]
+struct BoardRegion {
+    board_model: BoardModel<Arc<dyn TargetGeometry + Send + Sync>>,
+    index: CorrespondenceIndex,
+    gate_distance: f64,
+}
+
+impl FrameAccumulator {
+    pub fn new(max_frames: usize, voxel_size: Option<f64>) -> Self {
+        Self {
+            max_frames,
+            voxel_size,
+            region: None,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Keeps only points within the gate distance in meters of the
+    /// board. Points of already accumulated frames are filtered as
+    /// well.
+    pub fn focus_on<S>(&mut self, board_model: BoardModel<S>, gate_distance: f64)
+    where
+        S: TargetGeometry + Send + Sync + 'static,
+    {
+        let BoardModel {
+            pose,
+            marker_paper_size,
+            board_shape,
+        } = board_model;
+        let region = BoardRegion {
+            index: CorrespondenceIndex::new(&board_shape),
+            board_model: BoardModel {
+                pose,
+                marker_paper_size,
+                board_shape: Arc::new(board_shape),
+            },
+            gate_distance,
+        };
+        for frame in &mut self.frames {
+            *frame = region.select(frame);
+        }
+        self.region = Some(region);
+    }
+
+    /// Adds the points of a new frame.
+    pub fn push(&mut self, points: &[na::Point3<f64>]) {
+        let points = match &self.region {
+            Some(region) => region.select(points),
+            None => points.to_vec(),
+        };
+        self.frames.push_back(points);
+
+        while self.frames.len() > self.max_frames {
+            self.frames.pop_front();
+        }
+    }
+
+    /// The number of accumulated frames.
+    pub fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Checks if `max_frames` frames are accumulated.
+    pub fn is_full(&self) -> bool {
+        self.frames.len() >= self.max_frames
+    }
+
+    /// Drops all accumulated frames. The focused region is kept.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Merges the points of accumulated frames.
+    ///
+    /// If `voxel_size` is set, the points in the same voxel are merged
+    /// into their centroid. The points are ordered by the first point
+    /// falling into each voxel, so the result does not depend on hash
+    /// order.
+    pub fn points(&self) -> Vec<na::Point3<f64>> {
+        let points = self.frames.iter().flatten();
+
+        let voxel_size = match self.voxel_size {
+            Some(size) => size,
+            None => return points.copied().collect(),
+        };
+
+        let mut voxel_indices: HashMap<[i64; 3], usize> = HashMap::new();
+        let mut voxels: Vec<(na::Vector3<f64>, usize)> = vec![];
+
+        for point in points {
+            let key: [i64; 3] = point
+                .coords
+                .map(|val| (val / voxel_size).floor() as i64)
+                .into();
+            let index = *voxel_indices.entry(key).or_insert_with(|| {
+                voxels.push((na::Vector3::zeros(), 0));
+                voxels.len() - 1
+            });
+            let (sum, count) = &mut voxels[index];
+            *sum += point.coords;
+            *count += 1;
+        }
+
+        voxels
+            .into_iter()
+            .map(|(sum, count)| na::Point3::from(sum / count as f64))
+            .collect()
+    }
+}
+
+impl BoardRegion {
+    fn select(&self, points: &[na::Point3<f64>]) -> Vec<na::Point3<f64>> {
+        self.board_model
+            .find_correspondences_with(&self.index, points)
+            .into_iter()
+            .filter_map(|(point, corresponding_point)| {
+                ((point - corresponding_point).norm() <= self.gate_distance).then_some(*point)
+            })
+            .collect()
+    }
+}
@@ -1,212 +1,401 @@
 use crate::{
     config::Config,
-    detection::{FitBoardIcp, FitPlaneRansac, IcpData, PlaneRansacData},
+    consensus::{ConsensusStrategy, LoRansac, Msac, Prosac},
+    detection::{FitBoardIcp, FitPlaneRansac, IcpData, LmData, PlaneRansacData},
+    failure::DetectionFailure,
+    lm::{LmRefiner, LmSolution},
+    sensor::SensorParams,
 };
-use anyhow::Result;
 use approx::abs_diff_eq;
 use arrsac::Arrsac;
 use aruco_config::MultiArucoPattern;
-use hollow_board_config::{BoardModel, BoardShape};
+use hollow_board_config::{BoardModel, CorrespondenceIndex, TargetGeometry};
 use itertools::izip;
-use nalgebra::{Isometry3, Point3, Quaternion, Translation3, UnitQuaternion, Vector3};
+use nalgebra::{
+    Isometry3, Matrix3, Matrix6, Point3, Quaternion, Translation3, Unit, UnitQuaternion, Vector3,
+};
 use newslab_geom_algo::{self, centroid_of_points, kabsch, IJKW, XYZ};
 use noisy_float::prelude::*;
-use plane_estimator::{PlaneEstimator, PlaneModel};
-use sample_consensus::Consensus;
+use plane_estimator::{
+    ExtractedPlane, MultiPlaneExtractor, PlaneExtent, PlaneModel, PlaneQuality, PlaneRefiner,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
 use std::{
     borrow::Borrow,
     f64::{
         self,
-        consts::{FRAC_PI_2, FRAC_PI_4, PI},
+        consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU},
     },
+    mem,
 };
 
-unzip_n::unzip_n!(2);
-
 const EPS_F64: f64 = 1e-4;
 
+/// The Kabsch algorithm requires at least 3 point pairs.
+const ICP_MIN_POINTS: usize = 3;
+
 /// Fits a plane in a point set using RANSAC algorithm.
+///
+/// The minimal-sample plane found by RANSAC is refitted against all
+/// inliers if `plane_refine_loss` is set.
 pub fn fit_plane_ransac<'a>(
     board_detector: &Config,
     points: &'a [Point3<f64>],
-) -> Result<Option<FitPlaneRansac<'a>>> {
+) -> Result<FitPlaneRansac<'a>, DetectionFailure> {
+    let planes = extract_planes(board_detector, points, 1);
+    planes.into_iter().next().ok_or(DetectionFailure::NoPlane {
+        num_points: points.len(),
+    })
+}
+
+/// Fits up to `plane_ransac_max_planes` planes one after another. The
+/// inliers of each found plane are removed before searching the next
+/// one.
+pub fn fit_planes_ransac<'a>(
+    board_detector: &Config,
+    points: &'a [Point3<f64>],
+) -> Result<Vec<FitPlaneRansac<'a>>, DetectionFailure> {
+    let planes = extract_planes(
+        board_detector,
+        points,
+        board_detector.plane_ransac_max_planes,
+    );
+    if planes.is_empty() {
+        return Err(DetectionFailure::NoPlane {
+            num_points: points.len(),
+        });
+    }
+    Ok(planes)
+}
+
+/// Checks if the plane quality can match the target shape.
+///
+/// The inliers must be planar enough, and their extent on the plane
+/// must fit in the target diagonal while covering a part of the
+/// shorter target side.
+pub fn plane_matches_board(
+    board_detector: &Config,
+    target: &impl TargetGeometry,
+    quality: &PlaneQuality,
+) -> bool {
+    let Config {
+        plane_min_planarity,
+        plane_min_extent_ratio,
+        plane_extent_tolerance,
+        ..
+    } = *board_detector;
+    let diagonal = target.width().hypot(target.height());
+    let max_extent = diagonal * (1.0 + plane_extent_tolerance);
+    let min_extent = target.width().min(target.height()) * plane_min_extent_ratio;
+    let PlaneExtent {
+        major_length,
+        minor_length,
+        ..
+    } = quality.extent;
+
+    quality.planarity >= plane_min_planarity
+        && major_length <= max_extent
+        && minor_length <= max_extent
+        && major_length >= min_extent
+}
+
+fn extract_planes<'a>(
+    board_detector: &Config,
+    points: &'a [Point3<f64>],
+    max_planes: usize,
+) -> Vec<FitPlaneRansac<'a>> {
     let Config {
         plane_ransac_inlier_threshold,
         plane_ransac_max_iterations,
+        plane_ransac_strategy,
+        plane_ransac_seed,
+        plane_refine_loss,
         ..
     } = *board_detector;
 
-    let mut arrsac = Arrsac::new(plane_ransac_inlier_threshold, rand::thread_rng())
-        .max_candidate_hypotheses(plane_ransac_max_iterations);
-    let estimator = PlaneEstimator::new();
-    let (plane_model, inlier_indices) = {
-        match arrsac.model_inliers(&estimator, points.iter().cloned()) {
-            Some(ret) => ret,
-            None => return Ok(None),
-        }
+    let rng = match plane_ransac_seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    };
+    let threshold = plane_ransac_inlier_threshold;
+    let max_iterations = plane_ransac_max_iterations;
+    let extractor = MultiPlaneExtractor {
+        max_planes: Some(max_planes),
+        refiner: plane_refine_loss.map(PlaneRefiner::new),
+        ..MultiPlaneExtractor::new(threshold)
     };
-    let inlier_points: Vec<_> = inlier_indices.into_iter().map(|idx| &points[idx]).collect();
 
-    let viz_msg = PlaneRansacData {
-        plane_model: plane_model.clone(),
-        inlier_points: inlier_points.iter().map(|point| **point).collect(),
+    let planes = match plane_ransac_strategy {
+        ConsensusStrategy::Arrsac => {
+            let mut arrsac = Arrsac::new(threshold, rng).max_candidate_hypotheses(max_iterations);
+            extractor.extract(&mut arrsac, points)
+        }
+        ConsensusStrategy::Msac => {
+            extractor.extract(&mut Msac::new(threshold, max_iterations, rng), points)
+        }
+        ConsensusStrategy::LoRansac => {
+            extractor.extract(&mut LoRansac::new(threshold, max_iterations, rng), points)
+        }
+        ConsensusStrategy::Prosac => {
+            extractor.extract(&mut Prosac::new(threshold, max_iterations, rng), points)
+        }
     };
 
-    Ok(Some(FitPlaneRansac {
-        plane_model,
-        inlier_points,
-        ransac_data: viz_msg,
-    }))
+    planes
+        .into_iter()
+        .filter_map(|plane| {
+            let ExtractedPlane {
+                model: plane_model,
+                inlier_indices,
+                normal_covariance,
+                ..
+            } = plane;
+            let inlier_points: Vec<_> =
+                inlier_indices.into_iter().map(|idx| &points[idx]).collect();
+            let quality = PlaneQuality::compute(&plane_model, &inlier_points, points.len())?;
+
+            let viz_msg = PlaneRansacData {
+                plane_model: plane_model.clone(),
+                inlier_points: inlier_points.iter().map(|point| **point).collect(),
+                normal_covariance,
+                quality: quality.clone(),
+            };
+
+            Some(FitPlaneRansac {
+                plane_model,
+                inlier_points,
+                quality,
+                ransac_data: viz_msg,
+            })
+        })
+        .collect()
 }
 
 /// Estimates the board pose from a point set using ICP algorithm.
-pub fn fit_board_icp(
+///
+/// The target center is placed at the inlier centroid and the target
+/// normal is aligned to the plane normal. The ICP starts from
+/// `icp_num_rotations` in-plane rotations, each also flipped, in
+/// parallel, and the solution with the least loss is kept. The loss
+/// gap to the runner-up that ends at a distinct pose is reported as the
+/// ambiguity margin.
+///
+/// The optional point weights are indexed like the inlier points and
+/// scale their contributions to the Kabsch updates.
+pub fn fit_board_icp<S>(
     board_detector: &Config,
     aruco_detector: &MultiArucoPattern,
+    target: &S,
     plane_model: &PlaneModel,
+    plane_inlier_points: &[impl Borrow<Point3<f64>> + Sync],
+    point_weights: Option<&[f64]>,
+) -> Result<FitBoardIcp<S>, DetectionFailure>
+where
+    S: TargetGeometry + Clone + Send + Sync,
+{
+    // the plane frame is undefined without points
+    if plane_inlier_points.is_empty() {
+        return Err(DetectionFailure::TooFewPoints {
+            num_inliers: 0,
+            min_points: ICP_MIN_POINTS,
+        });
+    }
+
+    let num_rotations = board_detector.icp_num_rotations.max(1);
+    let outlier_threshold = board_detector.sensor_params().outlier_threshold;
+    let target_center = target.center();
+    let (inlier_centroid, plane_normal) = board_plane_frame(plane_model, plane_inlier_points);
+    let base_rotation = aligned_board_rotation(&plane_normal);
+    let plane_axis = Unit::new_normalize(plane_normal);
+    let flip = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), PI);
+
+    let init_poses: Vec<_> = (0..num_rotations)
+        .flat_map(|index| {
+            let angle = TAU * index as f64 / num_rotations as f64;
+            let rotation = UnitQuaternion::from_axis_angle(&plane_axis, angle) * base_rotation;
+            [rotation, rotation * flip]
+        })
+        .map(|rotation| {
+            let translation =
+                inlier_centroid - rotation * Vector3::new(target_center.x, target_center.y, 0.0);
+            Isometry3::from_parts(Translation3::from(translation.coords), rotation)
+        })
+        .collect();
+
+    let results: Vec<_> = init_poses
+        .par_iter()
+        .map(|init_pose| {
+            fit_board_icp_from(
+                board_detector,
+                aruco_detector,
+                target,
+                init_pose,
+                plane_inlier_points,
+                point_weights,
+            )
+        })
+        .collect();
+
+    let mut fits = vec![];
+    let mut failures = vec![];
+    for result in results {
+        match result {
+            Ok(fit) => fits.push((min_icp_loss(&fit.icp_losses), fit)),
+            Err(failure) => failures.push(failure),
+        }
+    }
+    fits.sort_by_key(|(loss, _)| r64(*loss));
+
+    let mut fits = fits.into_iter();
+    let (best_loss, best_fit) = match fits.next() {
+        Some(best) => best,
+        None => {
+            // report the failure closest to be accepted
+            let failure = failures
+                .into_iter()
+                .min_by_key(|failure| match *failure {
+                    DetectionFailure::LossTooLarge { min_loss, .. } => r64(min_loss),
+                    _ => r64(f64::INFINITY),
+                })
+                .unwrap();
+            return Err(failure);
+        }
+    };
+
+    // A rejected start never ends at the best pose, so its loss counts
+    // as a distinct solution.
+    let runner_up_loss = {
+        let best_pose = best_fit.board_pose;
+        let distinct_losses = fits.filter_map(|(loss, fit)| {
+            let is_distinct =
+                !places_target_alike(target, &fit.board_pose, &best_pose, outlier_threshold);
+            is_distinct.then_some(loss)
+        });
+        let rejected_losses = failures.iter().filter_map(|failure| match *failure {
+            DetectionFailure::LossTooLarge { min_loss, .. } => Some(min_loss),
+            _ => None,
+        });
+        distinct_losses
+            .chain(rejected_losses)
+            .map(r64)
+            .min()
+            .map(|loss| loss.raw())
+    };
+
+    Ok(FitBoardIcp {
+        ambiguity_margin: runner_up_loss.map(|loss| loss - best_loss),
+        ..best_fit
+    })
+}
+
+/// Estimates the board pose from a point set using ICP algorithm
+/// starting from the given pose.
+///
+/// The optional point weights are indexed like the inlier points and
+/// scale their contributions to the Kabsch updates.
+pub fn fit_board_icp_from<S>(
+    board_detector: &Config,
+    aruco_detector: &MultiArucoPattern,
+    target: &S,
+    init_pose: &Isometry3<f64>,
     plane_inlier_points: &[impl Borrow<Point3<f64>>],
-) -> Result<Option<FitBoardIcp>> {
-    // find board by modified ICP algoirthm
-    const GOOD_FIT_THRESHOLD: f64 = 0.015; // velodyne 32-MR
-                                           // let good_fit_threshold = 0.1; // ouster os-1
-    const OUTLIER_THRESHOLD: f64 = 0.1;
+    point_weights: Option<&[f64]>,
+) -> Result<FitBoardIcp<S>, DetectionFailure>
+where
+    S: TargetGeometry + Clone,
+{
+    if plane_inlier_points.len() < ICP_MIN_POINTS {
+        return Err(DetectionFailure::TooFewPoints {
+            num_inliers: plane_inlier_points.len(),
+            min_points: ICP_MIN_POINTS,
+        });
+    }
 
+    // find board by modified ICP algoirthm
     let Config {
-        board_shape:
-            BoardShape {
-                board_width,
-                hole_radius,
-                hole_center_shift,
-            },
         max_icp_iterations,
         icp_pose_weight_threshold,
         icp_rejection_threshold,
         ..
     } = *board_detector;
     let marker_paper_size = aruco_detector.paper_size();
+    let SensorParams {
+        good_fit_threshold,
+        outlier_threshold,
+        ..
+    } = board_detector.sensor_params();
 
     let (board_pose, icp_losses, viz_msg) = {
-        let init_pose = {
-            let inlier_centroid: Point3<f64> =
-                centroid_of_points(plane_inlier_points.iter().map(|point| {
-                    let point: [f64; 3] = (*point.borrow()).into();
-                    point
-                }))
-                .unwrap()
-                .into();
-
-            // obtain the plane normal vector that points towards the origin
-            let plane_normal = {
-                let normal: Vector3<f64> = nalgebra::convert(*plane_model.normal);
-                if (Point3::origin() - inlier_centroid).dot(&normal) < 0.0 {
-                    -normal
-                } else {
-                    normal
-                }
-            };
-
-            // let the xy-plane projections of board normal and plane normal overlap
-            // it decreases the chance of falling into local minimum
-            let rotation = {
-                let lifting_rotation = UnitQuaternion::from_euler_angles(0.0, -FRAC_PI_2, 0.0)
-                    * UnitQuaternion::from_euler_angles(0.0, 0.0, -FRAC_PI_4);
-                let lifted_normal = lifting_rotation * Vector3::z_axis();
-                debug_assert!(abs_diff_eq!(
-                    (*lifted_normal + *Vector3::x_axis()).norm(),
-                    0.0,
-                    epsilon = EPS_F64
-                ));
-
-                let planar_rotation = {
-                    let planar_plane_normal = Vector3::new(plane_normal.x, plane_normal.y, 0.0);
-                    UnitQuaternion::rotation_between(&lifted_normal, &planar_plane_normal)
-                        .unwrap_or_else(|| {
-                            if lifted_normal.dot(&planar_plane_normal) >= 0.0 {
-                                UnitQuaternion::identity()
-                            } else {
-                                UnitQuaternion::from_euler_angles(0.0, 0.0, PI)
-                            }
-                        })
-                };
-                planar_rotation * lifting_rotation
-            };
-
-            Isometry3::from_parts(Translation3::identity(), rotation)
-        };
         let init_inlier_points: Vec<&Point3<_>> = plane_inlier_points
             .iter()
             .map(|point| point.borrow())
             .collect();
+        let init_inlier_weights: Vec<f64> = match point_weights {
+            Some(weights) => weights.to_vec(),
+            None => vec![1.0; init_inlier_points.len()],
+        };
 
-        let (inlier_points, corresponding_points, icp_losses, pose) = {
+        let (inlier_points, corresponding_points, icp_losses, pose_history, pose) = {
+            let correspondence_index = CorrespondenceIndex::new(target);
+            let mut correspondences = Vec::with_capacity(init_inlier_points.len());
             let mut inlier_points = init_inlier_points;
+            let mut inlier_weights = init_inlier_weights;
+            // the buffers of good points are swapped with the inliers on
+            // each iteration to avoid allocations
+            let mut good_inlier_points = Vec::with_capacity(inlier_points.len());
+            let mut good_corresponding_points = Vec::with_capacity(inlier_points.len());
+            let mut good_weights = Vec::with_capacity(inlier_points.len());
             let mut losses = vec![];
+            let mut poses = vec![];
             let mut termination_count = 0;
-            let mut pose = init_pose;
+            let mut pose = *init_pose;
             let mut step = 0;
 
             loop {
-                let board_model = BoardModel {
-                    pose,
-                    board_shape: BoardShape {
-                        board_width,
-                        hole_radius,
-                        hole_center_shift,
-                    },
-                    marker_paper_size,
-                };
-
-                let correspondings = board_model.find_correspondences(inlier_points);
-                let correspondings = match correspondings {
-                    Some(corr) => corr,
-                    None => return Ok(None),
-                };
-                debug_assert!({
-                    correspondings
-                        .iter()
-                        .all(|(_data_point, corresponding_point)| {
-                            let center = board_model.board_center();
-
-                            abs_diff_eq!(
-                                board_model
-                                    .board_z_axis()
-                                    .dot(&(corresponding_point - center)),
-                                0.0,
-                                epsilon = EPS_F64
-                            )
-                        })
-                });
+                correspondence_index.find_correspondences_into(
+                    &pose,
+                    inlier_points.iter().copied(),
+                    &mut correspondences,
+                );
 
                 // reject outliers
-                let (good_inlier_points, good_corresponding_points, avg_loss) = {
-                    let losses: Vec<_> = correspondings
+                let avg_loss = {
+                    let avg_loss = correspondences
                         .iter()
-                        .map(|(input_point, corresponding_point)| {
-                            let loss = (input_point.borrow() - corresponding_point).norm();
-                            loss
-                        })
-                        .collect();
-                    let avg_loss = losses.iter().sum::<f64>() / correspondings.len() as f64;
-
-                    let good_correspondences: Vec<_> = if avg_loss <= GOOD_FIT_THRESHOLD {
-                        izip!(correspondings, losses.iter().cloned())
-                            .filter_map(|((inlier_point, corresponding_point), loss)| {
-                                (loss < OUTLIER_THRESHOLD)
-                                    .then_some((inlier_point, corresponding_point))
-                            })
-                            .collect()
-                    } else {
-                        correspondings
-                    };
-
-                    let (good_inlier_points, good_corresponding_points) =
-                        good_correspondences.into_iter().unzip_n_vec();
+                        .map(|correspondence| correspondence.distance())
+                        .sum::<f64>()
+                        / correspondences.len() as f64;
+                    let reject_outliers = avg_loss <= good_fit_threshold;
+
+                    good_inlier_points.clear();
+                    good_corresponding_points.clear();
+                    good_weights.clear();
+
+                    let good = izip!(&inlier_points, &correspondences, &inlier_weights).filter(
+                        |(_, correspondence, _)| {
+                            !reject_outliers || correspondence.distance() < outlier_threshold
+                        },
+                    );
+                    for (&inlier_point, correspondence, &weight) in good {
+                        good_inlier_points.push(inlier_point);
+                        good_corresponding_points.push(correspondence.target_point(&pose));
+                        good_weights.push(weight);
+                    }
 
-                    (good_inlier_points, good_corresponding_points, avg_loss)
+                    avg_loss
                 };
 
                 // compute transformation
-                let align_pose: Isometry3<_> = {
+                let align_pose: Isometry3<_> = if point_weights.is_some() {
+                    weighted_kabsch(
+                        &good_inlier_points,
+                        &good_corresponding_points,
+                        &good_weights,
+                    )
+                    .unwrap_or_else(Isometry3::identity)
+                } else {
                     // let lhs = good_inlier_points.into_iter().map(<[f64; 3]>::from);
                     let pairs = izip!(
                         good_inlier_points
@@ -265,12 +454,21 @@ pub fn fit_board_icp(
 
                 // update state
                 losses.push(avg_loss);
-                inlier_points = good_inlier_points;
+                poses.push(pose);
+                mem::swap(&mut inlier_points, &mut good_inlier_points);
+                mem::swap(&mut inlier_weights, &mut good_weights);
                 pose = align_pose * pose;
                 step += 1;
 
                 if step == max_icp_iterations || termination_count > 16 {
-                    break (inlier_points, good_corresponding_points, losses, pose);
+                    poses.push(pose);
+                    break (
+                        inlier_points,
+                        good_corresponding_points,
+                        losses,
+                        poses,
+                        pose,
+                    );
                 }
             }
         };
@@ -279,11 +477,7 @@ pub fn fit_board_icp(
         let viz_msg = {
             let board_model = BoardModel {
                 pose,
-                board_shape: BoardShape {
-                    board_width,
-                    hole_radius,
-                    hole_center_shift,
-                },
+                board_shape: target.clone(),
                 marker_paper_size,
             };
 
@@ -295,6 +489,7 @@ pub fn fit_board_icp(
 
             IcpData {
                 correspondences,
+                pose_history,
                 board_model,
             }
         };
@@ -304,25 +499,441 @@ pub fn fit_board_icp(
 
     // reject result if loss is too large
     {
-        let min_icp_loss = icp_losses
-            .iter()
-            .copied()
-            .map(r64)
-            .min()
-            .map(|loss| loss.raw());
-        let min_icp_loss = match min_icp_loss {
-            Some(loss) => loss,
-            None => return Ok(None),
-        };
+        let min_icp_loss = min_icp_loss(&icp_losses);
 
         if min_icp_loss > icp_rejection_threshold {
-            return Ok(None);
+            return Err(DetectionFailure::LossTooLarge {
+                num_inliers: plane_inlier_points.len(),
+                min_loss: min_icp_loss,
+                threshold: icp_rejection_threshold,
+            });
         }
     }
 
-    Ok(Some(FitBoardIcp {
+    Ok(FitBoardIcp {
         board_pose,
         icp_losses,
         icp_data: viz_msg,
-    }))
+        lm_data: None,
+        ambiguity_margin: None,
+    })
+}
+
+/// Estimates the board pose from a point set using Levenberg–Marquardt
+/// algorithm.
+///
+/// The target center is placed at the inlier centroid and the target
+/// normal is aligned to the plane normal. The optimization starts from
+/// several in-plane rotations, and the solution with the least final
+/// cost is kept.
+///
+/// The optional point weights are indexed like the inlier points and
+/// scale their residuals.
+pub fn fit_board_lm<S>(
+    board_detector: &Config,
+    aruco_detector: &MultiArucoPattern,
+    target: &S,
+    plane_model: &PlaneModel,
+    plane_inlier_points: &[impl Borrow<Point3<f64>>],
+    point_weights: Option<&[f64]>,
+) -> Result<FitBoardIcp<S>, DetectionFailure>
+where
+    S: TargetGeometry + Clone,
+{
+    // the seeds span the rotations under which the target border is
+    // symmetric, so they are not affected by holes
+    const NUM_ROTATION_SEEDS: usize = 6;
+
+    // the plane frame is undefined without points
+    if plane_inlier_points.is_empty() {
+        return Err(DetectionFailure::TooFewPoints {
+            num_inliers: 0,
+            min_points: LmRefiner::MIN_POINTS,
+        });
+    }
+
+    let target_center = target.center();
+    let seed_span = TAU / target.symmetry_order() as f64;
+    let (inlier_centroid, plane_normal) = board_plane_frame(plane_model, plane_inlier_points);
+    let base_rotation = aligned_board_rotation(&plane_normal);
+    let plane_axis = Unit::new_normalize(plane_normal);
+
+    let init_poses = (0..NUM_ROTATION_SEEDS).map(|index| {
+        let angle = seed_span * index as f64 / NUM_ROTATION_SEEDS as f64;
+        let rotation = UnitQuaternion::from_axis_angle(&plane_axis, angle) * base_rotation;
+        let translation =
+            inlier_centroid - rotation * Vector3::new(target_center.x, target_center.y, 0.0);
+        Isometry3::from_parts(Translation3::from(translation.coords), rotation)
+    });
+
+    fit_board_lm_with_seeds(
+        board_detector,
+        aruco_detector,
+        target,
+        inlier_centroid.coords.norm(),
+        init_poses,
+        plane_inlier_points,
+        point_weights,
+    )
+}
+
+/// Estimates the board pose from a point set using Levenberg–Marquardt
+/// algorithm starting from the given pose.
+///
+/// The optional point weights are indexed like the inlier points and
+/// scale their residuals.
+pub fn fit_board_lm_from<S>(
+    board_detector: &Config,
+    aruco_detector: &MultiArucoPattern,
+    target: &S,
+    init_pose: &Isometry3<f64>,
+    plane_inlier_points: &[impl Borrow<Point3<f64>>],
+    point_weights: Option<&[f64]>,
+) -> Result<FitBoardIcp<S>, DetectionFailure>
+where
+    S: TargetGeometry + Clone,
+{
+    let range = init_pose.translation.vector.norm();
+    fit_board_lm_with_seeds(
+        board_detector,
+        aruco_detector,
+        target,
+        range,
+        [*init_pose],
+        plane_inlier_points,
+        point_weights,
+    )
+}
+
+/// Refines the board pose from each initial pose and keeps the
+/// solution with the least final cost.
+fn fit_board_lm_with_seeds<S>(
+    board_detector: &Config,
+    aruco_detector: &MultiArucoPattern,
+    target: &S,
+    range: f64,
+    init_poses: impl IntoIterator<Item = Isometry3<f64>>,
+    plane_inlier_points: &[impl Borrow<Point3<f64>>],
+    point_weights: Option<&[f64]>,
+) -> Result<FitBoardIcp<S>, DetectionFailure>
+where
+    S: TargetGeometry + Clone,
+{
+    let icp_rejection_threshold = board_detector.icp_rejection_threshold;
+    let marker_paper_size = aruco_detector.paper_size();
+    let refiner = board_lm_refiner(board_detector, range);
+
+    let solution = init_poses
+        .into_iter()
+        .filter_map(|pose| {
+            let init = BoardModel {
+                pose,
+                board_shape: target.clone(),
+                marker_paper_size,
+            };
+            refiner.refine_weighted(&init, plane_inlier_points, point_weights)
+        })
+        .min_by_key(|solution| r64(solution.final_cost));
+    let LmSolution {
+        pose: board_pose,
+        final_cost,
+        mean_losses: icp_losses,
+        poses: pose_history,
+        pose_covariance,
+        iterations,
+        converged,
+        correspondences,
+        ..
+    } = match solution {
+        Some(solution) => solution,
+        None => {
+            return Err(DetectionFailure::TooFewPoints {
+                num_inliers: plane_inlier_points.len(),
+                min_points: LmRefiner::MIN_POINTS,
+            })
+        }
+    };
+
+    // reject result if loss is too large
+    let final_loss = icp_losses.last().copied().unwrap_or(f64::INFINITY);
+    if final_loss > icp_rejection_threshold {
+        return Err(DetectionFailure::LossTooLarge {
+            num_inliers: plane_inlier_points.len(),
+            min_loss: final_loss,
+            threshold: icp_rejection_threshold,
+        });
+    }
+
+    let icp_data = IcpData {
+        correspondences,
+        pose_history,
+        board_model: BoardModel {
+            pose: board_pose,
+            board_shape: target.clone(),
+            marker_paper_size,
+        },
+    };
+
+    Ok(FitBoardIcp {
+        board_pose,
+        icp_losses,
+        icp_data,
+        lm_data: Some(LmData {
+            final_cost,
+            pose_covariance,
+            iterations,
+            converged,
+        }),
+        ambiguity_margin: None,
+    })
+}
+
+/// Estimates the covariance of the board pose from the final board
+/// inliers.
+///
+/// The covariance is ordered as a translation followed by a rotation
+/// vector, both in the world frame. It returns `None` if the pose is not
+/// fully constrained by the points.
+pub fn estimate_pose_covariance<S>(
+    board_detector: &Config,
+    board_model: &BoardModel<S>,
+    board_inlier_points: &[impl Borrow<Point3<f64>>],
+) -> Option<Matrix6<f64>>
+where
+    S: TargetGeometry,
+{
+    let range = board_model.board_center().coords.norm();
+    let range_noise = board_detector.sensor_params().range_noise;
+    let local_covariance = board_lm_refiner(board_detector, range).pose_covariance(
+        board_model,
+        board_inlier_points,
+        range_noise,
+    )?;
+
+    // The perturbation (rotation, translation) in the board frame is
+    // mapped to the world frame by the board rotation, and the two
+    // parts are swapped.
+    let jacobian = {
+        let rotation = board_model.pose.rotation.to_rotation_matrix();
+        let mut jacobian = Matrix6::zeros();
+        jacobian
+            .fixed_view_mut::<3, 3>(0, 3)
+            .copy_from(rotation.matrix());
+        jacobian
+            .fixed_view_mut::<3, 3>(3, 0)
+            .copy_from(rotation.matrix());
+        jacobian
+    };
+
+    Some(jacobian * local_covariance * jacobian.transpose())
+}
+
+/// Computes the confidence score of a detection in `[0, 1]`.
+///
+/// The score is the product of two terms. The fit term decreases
+/// linearly from 1 to 0 as the final loss grows to
+/// `icp_rejection_threshold`. The precision term is
+/// `range_noise / (range_noise + position_std)`, where `position_std`
+/// is the standard deviation of the board position. The score is 0 if
+/// the covariance is not available.
+pub fn confidence_score(
+    board_detector: &Config,
+    final_loss: f64,
+    pose_covariance: Option<&Matrix6<f64>>,
+) -> f64 {
+    let pose_covariance = match pose_covariance {
+        Some(cov) => cov,
+        None => return 0.0,
+    };
+    let range_noise = board_detector.sensor_params().range_noise;
+
+    let fit = (1.0 - final_loss / board_detector.icp_rejection_threshold).clamp(0.0, 1.0);
+    let precision = {
+        let position_std = pose_covariance
+            .fixed_view::<3, 3>(0, 0)
+            .trace()
+            .max(0.0)
+            .sqrt();
+        range_noise / (range_noise + position_std)
+    };
+
+    fit * precision
+}
+
+/// Builds the Levenberg–Marquardt refiner for a board at the range.
+fn board_lm_refiner(board_detector: &Config, range: f64) -> LmRefiner {
+    let Config {
+        lm_max_iterations,
+        lm_edge_weight,
+        lm_boundary_radius,
+        ..
+    } = *board_detector;
+    let sensor_params = board_detector.sensor_params();
+
+    // the boundary neighborhood must span at least a few scan lines
+    let boundary_radius = lm_boundary_radius.max(sensor_params.scan_line_spacing(range) * 2.5);
+
+    LmRefiner {
+        edge_weight: lm_edge_weight,
+        boundary_radius,
+        ..LmRefiner::new(
+            lm_max_iterations,
+            sensor_params.good_fit_threshold,
+            sensor_params.outlier_threshold,
+        )
+    }
+}
+
+/// Computes the centroid of plane inliers and the plane normal vector
+/// that points towards the origin.
+fn board_plane_frame(
+    plane_model: &PlaneModel,
+    plane_inlier_points: &[impl Borrow<Point3<f64>>],
+) -> (Point3<f64>, Vector3<f64>) {
+    let inlier_centroid: Point3<f64> =
+        centroid_of_points(plane_inlier_points.iter().map(|point| {
+            let point: [f64; 3] = (*point.borrow()).into();
+            point
+        }))
+        .unwrap()
+        .into();
+
+    // obtain the plane normal vector that points towards the origin
+    let plane_normal = {
+        let normal: Vector3<f64> = nalgebra::convert(*plane_model.normal);
+        if (Point3::origin() - inlier_centroid).dot(&normal) < 0.0 {
+            -normal
+        } else {
+            normal
+        }
+    };
+
+    (inlier_centroid, plane_normal)
+}
+
+/// Computes the initial board rotation like [initial_board_rotation]
+/// and tilts it so that the board normal is the plane normal.
+fn aligned_board_rotation(plane_normal: &Vector3<f64>) -> UnitQuaternion<f64> {
+    let rotation = initial_board_rotation(plane_normal);
+    let tilt = UnitQuaternion::rotation_between(&(rotation * Vector3::z()), plane_normal)
+        .unwrap_or_else(UnitQuaternion::identity);
+    tilt * rotation
+}
+
+/// Finds the rigid transform that moves the model points onto the data
+/// points in the weighted least squares sense.
+///
+/// It returns `None` if the weights sum to zero or the SVD fails.
+fn weighted_kabsch(
+    data_points: &[&Point3<f64>],
+    model_points: &[Point3<f64>],
+    weights: &[f64],
+) -> Option<Isometry3<f64>> {
+    let total_weight: f64 = weights.iter().sum();
+    if total_weight <= 0.0 {
+        return None;
+    }
+
+    let data_centroid =
+        izip!(data_points, weights).fold(Vector3::zeros(), |sum, (point, weight)| {
+            sum + point.coords * *weight
+        }) / total_weight;
+    let model_centroid =
+        izip!(model_points, weights).fold(Vector3::zeros(), |sum, (point, weight)| {
+            sum + point.coords * *weight
+        }) / total_weight;
+    let covariance = izip!(data_points, model_points, weights).fold(
+        Matrix3::zeros(),
+        |sum, (data_point, model_point, weight)| {
+            sum + (model_point.coords - model_centroid)
+                * (data_point.coords - data_centroid).transpose()
+                * *weight
+        },
+    );
+
+    let svd = covariance.svd(true, true);
+    let (u, v_t) = (svd.u?, svd.v_t?);
+    // flip the least singular direction to avoid a reflection
+    let sign = (v_t.transpose() * u.transpose()).determinant().signum();
+    let rotation =
+        v_t.transpose() * Matrix3::from_diagonal(&Vector3::new(1.0, 1.0, sign)) * u.transpose();
+    let rotation = UnitQuaternion::from_matrix(&rotation);
+    let translation = data_centroid - rotation * model_centroid;
+
+    Some(Isometry3::from_parts(
+        Translation3::from(translation),
+        rotation,
+    ))
+}
+
+/// Checks if two poses place the target feature points at the same
+/// places, regardless of which feature goes where.
+///
+/// A target mirrored about its symmetry axis covers the same points,
+/// so such poses are alike even though their rotations differ.
+fn places_target_alike<S>(
+    target: &S,
+    lhs: &Isometry3<f64>,
+    rhs: &Isometry3<f64>,
+    tolerance: f64,
+) -> bool
+where
+    S: TargetGeometry,
+{
+    let feature_points = target.feature_points();
+    let place = |pose: &Isometry3<f64>| -> Vec<Point3<f64>> {
+        feature_points
+            .iter()
+            .map(|point| pose * Point3::new(point.x, point.y, 0.0))
+            .collect()
+    };
+    let lhs_points = place(lhs);
+    let rhs_points = place(rhs);
+
+    lhs_points.iter().all(|lhs_point| {
+        rhs_points
+            .iter()
+            .any(|rhs_point| (lhs_point - rhs_point).norm() <= tolerance)
+    })
+}
+
+/// Finds the least loss among ICP iterations. It is infinite if there
+/// are no iterations.
+fn min_icp_loss(icp_losses: &[f64]) -> f64 {
+    icp_losses
+        .iter()
+        .copied()
+        .map(r64)
+        .min()
+        .map(|loss| loss.raw())
+        .unwrap_or(f64::INFINITY)
+}
+
+/// Computes the initial board rotation that stands the board upright
+/// like a diamond facing the plane normal.
+fn initial_board_rotation(plane_normal: &Vector3<f64>) -> UnitQuaternion<f64> {
+    // let the xy-plane projections of board normal and plane normal overlap
+    // it decreases the chance of falling into local minimum
+    let lifting_rotation = UnitQuaternion::from_euler_angles(0.0, -FRAC_PI_2, 0.0)
+        * UnitQuaternion::from_euler_angles(0.0, 0.0, -FRAC_PI_4);
+    let lifted_normal = lifting_rotation * Vector3::z_axis();
+    debug_assert!(abs_diff_eq!(
+        (*lifted_normal + *Vector3::x_axis()).norm(),
+        0.0,
+        epsilon = EPS_F64
+    ));
+
+    let planar_rotation = {
+        let planar_plane_normal = Vector3::new(plane_normal.x, plane_normal.y, 0.0);
+        UnitQuaternion::rotation_between(&lifted_normal, &planar_plane_normal).unwrap_or_else(
+            || {
+                if lifted_normal.dot(&planar_plane_normal) >= 0.0 {
+                    UnitQuaternion::identity()
+                } else {
+                    UnitQuaternion::from_euler_angles(0.0, 0.0, PI)
+                }
+            },
+        )
+    };
+    planar_rotation * lifting_rotation
 }
@@ -1,13 +1,268 @@
-use hollow_board_config::BoardShape;
+use crate::{
+    consensus::ConsensusStrategy,
+    sensor::{SensorOverrides, SensorParams, SensorProfile},
+};
+use aruco_config::MultiArucoPattern;
+use hollow_board_config::{validate_target, BoardShape, Violations};
+use plane_estimator::RobustLoss;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Config {
+    /// The LiDAR model that presets sensor dependent parameters.
+    #[serde(default)]
+    pub sensor_profile: SensorProfile,
+    /// Overrides on the parameters of the sensor profile.
+    #[serde(default)]
+    pub sensor_overrides: SensorOverrides,
+    /// The method used to estimate the board pose on the plane.
+    #[serde(default)]
+    pub board_refine_method: BoardRefineMethod,
+    /// The maximum number of boards found by
+    /// [Detector::detect_all](crate::Detector::detect_all).
+    #[serde(default = "default_max_boards")]
+    pub max_boards: usize,
     pub max_icp_iterations: usize,
     pub icp_pose_weight_threshold: f64,
     pub icp_rejection_threshold: f64,
+    /// The number of in-plane rotations the ICP starts from. Each
+    /// rotation is also tried flipped, and the starts run in parallel,
+    /// so a candidate plane costs twice this many ICP runs.
+    #[serde(default = "default_icp_num_rotations")]
+    pub icp_num_rotations: usize,
+    /// The maximum number of Levenberg–Marquardt iterations per
+    /// initial pose.
+    #[serde(default = "default_lm_max_iterations")]
+    pub lm_max_iterations: usize,
+    /// The weight of edge and hole boundary residuals relative to
+    /// point-to-plane residuals.
+    #[serde(default = "default_lm_edge_weight")]
+    pub lm_edge_weight: f64,
+    /// The neighborhood radius in meters used to find points on the
+    /// boundary of the board point cloud. It should span a few scan
+    /// lines on the board.
+    #[serde(default = "default_lm_boundary_radius")]
+    pub lm_boundary_radius: f64,
+    /// How points at the board border and hole edges are compensated
+    /// for the beam divergence before board fitting.
+    #[serde(default)]
+    pub edge_compensation: EdgeCompensation,
+    /// The weight of edge points relative to other points, used if
+    /// edge points are down-weighted.
+    #[serde(default = "default_edge_point_weight")]
+    pub edge_point_weight: f64,
+    /// If set, the board pose is refined by aligning the fitted hole
+    /// centers. At least two holes must be fitted.
+    #[serde(default)]
+    pub hole_refine: bool,
+    /// The maximum RMS residual in meters of a hole circle fit to be
+    /// used in pose refinement.
+    #[serde(default = "default_hole_fit_max_residual")]
+    pub hole_fit_max_residual: f64,
+    /// The minimum contrast of mean intensities between the marker
+    /// paper and the bare board, defined as `|paper - bare| / (paper +
+    /// bare)`, to orient the board by intensity.
+    #[serde(default = "default_intensity_min_contrast")]
+    pub intensity_min_contrast: f64,
+    /// The minimum number of points on the marker paper and on the bare
+    /// board to orient the board by intensity.
+    #[serde(default = "default_intensity_min_points")]
+    pub intensity_min_points: usize,
+    /// Points within this distance in meters of the previous board are
+    /// used to track the board in the next frame.
+    #[serde(default = "default_track_gate_distance")]
+    pub track_gate_distance: f64,
+    /// The tracking is considered diverged if the board moves more than
+    /// this distance in meters between frames.
+    #[serde(default = "default_track_max_translation")]
+    pub track_max_translation: f64,
+    /// The tracking is considered diverged if the board rotates more
+    /// than this angle in degrees between frames.
+    #[serde(default = "default_track_max_rotation")]
+    pub track_max_rotation: f64,
+    /// The weight of the new pose in the exponential pose smoothing, in
+    /// `(0, 1]`. The pose is not smoothed if it is 1.
+    #[serde(default = "default_track_smoothing")]
+    pub track_smoothing: f64,
+    /// Points within this distance in meters of a ground plane are
+    /// removed before proposing board candidates.
+    #[serde(default = "default_proposal_ground_threshold")]
+    pub proposal_ground_threshold: f64,
+    /// A plane larger than the board is taken as the ground if its
+    /// normal is within this angle in degrees of the z axis.
+    #[serde(default = "default_proposal_ground_max_tilt")]
+    pub proposal_ground_max_tilt: f64,
+    /// Points closer than this distance in meters are put in the same
+    /// cluster when proposing board candidates.
+    #[serde(default = "default_proposal_cluster_tolerance")]
+    pub proposal_cluster_tolerance: f64,
+    /// Clusters with fewer points are not proposed as board
+    /// candidates.
+    #[serde(default = "default_proposal_min_cluster_points")]
+    pub proposal_min_cluster_points: usize,
     pub plane_ransac_max_iterations: usize,
     pub plane_ransac_inlier_threshold: f64,
+    /// The consensus algorithm used to fit the board plane.
+    #[serde(default)]
+    pub plane_ransac_strategy: ConsensusStrategy,
+    /// The seed of the random number generator. The detection is
+    /// reproducible if the seed is set.
+    #[serde(default)]
+    pub plane_ransac_seed: Option<u64>,
+    /// The loss used to refit the RANSAC plane against all inliers.
+    /// The refinement is skipped if it is set to `null`.
+    #[serde(default = "default_plane_refine_loss")]
+    pub plane_refine_loss: Option<RobustLoss>,
+    /// The maximum number of planes extracted and tried as board
+    /// candidates.
+    #[serde(default = "default_plane_ransac_max_planes")]
+    pub plane_ransac_max_planes: usize,
+    /// The minimum planarity of a board candidate plane.
+    #[serde(default = "default_plane_min_planarity")]
+    pub plane_min_planarity: f64,
+    /// The minimum extent of a board candidate plane, as a ratio to
+    /// the board width.
+    #[serde(default = "default_plane_min_extent_ratio")]
+    pub plane_min_extent_ratio: f64,
+    /// The tolerated ratio by which the extent of a board candidate
+    /// plane may exceed the board diagonal.
+    #[serde(default = "default_plane_extent_tolerance")]
+    pub plane_extent_tolerance: f64,
     #[serde(flatten)]
     pub board_shape: BoardShape,
 }
+
+impl Config {
+    /// Gets the sensor parameters with overrides applied.
+    pub fn sensor_params(&self) -> SensorParams {
+        self.sensor_profile
+            .params()
+            .with_overrides(&self.sensor_overrides)
+    }
+
+    /// Checks that the board shape and the marker pattern agree
+    /// geometrically. See [validate_target].
+    pub fn validate(&self, aruco_pattern: &MultiArucoPattern) -> Result<(), Violations> {
+        validate_target(&self.board_shape, aruco_pattern)
+    }
+}
+
+/// The method used to estimate the board pose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoardRefineMethod {
+    /// Point-to-point ICP with Kabsch updates.
+    #[default]
+    Icp,
+    /// Levenberg–Marquardt over point-to-plane and edge residuals.
+    Lm,
+}
+
+/// The compensation of edge points whose beam footprints straddle the
+/// board border or a hole edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeCompensation {
+    /// Edge points are used as measured.
+    #[default]
+    None,
+    /// Edge points are weighted by
+    /// [edge_point_weight](Config::edge_point_weight) in board fitting.
+    DownWeight,
+    /// Edge points are moved toward the board by half the beam
+    /// footprint.
+    ///
+    /// Each edge point moves sideways toward its neighbor on the same
+    /// scan ring, not along the beam, and its range is kept. Only the
+    /// ends of ring runs are moved, so the top and bottom edges of the
+    /// board, which run along the rings, are not compensated.
+    Shrink,
+}
+
+fn default_max_boards() -> usize {
+    3
+}
+
+fn default_icp_num_rotations() -> usize {
+    4
+}
+
+fn default_lm_max_iterations() -> usize {
+    50
+}
+
+fn default_lm_edge_weight() -> f64 {
+    1.0
+}
+
+fn default_lm_boundary_radius() -> f64 {
+    0.1
+}
+
+fn default_edge_point_weight() -> f64 {
+    0.3
+}
+
+fn default_hole_fit_max_residual() -> f64 {
+    0.02
+}
+
+fn default_intensity_min_contrast() -> f64 {
+    0.1
+}
+
+fn default_intensity_min_points() -> usize {
+    10
+}
+
+fn default_track_gate_distance() -> f64 {
+    0.2
+}
+
+fn default_track_max_translation() -> f64 {
+    0.3
+}
+
+fn default_track_max_rotation() -> f64 {
+    15.0
+}
+
+fn default_track_smoothing() -> f64 {
+    0.5
+}
+
+fn default_proposal_ground_threshold() -> f64 {
+    0.1
+}
+
+fn default_proposal_ground_max_tilt() -> f64 {
+    15.0
+}
+
+fn default_proposal_cluster_tolerance() -> f64 {
+    0.2
+}
+
+fn default_proposal_min_cluster_points() -> usize {
+    30
+}
+
+fn default_plane_refine_loss() -> Option<RobustLoss> {
+    Some(RobustLoss::LeastSquares)
+}
+
+fn default_plane_ransac_max_planes() -> usize {
+    3
+}
+
+fn default_plane_min_planarity() -> f64 {
+    0.05
+}
+
+fn default_plane_min_extent_ratio() -> f64 {
+    0.3
+}
+
+fn default_plane_extent_tolerance() -> f64 {
+    0.2
+}
@@ -0,0 +1,408 @@
+//! Consensus algorithms for plane fitting.
+//!
+//! ARRSAC is provided by the [arrsac] crate. This module implements
+//! MSAC, LO-RANSAC and PROSAC on top of [PlaneEstimator].
+
+use nalgebra::Point3;
+use noisy_float::prelude::*;
+use plane_estimator::{PlaneEstimator, PlaneModel, PlaneRefiner, RefinedPlane};
+use rand::{seq::index, RngCore};
+use sample_consensus::{Consensus, Estimator, Model};
+use serde::{Deserialize, Serialize};
+
+const MIN_SAMPLES: usize = <PlaneEstimator as Estimator<Point3<f64>>>::MIN_SAMPLES;
+
+/// The consensus algorithm used to fit planes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsensusStrategy {
+    /// Adaptive Real-time Random Sample Consensus.
+    #[default]
+    Arrsac,
+    /// M-estimator Sample Consensus. Models are scored by truncated
+    /// squared residuals instead of inlier counts.
+    Msac,
+    /// RANSAC with local optimization. Each new best model is refitted
+    /// against its inliers.
+    LoRansac,
+    /// Progressive Sample Consensus. Points nearer to the sensor are
+    /// sampled first.
+    Prosac,
+}
+
+/// M-estimator Sample Consensus.
+#[derive(Debug, Clone)]
+pub struct Msac<R> {
+    pub inlier_threshold: f64,
+    pub max_iterations: usize,
+    /// The probability that the best model is found when the
+    /// iteration stops early.
+    pub confidence: f64,
+    pub rng: R,
+}
+
+impl<R> Msac<R> {
+    pub fn new(inlier_threshold: f64, max_iterations: usize, rng: R) -> Self {
+        Self {
+            inlier_threshold,
+            max_iterations,
+            confidence: 0.99,
+            rng,
+        }
+    }
+}
+
+impl<R> Consensus<PlaneEstimator, Point3<f64>> for Msac<R>
+where
+    R: RngCore,
+{
+    type Inliers = Vec<usize>;
+
+    fn model<I>(&mut self, estimator: &PlaneEstimator, data: I) -> Option<PlaneModel>
+    where
+        I: Iterator<Item = Point3<f64>> + Clone,
+    {
+        self.model_inliers(estimator, data).map(|(model, _)| model)
+    }
+
+    fn model_inliers<I>(
+        &mut self,
+        estimator: &PlaneEstimator,
+        data: I,
+    ) -> Option<(PlaneModel, Self::Inliers)>
+    where
+        I: Iterator<Item = Point3<f64>> + Clone,
+    {
+        let points: Vec<_> = data.collect();
+        if points.len() < MIN_SAMPLES {
+            return None;
+        }
+        let threshold = self.inlier_threshold;
+
+        let cost_of = |model: &PlaneModel| -> f64 {
+            points
+                .iter()
+                .map(|point| model.residual(point).powi(2).min(threshold.powi(2)))
+                .sum()
+        };
+
+        let mut best: Option<(PlaneModel, f64)> = None;
+        let mut num_iterations = self.max_iterations;
+        let mut iteration = 0;
+
+        while iteration < num_iterations {
+            iteration += 1;
+
+            let model = match sample_model(estimator, &points, &mut self.rng) {
+                Some(model) => model,
+                None => continue,
+            };
+            let cost = cost_of(&model);
+
+            let is_better = match &best {
+                Some((_, best_cost)) => cost < *best_cost,
+                None => true,
+            };
+
+            if is_better {
+                let num_inliers = count_inliers(&model, &points, threshold);
+                num_iterations = num_iterations.min(required_iterations(
+                    num_inliers,
+                    points.len(),
+                    self.confidence,
+                ));
+                best = Some((model, cost));
+            }
+        }
+
+        let (model, _) = best?;
+        let inliers = find_inliers(&model, &points, threshold);
+        Some((model, inliers))
+    }
+}
+
+/// RANSAC with local optimization.
+#[derive(Debug, Clone)]
+pub struct LoRansac<R> {
+    pub inlier_threshold: f64,
+    pub max_iterations: usize,
+    /// The probability that the best model is found when the
+    /// iteration stops early.
+    pub confidence: f64,
+    /// The maximum number of refitting steps on each new best model.
+    pub max_local_iterations: usize,
+    pub rng: R,
+}
+
+impl<R> LoRansac<R> {
+    pub fn new(inlier_threshold: f64, max_iterations: usize, rng: R) -> Self {
+        Self {
+            inlier_threshold,
+            max_iterations,
+            confidence: 0.99,
+            max_local_iterations: 10,
+            rng,
+        }
+    }
+
+    /// Refits the model against its inliers until the inlier set stops
+    /// growing.
+    fn local_optimize(
+        &self,
+        mut model: PlaneModel,
+        mut inliers: Vec<usize>,
+        points: &[Point3<f64>],
+    ) -> (PlaneModel, Vec<usize>) {
+        let refiner = PlaneRefiner::default();
+
+        for _ in 0..self.max_local_iterations {
+            let inlier_points: Vec<_> = inliers.iter().map(|&idx| &points[idx]).collect();
+            let refined_model = match refiner.refine(&model, &inlier_points) {
+                Some(RefinedPlane { model, .. }) => model,
+                None => break,
+            };
+            let refined_inliers = find_inliers(&refined_model, points, self.inlier_threshold);
+
+            if refined_inliers.len() <= inliers.len() {
+                break;
+            }
+            model = refined_model;
+            inliers = refined_inliers;
+        }
+
+        (model, inliers)
+    }
+}
+
+impl<R> Consensus<PlaneEstimator, Point3<f64>> for LoRansac<R>
+where
+    R: RngCore,
+{
+    type Inliers = Vec<usize>;
+
+    fn model<I>(&mut self, estimator: &PlaneEstimator, data: I) -> Option<PlaneModel>
+    where
+        I: Iterator<Item = Point3<f64>> + Clone,
+    {
+        self.model_inliers(estimator, data).map(|(model, _)| model)
+    }
+
+    fn model_inliers<I>(
+        &mut self,
+        estimator: &PlaneEstimator,
+        data: I,
+    ) -> Option<(PlaneModel, Self::Inliers)>
+    where
+        I: Iterator<Item = Point3<f64>> + Clone,
+    {
+        let points: Vec<_> = data.collect();
+        if points.len() < MIN_SAMPLES {
+            return None;
+        }
+
+        let mut best: Option<(PlaneModel, Vec<usize>)> = None;
+        let mut num_iterations = self.max_iterations;
+        let mut iteration = 0;
+
+        while iteration < num_iterations {
+            iteration += 1;
+
+            let model = match sample_model(estimator, &points, &mut self.rng) {
+                Some(model) => model,
+                None => continue,
+            };
+            let num_inliers = count_inliers(&model, &points, self.inlier_threshold);
+            let best_num_inliers = best.as_ref().map_or(0, |(_, inliers)| inliers.len());
+
+            if num_inliers > best_num_inliers {
+                let inliers = find_inliers(&model, &points, self.inlier_threshold);
+                let (model, inliers) = self.local_optimize(model, inliers, &points);
+                num_iterations = num_iterations.min(required_iterations(
+                    inliers.len(),
+                    points.len(),
+                    self.confidence,
+                ));
+                best = Some((model, inliers));
+            }
+        }
+
+        best
+    }
+}
+
+/// Progressive Sample Consensus.
+///
+/// Points are ranked by their distance to the sensor origin, assuming
+/// that nearer points have less range noise. Samples are drawn from a
+/// growing set of top-ranked points.
+#[derive(Debug, Clone)]
+pub struct Prosac<R> {
+    pub inlier_threshold: f64,
+    pub max_iterations: usize,
+    /// The probability that the best model is found when the
+    /// iteration stops early. The early stop is only considered after
+    /// the sampling set covers all points, since samples drawn from
+    /// top-ranked points are not uniform.
+    pub confidence: f64,
+    pub rng: R,
+}
+
+impl<R> Prosac<R> {
+    pub fn new(inlier_threshold: f64, max_iterations: usize, rng: R) -> Self {
+        Self {
+            inlier_threshold,
+            max_iterations,
+            confidence: 0.99,
+            rng,
+        }
+    }
+}
+
+impl<R> Consensus<PlaneEstimator, Point3<f64>> for Prosac<R>
+where
+    R: RngCore,
+{
+    type Inliers = Vec<usize>;
+
+    fn model<I>(&mut self, estimator: &PlaneEstimator, data: I) -> Option<PlaneModel>
+    where
+        I: Iterator<Item = Point3<f64>> + Clone,
+    {
+        self.model_inliers(estimator, data).map(|(model, _)| model)
+    }
+
+    fn model_inliers<I>(
+        &mut self,
+        estimator: &PlaneEstimator,
+        data: I,
+    ) -> Option<(PlaneModel, Self::Inliers)>
+    where
+        I: Iterator<Item = Point3<f64>> + Clone,
+    {
+        let points: Vec<_> = data.collect();
+        let num_points = points.len();
+        if num_points < MIN_SAMPLES {
+            return None;
+        }
+
+        let ranked_indices: Vec<usize> = {
+            let mut indices: Vec<_> = (0..num_points).collect();
+            indices.sort_by_cached_key(|&idx| r64(points[idx].coords.norm()));
+            indices
+        };
+
+        // The growth function of the sampling set. `subset_size` grows
+        // by one whenever the iteration reaches `growth_iteration`.
+        let mut subset_size = MIN_SAMPLES;
+        let mut expected_samples = {
+            // The expected number of samples drawn from the top
+            // MIN_SAMPLES points among max_iterations samples.
+            (0..MIN_SAMPLES).fold(self.max_iterations as f64, |prod, idx| {
+                prod * (MIN_SAMPLES - idx) as f64 / (num_points - idx) as f64
+            })
+        };
+        let mut growth_iteration = 1;
+
+        let mut best: Option<(PlaneModel, usize)> = None;
+        let mut iteration = 0;
+
+        while iteration < self.max_iterations {
+            if let Some((_, best_num_inliers)) = best {
+                let required_iterations =
+                    required_iterations(best_num_inliers, num_points, self.confidence);
+                if subset_size == num_points && iteration >= required_iterations {
+                    break;
+                }
+            }
+            iteration += 1;
+
+            while growth_iteration <= iteration && subset_size < num_points {
+                let next_expected_samples = expected_samples * (subset_size + 1) as f64
+                    / (subset_size + 1 - MIN_SAMPLES) as f64;
+                growth_iteration += (next_expected_samples - expected_samples).ceil() as usize;
+                expected_samples = next_expected_samples;
+                subset_size += 1;
+            }
+
+            // Draw the last point of the subset and the others from
+            // the points ranked before it, unless the subset is
+            // already used up.
+            let sample: Vec<_> = if growth_iteration < iteration {
+                index::sample(&mut self.rng, subset_size, MIN_SAMPLES)
+                    .into_iter()
+                    .map(|rank| points[ranked_indices[rank]])
+                    .collect()
+            } else {
+                index::sample(&mut self.rng, subset_size - 1, MIN_SAMPLES - 1)
+                    .into_iter()
+                    .chain([subset_size - 1])
+                    .map(|rank| points[ranked_indices[rank]])
+                    .collect()
+            };
+
+            let model = match estimator.estimate(sample.into_iter()).into_iter().next() {
+                Some(model) => model,
+                None => continue,
+            };
+            let num_inliers = count_inliers(&model, &points, self.inlier_threshold);
+
+            let is_better = match &best {
+                Some((_, best_num_inliers)) => num_inliers > *best_num_inliers,
+                None => true,
+            };
+
+            if is_better {
+                best = Some((model, num_inliers));
+            }
+        }
+
+        let (model, _) = best?;
+        let inliers = find_inliers(&model, &points, self.inlier_threshold);
+        Some((model, inliers))
+    }
+}
+
+/// Estimates a plane from a random minimal sample.
+fn sample_model(
+    estimator: &PlaneEstimator,
+    points: &[Point3<f64>],
+    rng: &mut impl RngCore,
+) -> Option<PlaneModel> {
+    let sample = index::sample(rng, points.len(), MIN_SAMPLES)
+        .into_iter()
+        .map(|idx| points[idx]);
+    estimator.estimate(sample).into_iter().next()
+}
+
+fn count_inliers(model: &PlaneModel, points: &[Point3<f64>], threshold: f64) -> usize {
+    points
+        .iter()
+        .filter(|point| model.residual(*point) <= threshold)
+        .count()
+}
+
+fn find_inliers(model: &PlaneModel, points: &[Point3<f64>], threshold: f64) -> Vec<usize> {
+    points
+        .iter()
+        .enumerate()
+        .filter(|(_, point)| model.residual(*point) <= threshold)
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Computes the number of iterations needed to draw an all-inlier
+/// sample with the given confidence.
+fn required_iterations(num_inliers: usize, num_points: usize, confidence: f64) -> usize {
+    let inlier_ratio = num_inliers as f64 / num_points as f64;
+    let all_inlier_prob = inlier_ratio.powi(MIN_SAMPLES as i32);
+
+    if all_inlier_prob >= 1.0 {
+        return 1;
+    }
+    if all_inlier_prob <= 0.0 {
+        return usize::MAX;
+    }
+
+    let iterations = (1.0 - confidence).ln() / (1.0 - all_inlier_prob).ln();
+    iterations.ceil().max(1.0) as usize
+}
@@ -1,38 +1,100 @@
-use hollow_board_config::BoardModel;
+use crate::{hole::HoleFit, orientation::OrientationSource};
+use hollow_board_config::{BoardModel, BoardShape};
 use nalgebra as na;
-use plane_estimator::PlaneModel;
+use plane_estimator::{PlaneModel, PlaneQuality};
+use serde::{Deserialize, Serialize};
 use std::f64;
 
-#[derive(Debug, Clone)]
-pub struct Detection {
-    pub board_model: BoardModel,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Detection<S = BoardShape> {
+    pub board_model: BoardModel<S>,
     pub plane_ransac_data: PlaneRansacData,
-    pub icp_data: IcpData,
+    pub icp_data: IcpData<S>,
     pub icp_losses: Vec<f64>,
+    /// The result summary of Levenberg–Marquardt refinement. It is
+    /// available only if the board pose is refined by it.
+    pub lm_data: Option<LmData>,
+    /// The loss gap between the best and the runner-up ICP solutions.
+    /// See [FitBoardIcp::ambiguity_margin].
+    pub ambiguity_margin: Option<f64>,
+    /// The circle fits of holes that have enough boundary points.
+    pub hole_fits: Vec<HoleFit>,
+    /// The 6x6 covariance of the board pose, ordered as a translation
+    /// followed by a rotation vector, both in the world frame. It
+    /// follows the layout of ROS `PoseWithCovariance`. It is `None` if
+    /// the pose is not fully constrained by the board points.
+    pub pose_covariance: Option<na::Matrix6<f64>>,
+    /// The confidence score in `[0, 1]` derived from the final loss and
+    /// the pose covariance.
+    pub score: f64,
+    /// How the board orientation among its symmetric poses is decided.
+    pub orientation: OrientationSource,
 }
 
-#[derive(Debug, Clone)]
-pub struct IcpData {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IcpData<S = BoardShape> {
     pub correspondences: Vec<(na::Point3<f64>, na::Point3<f64>)>, // (data_point, model_point)
-    pub board_model: BoardModel,
+    /// The board pose at each iteration, ending with the final pose.
+    pub pose_history: Vec<na::Isometry3<f64>>,
+    pub board_model: BoardModel<S>,
 }
 
-#[derive(Debug, Clone)]
+impl<S> IcpData<S>
+where
+    S: Clone,
+{
+    /// Places the board at each pose in the history, for example to
+    /// render the fitting steps with `BoardModel::render_kiss3d`.
+    pub fn board_model_history(&self) -> Vec<BoardModel<S>> {
+        self.pose_history
+            .iter()
+            .map(|&pose| BoardModel {
+                pose,
+                ..self.board_model.clone()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlaneRansacData {
     pub plane_model: PlaneModel,
     pub inlier_points: Vec<na::Point3<f64>>,
+    /// The covariance of the plane normal. It is available only if the
+    /// plane is refined.
+    pub normal_covariance: Option<na::Matrix3<f64>>,
+    pub quality: PlaneQuality,
 }
 
-#[derive(Debug, Clone)]
-pub struct FitBoardIcp {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FitBoardIcp<S = BoardShape> {
     pub board_pose: na::Isometry3<f64>,
     pub icp_losses: Vec<f64>,
-    pub icp_data: IcpData,
+    pub icp_data: IcpData<S>,
+    pub lm_data: Option<LmData>,
+    /// The loss gap between the best ICP solution and the runner-up
+    /// that places the target corners and holes elsewhere. A small
+    /// margin means the pose is ambiguous. It is available only if the
+    /// ICP runs from multiple starts and another start ends at a
+    /// distinct pose or is rejected.
+    pub ambiguity_margin: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LmData {
+    /// The half sum of squared residuals at the final pose.
+    pub final_cost: f64,
+    /// The 6x6 covariance of the pose perturbation, ordered as a
+    /// rotation vector followed by a translation in the board frame.
+    pub pose_covariance: Option<na::Matrix6<f64>>,
+    pub iterations: usize,
+    pub converged: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct FitPlaneRansac<'a> {
     pub plane_model: PlaneModel,
     pub inlier_points: Vec<&'a na::Point3<f64>>,
+    pub quality: PlaneQuality,
     pub ransac_data: PlaneRansacData,
 }
@@ -1,128 +1,616 @@
 use crate::{
-    algo::{fit_board_icp, fit_plane_ransac},
-    config::Config,
-    detection::{FitBoardIcp, FitPlaneRansac},
+    algo::{
+        confidence_score, estimate_pose_covariance, fit_board_icp, fit_board_icp_from,
+        fit_board_lm, fit_board_lm_from, fit_planes_ransac, plane_matches_board,
+    },
+    config::{BoardRefineMethod, Config, EdgeCompensation},
+    detection::{FitBoardIcp, FitPlaneRansac, PlaneRansacData},
+    edge::{edge_point_weights, shrink_edge_points},
+    failure::DetectionFailure,
+    hole::{find_hole_boundary_points, fit_hole_circle, refine_pose_with_holes, HoleFit},
+    orientation::{
+        orient_by_intensity, orient_by_lowest_corner, orientation_candidates, OrientationSource,
+    },
+    proposal::{propose_boards, Proposals},
+    trace::{DetectionTrace, PlaneAttempt},
     Detection,
 };
-use anyhow::Result;
 use aruco_config::MultiArucoPattern;
-use hollow_board_config::{BoardModel, BoardShape};
-use na::coordinates::XYZ;
+use hollow_board_config::{BoardModel, BoardShape, TargetGeometry};
 use nalgebra as na;
-use noisy_float::prelude::*;
-use std::f64::{self, consts::FRAC_PI_2};
+use plane_estimator::{PlaneModel, PlaneQuality, PlaneRefiner, RobustLoss};
+use std::f64;
 
+/// Detects calibration targets in point clouds.
+///
+/// The target defaults to the hollow board described by the
+/// `board_shape` of the config.
 #[derive(Debug, Clone)]
-pub struct Detector {
+pub struct Detector<S = BoardShape> {
     config: Config,
     aruco_pattern: MultiArucoPattern,
+    target: S,
 }
 
 impl Detector {
     pub fn new(config: Config, aruco_pattern: MultiArucoPattern) -> Self {
+        let target = config.board_shape.clone();
+        Self::with_target(config, aruco_pattern, target)
+    }
+}
+
+impl<S> Detector<S>
+where
+    S: TargetGeometry + Clone + Send + Sync,
+{
+    /// Creates a detector for the given target geometry. The
+    /// `board_shape` of the config is ignored.
+    pub fn with_target(config: Config, aruco_pattern: MultiArucoPattern, target: S) -> Self {
         Self {
             config,
             aruco_pattern,
+            target,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn target(&self) -> &S {
+        &self.target
+    }
+
+    /// Detects a board in the point cloud.
+    ///
+    /// If no board is found, it returns the failure of the first
+    /// board-like plane, or tells that no plane matches the board.
+    pub fn detect(&self, points: &[na::Point3<f64>]) -> Result<Detection<S>, DetectionFailure> {
+        self.detect_points(points, None, None)
+    }
+
+    /// Detects a board in the point cloud with per-point LiDAR
+    /// intensities, which are used to orient the board by the marker
+    /// paper.
+    ///
+    /// # Panics
+    /// It panics if the numbers of points and intensities differ.
+    pub fn detect_with_intensities(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: &[f64],
+    ) -> Result<Detection<S>, DetectionFailure> {
+        assert_intensities(points, intensities);
+        self.detect_points(points, Some(intensities), None)
+    }
+
+    /// Detects a board like [Self::detect] and records the candidate
+    /// planes and the result for offline debugging.
+    pub fn detect_traced(&self, points: &[na::Point3<f64>]) -> DetectionTrace<S> {
+        self.trace_points(points, None)
+    }
+
+    /// Detects a board like [Self::detect_with_intensities] and records
+    /// the candidate planes and the result for offline debugging.
+    ///
+    /// # Panics
+    /// It panics if the numbers of points and intensities differ.
+    pub fn detect_traced_with_intensities(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: &[f64],
+    ) -> DetectionTrace<S> {
+        assert_intensities(points, intensities);
+        self.trace_points(points, Some(intensities))
+    }
+
+    /// Detects all boards in the point cloud.
+    ///
+    /// The points on each found board are removed before searching the
+    /// next one. The search stops when no more boards are found or
+    /// `max_boards` boards are found. It fails only if no board is found
+    /// at all.
+    pub fn detect_all(
+        &self,
+        points: &[na::Point3<f64>],
+    ) -> Result<Vec<Detection<S>>, DetectionFailure> {
+        self.detect_all_points(points, None)
+    }
+
+    /// Detects all boards in the point cloud with per-point LiDAR
+    /// intensities. See [Self::detect_all] and
+    /// [Self::detect_with_intensities].
+    ///
+    /// # Panics
+    /// It panics if the numbers of points and intensities differ.
+    pub fn detect_all_with_intensities(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: &[f64],
+    ) -> Result<Vec<Detection<S>>, DetectionFailure> {
+        assert_intensities(points, intensities);
+        self.detect_all_points(points, Some(intensities))
+    }
+
+    /// Detects the board near its previous pose.
+    ///
+    /// The points within `track_gate_distance` of the previous board
+    /// are refitted to a plane, and the board fitting starts from the
+    /// previous pose instead of searching planes using RANSAC.
+    pub fn detect_near(
+        &self,
+        points: &[na::Point3<f64>],
+        prev_board: &BoardModel<S>,
+    ) -> Result<Detection<S>, DetectionFailure> {
+        self.detect_near_points(points, None, prev_board)
+    }
+
+    /// Detects the board near its previous pose with per-point LiDAR
+    /// intensities. See [Self::detect_near] and
+    /// [Self::detect_with_intensities].
+    ///
+    /// # Panics
+    /// It panics if the numbers of points and intensities differ.
+    pub fn detect_near_with_intensities(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: &[f64],
+        prev_board: &BoardModel<S>,
+    ) -> Result<Detection<S>, DetectionFailure> {
+        assert_intensities(points, intensities);
+        self.detect_near_points(points, Some(intensities), prev_board)
+    }
+
+    /// Proposes board candidates in the point cloud without a manual
+    /// bounding box. See [propose_boards].
+    pub fn propose(&self, points: &[na::Point3<f64>]) -> Proposals {
+        propose_boards(&self.config, &self.target, points)
+    }
+
+    /// Detects boards among the proposed candidates.
+    ///
+    /// The candidates are tried in descending order of score until
+    /// `max_boards` boards are found. It fails only if no board is found
+    /// at all.
+    pub fn detect_proposed(
+        &self,
+        points: &[na::Point3<f64>],
+    ) -> Result<Vec<Detection<S>>, DetectionFailure> {
+        self.detect_proposed_points(points, None)
+    }
+
+    /// Detects boards among the proposed candidates with per-point
+    /// LiDAR intensities. See [Self::detect_proposed] and
+    /// [Self::detect_with_intensities].
+    ///
+    /// # Panics
+    /// It panics if the numbers of points and intensities differ.
+    pub fn detect_proposed_with_intensities(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: &[f64],
+    ) -> Result<Vec<Detection<S>>, DetectionFailure> {
+        assert_intensities(points, intensities);
+        self.detect_proposed_points(points, Some(intensities))
+    }
+
+    /// Removes the points on the board.
+    pub fn remove_board_points(
+        &self,
+        points: &[na::Point3<f64>],
+        board_model: &BoardModel<S>,
+    ) -> Vec<na::Point3<f64>> {
+        let is_on_board = self.board_point_mask(points, board_model);
+        retain_unmasked(points, &is_on_board)
+    }
+
+    /// Marks the points on the board.
+    pub fn board_point_mask(
+        &self,
+        points: &[na::Point3<f64>],
+        board_model: &BoardModel<S>,
+    ) -> Vec<bool> {
+        let outlier_threshold = self.config.sensor_params().outlier_threshold;
+        board_model
+            .find_correspondences(points)
+            .into_iter()
+            .map(|(point, corresponding_point)| {
+                (point - corresponding_point).norm() <= outlier_threshold
+            })
+            .collect()
+    }
+
+    fn trace_points(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: Option<&[f64]>,
+    ) -> DetectionTrace<S> {
+        let mut attempts = vec![];
+        let result = self.detect_points(points, intensities, Some(&mut attempts));
+        DetectionTrace {
+            points: points.to_vec(),
+            intensities: intensities.map(<[f64]>::to_vec),
+            attempts,
+            result,
         }
     }
 
-    pub fn detect(&self, points: &[na::Point3<f64>]) -> Result<Option<Detection>> {
+    /// Detects a board on the candidate planes. Each tried plane is
+    /// recorded in `attempts` if it is given.
+    fn detect_points(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: Option<&[f64]>,
+        mut attempts: Option<&mut Vec<PlaneAttempt>>,
+    ) -> Result<Detection<S>, DetectionFailure> {
+        // fit candidate planes using the configured consensus algorithm
+        let planes = fit_planes_ransac(&self.config, points)?;
+        let num_planes = planes.len();
+        let mut failure = None;
+
+        for plane in planes {
+            let traced_plane = attempts.is_some().then(|| plane.ransac_data.clone());
+            let record = |outcome: Option<&DetectionFailure>| {
+                if let (Some(attempts), Some(plane)) = (attempts.as_deref_mut(), traced_plane) {
+                    attempts.push(PlaneAttempt {
+                        plane,
+                        failure: outcome.cloned(),
+                    });
+                }
+            };
+
+            // skip planes that cannot be the board, such as walls and the ground
+            if !plane_matches_board(&self.config, &self.target, &plane.quality) {
+                let quality = &plane.quality;
+                let mismatch = DetectionFailure::PlaneMismatch {
+                    num_planes,
+                    num_inliers: quality.num_inliers,
+                    planarity: quality.planarity,
+                    major_length: quality.extent.major_length,
+                    minor_length: quality.extent.minor_length,
+                };
+                record(Some(&mismatch));
+                if failure.is_none() {
+                    failure = Some(mismatch);
+                }
+                continue;
+            }
+
+            let result = self.detect_on_plane(plane, None, points, intensities);
+            record(result.as_ref().err());
+            match result {
+                Ok(detection) => return Ok(detection),
+                Err(err) => {
+                    let is_first_fitting_failure = !failure
+                        .as_ref()
+                        .is_some_and(DetectionFailure::is_board_fitting_failure);
+                    if is_first_fitting_failure {
+                        failure = Some(err);
+                    }
+                }
+            }
+        }
+
+        // fit_planes_ransac() returns at least one plane, so the failure is always set
+        Err(failure.unwrap())
+    }
+
+    fn detect_all_points(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: Option<&[f64]>,
+    ) -> Result<Vec<Detection<S>>, DetectionFailure> {
+        let mut remaining_points = points.to_vec();
+        let mut remaining_intensities = intensities.map(<[f64]>::to_vec);
+        let mut detections = vec![];
+
+        while detections.len() < self.config.max_boards {
+            let detection =
+                match self.detect_points(&remaining_points, remaining_intensities.as_deref(), None)
+                {
+                    Ok(detection) => detection,
+                    Err(failure) if detections.is_empty() => return Err(failure),
+                    Err(_) => break,
+                };
+
+            // remove points on the found board
+            let num_points = remaining_points.len();
+            let is_on_board = self.board_point_mask(&remaining_points, &detection.board_model);
+            remaining_points = retain_unmasked(&remaining_points, &is_on_board);
+            remaining_intensities = remaining_intensities
+                .map(|intensities| retain_unmasked(&intensities, &is_on_board));
+            detections.push(detection);
+
+            if remaining_points.len() == num_points {
+                break;
+            }
+        }
+
+        Ok(detections)
+    }
+
+    fn detect_proposed_points(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: Option<&[f64]>,
+    ) -> Result<Vec<Detection<S>>, DetectionFailure> {
+        let Proposals {
+            proposals,
+            num_clusters,
+        } = self.propose(points);
+        let mut detections = vec![];
+        let mut failure = DetectionFailure::NoCandidate {
+            num_points: points.len(),
+            num_clusters,
+        };
+
+        for proposal in proposals {
+            if detections.len() >= self.config.max_boards {
+                break;
+            }
+
+            let proposal_intensities: Option<Vec<f64>> = intensities.map(|intensities| {
+                proposal
+                    .indices
+                    .iter()
+                    .map(|&idx| intensities[idx])
+                    .collect()
+            });
+            match self.detect_points(&proposal.points, proposal_intensities.as_deref(), None) {
+                Ok(detection) => detections.push(detection),
+                Err(err) => {
+                    if !failure.is_board_fitting_failure() {
+                        failure = err;
+                    }
+                }
+            }
+        }
+
+        if detections.is_empty() {
+            return Err(failure);
+        }
+        Ok(detections)
+    }
+
+    fn detect_near_points(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: Option<&[f64]>,
+        prev_board: &BoardModel<S>,
+    ) -> Result<Detection<S>, DetectionFailure> {
         let Config {
-            board_shape:
-                BoardShape {
-                    board_width,
-                    hole_radius,
-                    hole_center_shift,
-                },
+            track_gate_distance,
+            plane_ransac_inlier_threshold,
+            plane_refine_loss,
             ..
         } = self.config;
+
+        let gated_points: Vec<&na::Point3<f64>> = prev_board
+            .find_correspondences(points)
+            .into_iter()
+            .filter_map(|(point, corresponding_point)| {
+                ((point - corresponding_point).norm() <= track_gate_distance).then_some(point)
+            })
+            .collect();
+
+        // refit the board plane on gated points
+        let init_plane = PlaneModel {
+            center: prev_board.board_center(),
+            normal: prev_board.board_z_axis(),
+        };
+        let refiner = PlaneRefiner::new(plane_refine_loss.unwrap_or(RobustLoss::LeastSquares));
+        let refined =
+            refiner
+                .refine(&init_plane, &gated_points)
+                .ok_or(DetectionFailure::NoPlane {
+                    num_points: gated_points.len(),
+                })?;
+        let plane_model = refined.model;
+        let inlier_points: Vec<&na::Point3<f64>> = gated_points
+            .into_iter()
+            .filter(|point| {
+                plane_model.signed_distance(point).abs() <= plane_ransac_inlier_threshold
+            })
+            .collect();
+        let quality = PlaneQuality::compute(&plane_model, &inlier_points, points.len()).ok_or(
+            DetectionFailure::NoPlane {
+                num_points: points.len(),
+            },
+        )?;
+
+        let plane = FitPlaneRansac {
+            ransac_data: PlaneRansacData {
+                plane_model: plane_model.clone(),
+                inlier_points: inlier_points.iter().map(|point| **point).collect(),
+                normal_covariance: Some(refined.normal_covariance),
+                quality: quality.clone(),
+            },
+            plane_model,
+            inlier_points,
+            quality,
+        };
+        self.detect_on_plane(plane, Some(&prev_board.pose), points, intensities)
+    }
+
+    /// Locates the board on a candidate plane. The board fitting starts
+    /// from the initial pose if it is given. The points and intensities
+    /// are used to orient the board.
+    fn detect_on_plane(
+        &self,
+        plane: FitPlaneRansac<'_>,
+        init_pose: Option<&na::Isometry3<f64>>,
+        points: &[na::Point3<f64>],
+        intensities: Option<&[f64]>,
+    ) -> Result<Detection<S>, DetectionFailure> {
         let marker_paper_size = self.aruco_pattern.paper_size();
 
-        // fit plane using AARSAC (a derivation of RANSAC)
         let FitPlaneRansac {
             plane_model,
             inlier_points: plane_inlier_points,
             ransac_data: plane_ransac_data,
-        } = {
-            let ret = fit_plane_ransac(&self.config, points)?;
-            match ret {
-                Some(ret) => ret,
-                None => return Ok(None),
-            }
-        };
+            ..
+        } = plane;
+        let sensor_params = self.config.sensor_params();
+
+        // compensate edge points for the beam divergence
+        let (plane_inlier_points, point_weights): (Vec<na::Point3<f64>>, Option<Vec<f64>>) =
+            match self.config.edge_compensation {
+                EdgeCompensation::None => {
+                    (plane_inlier_points.into_iter().copied().collect(), None)
+                }
+                EdgeCompensation::DownWeight => {
+                    let weights = edge_point_weights(
+                        &plane_inlier_points,
+                        &sensor_params,
+                        self.config.edge_point_weight,
+                    );
+                    (
+                        plane_inlier_points.into_iter().copied().collect(),
+                        Some(weights),
+                    )
+                }
+                EdgeCompensation::Shrink => (
+                    shrink_edge_points(&plane_inlier_points, &sensor_params),
+                    None,
+                ),
+            };
+        let point_weights = point_weights.as_deref();
 
-        // fit board using custom ICP
+        // fit board using custom ICP or Levenberg–Marquardt
         let FitBoardIcp {
             board_pose,
             icp_losses,
             icp_data,
+            lm_data,
+            ambiguity_margin,
         } = {
-            let opt = fit_board_icp(
-                &self.config,
-                &self.aruco_pattern,
-                &plane_model,
-                &plane_inlier_points,
-            )?;
-
-            match opt {
-                Some(ret) => ret,
-                None => return Ok(None),
+            let config = &self.config;
+            let pattern = &self.aruco_pattern;
+            let target = &self.target;
+            let points = &plane_inlier_points;
+            match (config.board_refine_method, init_pose) {
+                (BoardRefineMethod::Icp, None) => {
+                    fit_board_icp(config, pattern, target, &plane_model, points, point_weights)?
+                }
+                (BoardRefineMethod::Icp, Some(init_pose)) => {
+                    fit_board_icp_from(config, pattern, target, init_pose, points, point_weights)?
+                }
+                (BoardRefineMethod::Lm, None) => {
+                    fit_board_lm(config, pattern, target, &plane_model, points, point_weights)?
+                }
+                (BoardRefineMethod::Lm, Some(init_pose)) => {
+                    fit_board_lm_from(config, pattern, target, init_pose, points, point_weights)?
+                }
             }
         };
 
-        let board_model = BoardModel {
-            pose: board_pose,
-            board_shape: BoardShape {
-                board_width,
-                hole_radius,
-                hole_center_shift,
-            },
-            marker_paper_size,
+        // Resolve the symmetric orientations of the board by the marker
+        // paper intensity, or let the board stand on its lowest corner.
+        let (board_model, orientation) = {
+            let board_model = BoardModel {
+                pose: board_pose,
+                board_shape: self.target.clone(),
+                marker_paper_size,
+            };
+            let candidates = orientation_candidates(&board_pose, &self.target);
+            let oriented = intensities.and_then(|intensities| {
+                orient_by_intensity(
+                    &self.config,
+                    &board_model,
+                    &candidates,
+                    marker_paper_size.as_meters(),
+                    points,
+                    intensities,
+                )
+            });
+            let (pose, orientation) = match oriented {
+                Some((pose, contrast)) => (pose, OrientationSource::Intensity { contrast }),
+                None => (
+                    orient_by_lowest_corner(&candidates),
+                    OrientationSource::LowestCorner,
+                ),
+            };
+            (
+                BoardModel {
+                    pose,
+                    ..board_model
+                },
+                orientation,
+            )
         };
 
-        // Correct the board pose to ensure it stands right.
-        let pose = {
-            let board_normal = board_model.board_z_axis();
-
-            let corners = [
-                board_model.bottom_corner(),
-                board_model.left_corner(),
-                board_model.top_corner(),
-                board_model.right_corner(),
-            ];
-            let (lowest_index, _) = corners
-                .iter()
-                .enumerate()
-                .min_by_key(|(_, point)| r64(point.z))
-                .unwrap();
-
-            // Compute the rotation using the by 90deg * index.
-            let fixup_rotation = {
-                let angle = FRAC_PI_2 * lowest_index as f64;
-                na::UnitQuaternion::from_axis_angle(&board_normal, angle)
+        // fit hole circles and optionally refine the pose with them
+        let (board_model, hole_fits) = {
+            let hole_fits: Vec<HoleFit> =
+                find_hole_boundary_points(&board_model, &sensor_params, &plane_inlier_points)
+                    .into_iter()
+                    .filter_map(|(hole, points)| fit_hole_circle(&board_model, hole, points))
+                    .collect();
+
+            let refined_pose = if self.config.hole_refine {
+                let good_fits: Vec<&HoleFit> = hole_fits
+                    .iter()
+                    .filter(|fit| fit.rms_residual <= self.config.hole_fit_max_residual)
+                    .collect();
+                refine_pose_with_holes(&board_model, &good_fits)
+            } else {
+                None
             };
 
-            // Use the position of the lowest point as the new translation.
-            let fixup_translation = {
-                let lowest_point = &corners[lowest_index];
-                let XYZ { x, y, z } = **lowest_point;
-                na::Translation3::new(x, y, z)
+            let board_model = match refined_pose {
+                Some(pose) => BoardModel {
+                    pose,
+                    ..board_model
+                },
+                None => board_model,
             };
+            (board_model, hole_fits)
+        };
 
-            // Construct the corrected pose
-            fixup_translation * fixup_rotation * &board_model.pose.rotation
+        // estimate the pose uncertainty from the final board inliers
+        let (pose_covariance, score) = {
+            let board_inlier_points: Vec<_> = icp_data
+                .correspondences
+                .iter()
+                .map(|(data_point, _)| *data_point)
+                .collect();
+            let pose_covariance =
+                estimate_pose_covariance(&self.config, &board_model, &board_inlier_points);
+            let final_loss = icp_losses.last().copied().unwrap_or(f64::INFINITY);
+            let score = confidence_score(&self.config, final_loss, pose_covariance.as_ref());
+            (pose_covariance, score)
         };
 
         let detection = Detection {
-            board_model: BoardModel {
-                pose,
-                ..board_model
-            },
+            board_model,
             plane_ransac_data,
             icp_data,
             icp_losses,
+            lm_data,
+            ambiguity_margin,
+            hole_fits,
+            pose_covariance,
+            score,
+            orientation,
         };
 
-        Ok(Some(detection))
+        Ok(detection)
     }
 }
+
+pub(crate) fn assert_intensities(points: &[na::Point3<f64>], intensities: &[f64]) {
+    assert_eq!(
+        points.len(),
+        intensities.len(),
+        "the numbers of points and intensities differ"
+    );
+}
+
+/// Keeps the items that are not masked.
+pub(crate) fn retain_unmasked<T: Copy>(items: &[T], mask: &[bool]) -> Vec<T> {
+    items
+        .iter()
+        .zip(mask)
+        .filter_map(|(item, &masked)| (!masked).then_some(*item))
+        .collect()
+}
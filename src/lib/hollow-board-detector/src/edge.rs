@@ -0,0 +1,134 @@
+//! Compensation of mixed pixels at board edges.
+//!
+//! A LiDAR beam spreads into a footprint that grows with the range.
+//! When the footprint straddles the board border or a hole edge, the
+//! return still comes from the partly covered board and is reported
+//! along the beam center. The board looks larger and the holes look
+//! smaller by up to half the footprint.
+//!
+//! Such edge points are the end points of continuous runs of points on
+//! each scan ring. They are either shrunk toward the board along the
+//! ring or down-weighted during board fitting.
+
+use crate::{
+    hole::{azimuth, split_scan_ring_indices, MIN_GAP_FIRINGS},
+    sensor::SensorParams,
+};
+use nalgebra as na;
+use noisy_float::prelude::*;
+use std::{borrow::Borrow, f64};
+
+/// A point at the end of a continuous run of points on a scan ring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdgePoint {
+    /// The index of the edge point.
+    pub index: usize,
+    /// The index of the adjacent point in the same run. It is `None` if
+    /// the point is isolated on its ring.
+    pub neighbor: Option<usize>,
+}
+
+/// Finds the points at the ends of continuous runs on each scan ring.
+///
+/// The points on each ring are sorted by azimuth. A run is broken where
+/// adjacent points are farther apart than a few firings, such as at a
+/// hole or at the board border.
+pub fn find_edge_points<P>(points: &[P], sensor_params: &SensorParams) -> Vec<EdgePoint>
+where
+    P: Borrow<na::Point3<f64>>,
+{
+    if points.is_empty() {
+        return vec![];
+    }
+
+    let min_gap = (sensor_params.horizontal_resolution * MIN_GAP_FIRINGS).to_radians();
+
+    // azimuths are measured from the point centroid to avoid the
+    // wrap-around at the back of the sensor
+    let ref_azimuth = {
+        let sum = points.iter().fold(na::Vector3::zeros(), |sum, point| {
+            sum + point.borrow().coords
+        });
+        azimuth(&na::Point3::from(sum / points.len() as f64))
+    };
+    let relative_azimuth = |index: usize| {
+        (azimuth(points[index].borrow()) - ref_azimuth + f64::consts::PI)
+            .rem_euclid(f64::consts::TAU)
+            - f64::consts::PI
+    };
+
+    let mut edge_points = vec![];
+
+    for mut ring in split_scan_ring_indices(points, sensor_params) {
+        ring.sort_by_key(|&index| r64(relative_azimuth(index)));
+        let azimuths: Vec<f64> = ring.iter().map(|&index| relative_azimuth(index)).collect();
+        let is_gap = |pos: usize| azimuths[pos + 1] - azimuths[pos] >= min_gap;
+
+        for (pos, &index) in ring.iter().enumerate() {
+            let gap_before = pos == 0 || is_gap(pos - 1);
+            let gap_after = pos + 1 == ring.len() || is_gap(pos);
+
+            let neighbor = match (gap_before, gap_after) {
+                (false, false) => continue,
+                (true, false) => Some(ring[pos + 1]),
+                (false, true) => Some(ring[pos - 1]),
+                (true, true) => None,
+            };
+            edge_points.push(EdgePoint { index, neighbor });
+        }
+    }
+
+    edge_points.sort_by_key(|edge| edge.index);
+    edge_points
+}
+
+/// Moves each edge point toward its neighbor on the ring by half the
+/// beam footprint at its range.
+///
+/// The shift is capped at half the distance to the neighbor so the
+/// ring order is kept. Other points are returned unchanged.
+///
+/// The point moves along the ring rather than along the beam, and the
+/// range is kept. Edges that run along the rings, such as the top and
+/// bottom board edges, have no run ends and are left uncompensated.
+pub fn shrink_edge_points<P>(points: &[P], sensor_params: &SensorParams) -> Vec<na::Point3<f64>>
+where
+    P: Borrow<na::Point3<f64>>,
+{
+    let mut shrunk: Vec<na::Point3<f64>> = points.iter().map(|point| *point.borrow()).collect();
+
+    for edge in find_edge_points(points, sensor_params) {
+        let neighbor = match edge.neighbor {
+            Some(neighbor) => *points[neighbor].borrow(),
+            None => continue,
+        };
+        let point = *points[edge.index].borrow();
+        let vec = neighbor - point;
+        let distance = vec.norm();
+        if distance <= 0.0 {
+            continue;
+        }
+
+        let half_footprint = sensor_params.beam_footprint(point.coords.norm()) / 2.0;
+        let shift = half_footprint.min(distance / 2.0);
+        shrunk[edge.index] = point + vec * (shift / distance);
+    }
+
+    shrunk
+}
+
+/// Assigns the edge weight to edge points and 1 to other points.
+pub fn edge_point_weights<P>(
+    points: &[P],
+    sensor_params: &SensorParams,
+    edge_weight: f64,
+) -> Vec<f64>
+where
+    P: Borrow<na::Point3<f64>>,
+{
+    let mut weights = vec![1.0; points.len()];
+    for edge in find_edge_points(points, sensor_params) {
+        weights[edge.index] = edge_weight;
+    }
+    weights
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The reason why a board is not detected.
+///
+/// Each variant tells the stage that failed along with the numbers
+/// that lead to the failure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DetectionFailure {
+    /// The proposal stage finds no board candidate in the point cloud.
+    NoCandidate {
+        num_points: usize,
+        num_clusters: usize,
+    },
+    /// The plane fitting finds no plane in the point cloud.
+    NoPlane { num_points: usize },
+    /// Planes are found but none of them matches the board shape. The
+    /// numbers are taken from the largest plane.
+    PlaneMismatch {
+        num_planes: usize,
+        num_inliers: usize,
+        planarity: f64,
+        major_length: f64,
+        minor_length: f64,
+    },
+    /// There are too few plane inliers to fit the board.
+    TooFewPoints {
+        num_inliers: usize,
+        min_points: usize,
+    },
+    /// The board fitting loss exceeds `icp_rejection_threshold`.
+    LossTooLarge {
+        num_inliers: usize,
+        min_loss: f64,
+        threshold: f64,
+    },
+}
+
+impl DetectionFailure {
+    /// Checks if the failure happens after a board-like plane is
+    /// found.
+    pub fn is_board_fitting_failure(&self) -> bool {
+        !matches!(
+            self,
+            Self::NoCandidate { .. } | Self::NoPlane { .. } | Self::PlaneMismatch { .. }
+        )
+    }
+}
+
+impl fmt::Display for DetectionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NoCandidate {
+                num_points,
+                num_clusters,
+            } => write!(
+                f,
+                "none of {num_clusters} clusters among {num_points} points \
+                 is proposed as a board candidate"
+            ),
+            Self::NoPlane { num_points } => {
+                write!(f, "no plane is found among {num_points} points")
+            }
+            Self::PlaneMismatch {
+                num_planes,
+                num_inliers,
+                planarity,
+                major_length,
+                minor_length,
+            } => write!(
+                f,
+                "none of {num_planes} planes matches the board; \
+                 the largest plane has {num_inliers} inliers, planarity {planarity:.3} \
+                 and extent {major_length:.3}m x {minor_length:.3}m"
+            ),
+            Self::TooFewPoints {
+                num_inliers,
+                min_points,
+            } => write!(
+                f,
+                "{num_inliers} plane inliers are too few to fit the board, \
+                 at least {min_points} are required"
+            ),
+            Self::LossTooLarge {
+                num_inliers,
+                min_loss,
+                threshold,
+            } => write!(
+                f,
+                "the minimum board fitting loss {min_loss:.4} on {num_inliers} plane inliers \
+                 exceeds the threshold {threshold:.4}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DetectionFailure {}
@@ -0,0 +1,279 @@
+//! Hole circle fitting on the calibration target.
+//!
+//! Hole boundary points are found on each scan ring where the ring is
+//! interrupted by a hole. A circle of the known hole radius is fitted
+//! to the boundary points of each hole on the board plane. The fitted
+//! hole centers are used to refine the in-plane board pose.
+
+use crate::sensor::SensorParams;
+use hollow_board_config::{BoardModel, TargetCircle, TargetGeometry};
+use nalgebra as na;
+use noisy_float::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Borrow, f64};
+
+/// A gap between adjacent points on a ring is considered a hole if it
+/// spans this many firings.
+pub(crate) const MIN_GAP_FIRINGS: f64 = 2.5;
+
+/// The circle fitted to the boundary points of a hole.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HoleFit {
+    /// The hole index in [TargetGeometry::holes].
+    pub hole: usize,
+    /// The fitted hole center on the board plane.
+    pub center: na::Point3<f64>,
+    /// The distance from the fitted center to the hole center of the
+    /// board model.
+    pub center_offset: f64,
+    pub boundary_points: Vec<na::Point3<f64>>,
+    /// The root mean square of distances from boundary points to the
+    /// fitted circle.
+    pub rms_residual: f64,
+    /// The maximum distance from boundary points to the fitted circle.
+    pub max_residual: f64,
+}
+
+/// Groups points into scan rings by their elevation angles from the
+/// sensor at the origin.
+///
+/// Adjacent rings are separated if their elevation angles differ by
+/// more than half the beam spacing.
+pub fn split_scan_rings<P>(points: &[P], sensor_params: &SensorParams) -> Vec<Vec<na::Point3<f64>>>
+where
+    P: Borrow<na::Point3<f64>>,
+{
+    split_scan_ring_indices(points, sensor_params)
+        .into_iter()
+        .map(|ring| {
+            ring.into_iter()
+                .map(|index| *points[index].borrow())
+                .collect()
+        })
+        .collect()
+}
+
+/// Groups point indices into scan rings like [split_scan_rings].
+pub fn split_scan_ring_indices<P>(points: &[P], sensor_params: &SensorParams) -> Vec<Vec<usize>>
+where
+    P: Borrow<na::Point3<f64>>,
+{
+    let max_step = (sensor_params.vertical_beam_spacing / 2.0).to_radians();
+
+    let mut indices: Vec<(f64, usize)> = points
+        .iter()
+        .enumerate()
+        .map(|(index, point)| (elevation(point.borrow()), index))
+        .collect();
+    indices.sort_by_key(|(elevation, _)| r64(*elevation));
+
+    let mut rings: Vec<Vec<usize>> = vec![];
+    let mut prev_elevation = f64::NEG_INFINITY;
+
+    for (elevation, index) in indices {
+        match rings.last_mut() {
+            Some(ring) if elevation - prev_elevation <= max_step => ring.push(index),
+            _ => rings.push(vec![index]),
+        }
+        prev_elevation = elevation;
+    }
+
+    rings
+}
+
+/// Finds the boundary points of each hole on the board.
+///
+/// The points on each ring are sorted by azimuth. If two adjacent
+/// points are far apart and the middle of them falls in a hole, both
+/// points are boundary points of that hole.
+pub fn find_hole_boundary_points<S, P>(
+    board_model: &BoardModel<S>,
+    sensor_params: &SensorParams,
+    points: &[P],
+) -> Vec<(usize, Vec<na::Point3<f64>>)>
+where
+    S: TargetGeometry,
+    P: Borrow<na::Point3<f64>>,
+{
+    let min_gap = (sensor_params.horizontal_resolution * MIN_GAP_FIRINGS).to_radians();
+    let inverse_pose = board_model.pose.inverse();
+    let holes = board_model.board_shape.holes();
+
+    // azimuths are measured from the board center to avoid the
+    // wrap-around at the back of the sensor
+    let ref_azimuth = azimuth(&board_model.board_center());
+    let relative_azimuth = |point: &na::Point3<f64>| {
+        (azimuth(point) - ref_azimuth + f64::consts::PI).rem_euclid(f64::consts::TAU)
+            - f64::consts::PI
+    };
+
+    let mut boundary_points: Vec<Vec<na::Point3<f64>>> = vec![vec![]; holes.len()];
+
+    for mut ring in split_scan_rings(points, sensor_params) {
+        ring.sort_by_key(|point| r64(relative_azimuth(point)));
+
+        for pair in ring.windows(2) {
+            let [lhs, rhs] = [pair[0], pair[1]];
+            if relative_azimuth(&rhs) - relative_azimuth(&lhs) < min_gap {
+                continue;
+            }
+
+            // the middle of the gap on the board plane
+            let middle = (inverse_pose * na::center(&lhs, &rhs)).xy();
+            let nearest = holes
+                .iter()
+                .enumerate()
+                .map(|(index, hole)| (index, hole, (middle - hole.center).norm()))
+                .min_by_key(|(_, _, distance)| r64(*distance));
+
+            if let Some((index, hole, distance)) = nearest {
+                if distance < hole.radius {
+                    boundary_points[index].push(lhs);
+                    boundary_points[index].push(rhs);
+                }
+            }
+        }
+    }
+
+    boundary_points.into_iter().enumerate().collect()
+}
+
+/// Fits a circle of the hole radius to the boundary points on the board
+/// plane.
+///
+/// The circle center is initialized at the hole center of the board
+/// model. It returns `None` if there are fewer than 3 points or the
+/// hole index is out of range.
+pub fn fit_hole_circle<S>(
+    board_model: &BoardModel<S>,
+    hole: usize,
+    boundary_points: Vec<na::Point3<f64>>,
+) -> Option<HoleFit>
+where
+    S: TargetGeometry,
+{
+    const MAX_ITERATIONS: usize = 20;
+    const TOLERANCE: f64 = 1e-9;
+
+    if boundary_points.len() < 3 {
+        return None;
+    }
+
+    let TargetCircle {
+        center: model_center,
+        radius: hole_radius,
+    } = board_model.board_shape.holes().get(hole).copied()?;
+    let inverse_pose = board_model.pose.inverse();
+    let planar_points: Vec<na::Point2<f64>> = boundary_points
+        .iter()
+        .map(|point| (inverse_pose * point).xy())
+        .collect();
+
+    // Gauss-Newton on the distances to the circle
+    let mut center = model_center;
+    for _ in 0..MAX_ITERATIONS {
+        let (jtj, jtr) = planar_points.iter().fold(
+            (na::Matrix2::zeros(), na::Vector2::zeros()),
+            |(jtj, jtr), point| {
+                let vec = point - center;
+                let distance = vec.norm();
+                if distance <= 0.0 {
+                    return (jtj, jtr);
+                }
+                let jacobian = -vec / distance;
+                let residual = distance - hole_radius;
+                (
+                    jtj + jacobian * jacobian.transpose(),
+                    jtr + jacobian * residual,
+                )
+            },
+        );
+
+        let step = -jtj.try_inverse()? * jtr;
+        center += step;
+
+        if step.norm() <= TOLERANCE {
+            break;
+        }
+    }
+
+    let residuals: Vec<f64> = planar_points
+        .iter()
+        .map(|point| ((point - center).norm() - hole_radius).abs())
+        .collect();
+    let rms_residual =
+        (residuals.iter().map(|res| res * res).sum::<f64>() / residuals.len() as f64).sqrt();
+    let max_residual = residuals.iter().copied().fold(0.0, f64::max);
+
+    Some(HoleFit {
+        hole,
+        center: board_model.pose * na::Point3::new(center.x, center.y, 0.0),
+        center_offset: (center - model_center).norm(),
+        boundary_points,
+        rms_residual,
+        max_residual,
+    })
+}
+
+/// Refines the board pose by aligning the model hole centers to the
+/// fitted hole centers on the board plane.
+///
+/// It returns `None` if fewer than two holes are fitted.
+pub fn refine_pose_with_holes<S>(
+    board_model: &BoardModel<S>,
+    hole_fits: &[&HoleFit],
+) -> Option<na::Isometry3<f64>>
+where
+    S: TargetGeometry,
+{
+    let holes = board_model.board_shape.holes();
+    let inverse_pose = board_model.pose.inverse();
+    let pairs: Vec<(na::Point2<f64>, na::Point2<f64>)> = hole_fits
+        .iter()
+        .filter_map(|fit| {
+            let model_center = holes.get(fit.hole)?.center;
+            let fitted_center = (inverse_pose * fit.center).xy();
+            Some((model_center, fitted_center))
+        })
+        .collect();
+    if pairs.len() < 2 {
+        return None;
+    }
+
+    // 2D Procrustes alignment from model centers to fitted centers
+    let count = pairs.len() as f64;
+    let model_mean = pairs
+        .iter()
+        .fold(na::Vector2::zeros(), |sum, (model, _)| sum + model.coords)
+        / count;
+    let fitted_mean = pairs
+        .iter()
+        .fold(na::Vector2::zeros(), |sum, (_, fitted)| sum + fitted.coords)
+        / count;
+    let (sin_sum, cos_sum) =
+        pairs
+            .iter()
+            .fold((0.0, 0.0), |(sin_sum, cos_sum), (model, fitted)| {
+                let model = model.coords - model_mean;
+                let fitted = fitted.coords - fitted_mean;
+                (sin_sum + model.perp(&fitted), cos_sum + model.dot(&fitted))
+            });
+    let angle = sin_sum.atan2(cos_sum);
+
+    let rotation = na::Rotation2::new(angle);
+    let translation = fitted_mean - rotation * model_mean;
+    let correction = na::Isometry3::new(
+        na::Vector3::new(translation.x, translation.y, 0.0),
+        na::Vector3::new(0.0, 0.0, angle),
+    );
+
+    Some(board_model.pose * correction)
+}
+
+pub(crate) fn elevation(point: &na::Point3<f64>) -> f64 {
+    point.z.atan2(point.x.hypot(point.y))
+}
+
+pub(crate) fn azimuth(point: &na::Point3<f64>) -> f64 {
+    point.y.atan2(point.x)
+}
@@ -1,6 +1,20 @@
+pub mod accumulator;
 pub mod algo;
 pub mod config;
+pub mod consensus;
 pub mod detection;
 pub mod detector;
+pub mod edge;
+pub mod failure;
+pub mod hole;
+pub mod lm;
+pub mod orientation;
+pub mod proposal;
+pub mod sensor;
+pub mod trace;
+pub mod tracker;
 
-pub use crate::{config::Config, detection::Detection, detector::Detector};
+pub use crate::{
+    accumulator::FrameAccumulator, config::Config, detection::Detection, detector::Detector,
+    failure::DetectionFailure, trace::DetectionTrace, tracker::BoardTracker,
+};
@@ -0,0 +1,446 @@
+//! Levenberg–Marquardt refinement of the board pose.
+//!
+//! The board pose is optimized over two kinds of residuals. Every data
+//! point has a point-to-plane residual, the distance from the point to
+//! the board plane. Each point also has an in-plane edge residual.
+//!
+//! - A point on the boundary of the point cloud is matched to the
+//!   nearest target edge or hole circle.
+//! - Other points are matched to the nearest board material, so the
+//!   residual is non-zero only if the point falls outside the board
+//!   border or inside a hole.
+//!
+//! The pose is updated by a right perturbation `pose * exp(delta)`,
+//! where `delta` is a 6-vector of a rotation vector followed by a
+//! translation, both in the board frame.
+
+use hollow_board_config::{BoardModel, Correspondence, CorrespondenceIndex, TargetGeometry};
+use nalgebra as na;
+use std::{borrow::Borrow, collections::HashMap};
+
+/// Refines the board pose using Levenberg–Marquardt algorithm.
+#[derive(Debug, Clone)]
+pub struct LmRefiner {
+    pub max_iterations: usize,
+    /// The weight of edge residuals relative to point-to-plane
+    /// residuals.
+    pub edge_weight: f64,
+    /// The neighborhood radius in meters used to find points on the
+    /// boundary of the point cloud.
+    pub boundary_radius: f64,
+    /// The iteration stops when the relative cost decrease or the step
+    /// norm is below this value.
+    pub tolerance: f64,
+    /// Outliers are rejected only if the mean point loss is below this
+    /// value in meters.
+    pub good_fit_threshold: f64,
+    /// A point whose loss exceeds this value in meters is an outlier.
+    pub outlier_threshold: f64,
+}
+
+impl LmRefiner {
+    /// The minimum number of points to determine the 6-DoF pose.
+    pub const MIN_POINTS: usize = 6;
+
+    pub fn new(max_iterations: usize, good_fit_threshold: f64, outlier_threshold: f64) -> Self {
+        Self {
+            max_iterations,
+            edge_weight: 1.0,
+            boundary_radius: 0.1,
+            tolerance: 1e-5,
+            good_fit_threshold,
+            outlier_threshold,
+        }
+    }
+
+    /// Refines the pose of the initial board model against the points.
+    ///
+    /// It returns `None` if there are fewer than [Self::MIN_POINTS]
+    /// points.
+    pub fn refine<S, P>(&self, init: &BoardModel<S>, points: &[P]) -> Option<LmSolution>
+    where
+        S: TargetGeometry + Clone,
+        P: Borrow<na::Point3<f64>>,
+    {
+        self.refine_weighted(init, points, None)
+    }
+
+    /// Refines the pose like [Self::refine], scaling the residuals of
+    /// each point by its weight.
+    ///
+    /// The weights are indexed by point. All points have weight 1 if
+    /// the weights are `None`.
+    pub fn refine_weighted<S, P>(
+        &self,
+        init: &BoardModel<S>,
+        points: &[P],
+        weights: Option<&[f64]>,
+    ) -> Option<LmSolution>
+    where
+        S: TargetGeometry + Clone,
+        P: Borrow<na::Point3<f64>>,
+    {
+        if points.len() < Self::MIN_POINTS {
+            return None;
+        }
+
+        // the boundary does not depend on the in-plane pose, so it is
+        // found once on the initial plane
+        let points = self.data_points(init, points, weights);
+        let index = CorrespondenceIndex::new(&init.board_shape);
+        let mut correspondences = Vec::with_capacity(points.len());
+
+        let mut pose = init.pose;
+        let mut lambda = 1e-3;
+        let mut costs = vec![];
+        let mut mean_losses = vec![];
+        let mut poses = vec![];
+        let mut converged = false;
+        let mut iterations = 0;
+
+        while iterations < self.max_iterations {
+            iterations += 1;
+
+            let (active_points, mean_loss) =
+                self.select_points(&index, &pose, &points, &mut correspondences);
+            let lin = self.linearize(&index, &pose, &active_points, &mut correspondences);
+            mean_losses.push(mean_loss);
+            costs.push(lin.cost);
+            poses.push(pose);
+
+            // try steps with increasing damping until the cost decreases
+            let step = loop {
+                if lambda > 1e12 {
+                    break None;
+                }
+
+                let damped = {
+                    let mut mat = lin.jtj;
+                    for idx in 0..6 {
+                        mat[(idx, idx)] += lambda * lin.jtj[(idx, idx)].max(1e-12);
+                    }
+                    mat
+                };
+                let delta = match damped.cholesky() {
+                    Some(chol) => -chol.solve(&lin.jtr),
+                    None => {
+                        lambda *= 10.0;
+                        continue;
+                    }
+                };
+
+                let trial_pose = perturb(&pose, &delta);
+                let trial_cost = self
+                    .linearize(&index, &trial_pose, &active_points, &mut correspondences)
+                    .cost;
+
+                if trial_cost < lin.cost {
+                    lambda = (lambda / 10.0).max(1e-12);
+                    break Some((trial_pose, trial_cost, delta.norm()));
+                }
+
+                lambda *= 10.0;
+            };
+
+            let (trial_pose, trial_cost, step_norm) = match step {
+                Some(step) => step,
+                None => {
+                    // no step can decrease the cost
+                    converged = true;
+                    break;
+                }
+            };
+            pose = trial_pose;
+
+            let cost_decrease = lin.cost - trial_cost;
+            if step_norm <= self.tolerance || cost_decrease <= self.tolerance * lin.cost {
+                converged = true;
+                break;
+            }
+        }
+
+        // evaluate the final pose
+        let (active_points, mean_loss) =
+            self.select_points(&index, &pose, &points, &mut correspondences);
+        let lin = self.linearize(&index, &pose, &active_points, &mut correspondences);
+        mean_losses.push(mean_loss);
+        costs.push(lin.cost);
+        poses.push(pose);
+
+        let pose_covariance = {
+            let dof = lin.num_residuals as f64 - 6.0;
+            let variance = if dof > 0.0 { 2.0 * lin.cost / dof } else { 0.0 };
+            lin.jtj.cholesky().map(|chol| chol.inverse() * variance)
+        };
+
+        let correspondences = active_points
+            .iter()
+            .zip(&correspondences)
+            .map(|(data, correspondence)| (*data.point, correspondence.target_point(&pose)))
+            .collect();
+
+        Some(LmSolution {
+            pose,
+            final_cost: lin.cost,
+            costs,
+            mean_losses,
+            poses,
+            pose_covariance,
+            iterations,
+            converged,
+            correspondences,
+        })
+    }
+
+    /// Estimates the covariance of the pose perturbation `delta` at the
+    /// model pose.
+    ///
+    /// The residual variance is the larger one of the squared range
+    /// noise and the residual variance of the fit. It returns `None` if
+    /// the pose is not fully constrained by the points.
+    pub fn pose_covariance<S, P>(
+        &self,
+        model: &BoardModel<S>,
+        points: &[P],
+        range_noise: f64,
+    ) -> Option<na::Matrix6<f64>>
+    where
+        S: TargetGeometry,
+        P: Borrow<na::Point3<f64>>,
+    {
+        if points.len() < Self::MIN_POINTS {
+            return None;
+        }
+
+        let points = self.data_points(model, points, None);
+        let index = CorrespondenceIndex::new(&model.board_shape);
+        let mut correspondences = Vec::with_capacity(points.len());
+        let lin = self.linearize(&index, &model.pose, &points, &mut correspondences);
+        let dof = lin.num_residuals as f64 - 6.0;
+        let fit_variance = if dof > 0.0 { 2.0 * lin.cost / dof } else { 0.0 };
+        let variance = fit_variance.max(range_noise.powi(2));
+        lin.jtj.cholesky().map(|chol| chol.inverse() * variance)
+    }
+
+    /// Pairs each point with whether it is on the boundary of the
+    /// point cloud and its weight.
+    fn data_points<'a, S, P>(
+        &self,
+        model: &BoardModel<S>,
+        points: &'a [P],
+        weights: Option<&[f64]>,
+    ) -> Vec<DataPoint<'a>>
+    where
+        S: TargetGeometry,
+        P: Borrow<na::Point3<f64>>,
+    {
+        let points: Vec<&na::Point3<f64>> = points.iter().map(|point| point.borrow()).collect();
+        let is_boundary = self.find_boundary_points(model, &points);
+        points
+            .into_iter()
+            .zip(is_boundary)
+            .enumerate()
+            .map(|(index, (point, is_boundary))| DataPoint {
+                point,
+                is_boundary,
+                weight: weights.map_or(1.0, |weights| weights[index]),
+            })
+            .collect()
+    }
+
+    /// Marks points on the boundary of the point cloud.
+    ///
+    /// A point is on the boundary if its neighbors on the board plane
+    /// lie mostly on one side, that is, the neighbor centroid is far
+    /// from the point.
+    fn find_boundary_points<S>(
+        &self,
+        model: &BoardModel<S>,
+        points: &[&na::Point3<f64>],
+    ) -> Vec<bool>
+    where
+        S: TargetGeometry,
+    {
+        let inverse_pose = model.pose.inverse();
+        let radius = self.boundary_radius;
+        let planar_points: Vec<na::Point2<f64>> = points
+            .iter()
+            .map(|point| (inverse_pose * *point).xy())
+            .collect();
+
+        // bucket the points into a grid of radius-sized cells, so the
+        // neighbors of a point lie in the 3x3 cells around its cell
+        let cell_of = |point: &na::Point2<f64>| -> [i64; 2] {
+            [
+                (point.x / radius).floor() as i64,
+                (point.y / radius).floor() as i64,
+            ]
+        };
+        let mut grid: HashMap<[i64; 2], Vec<usize>> = HashMap::new();
+        for (index, point) in planar_points.iter().enumerate() {
+            grid.entry(cell_of(point)).or_default().push(index);
+        }
+
+        planar_points
+            .iter()
+            .map(|point| {
+                let [cx, cy] = cell_of(point);
+                let (sum, count) = (-1..=1)
+                    .flat_map(|dx| (-1..=1).map(move |dy| [cx + dx, cy + dy]))
+                    .filter_map(|cell| grid.get(&cell))
+                    .flatten()
+                    .map(|&other| &planar_points[other])
+                    .filter(|other| (*other - point).norm() <= radius)
+                    .fold((na::Vector2::zeros(), 0), |(sum, count), other| {
+                        (sum + (other - point), count + 1)
+                    });
+
+                // the point itself is counted
+                if count <= 3 {
+                    return true;
+                }
+                let shift = (sum / (count - 1) as f64).norm();
+                shift > radius / 3.0
+            })
+            .collect()
+    }
+
+    /// Selects points used in an iteration and computes the mean point
+    /// loss over all points.
+    fn select_points<'a>(
+        &self,
+        index: &CorrespondenceIndex,
+        pose: &na::Isometry3<f64>,
+        points: &[DataPoint<'a>],
+        correspondences: &mut Vec<Correspondence>,
+    ) -> (Vec<DataPoint<'a>>, f64) {
+        index.find_correspondences_into(
+            pose,
+            points.iter().map(|data| data.point),
+            correspondences,
+        );
+        let mean_loss = correspondences
+            .iter()
+            .map(|correspondence| correspondence.distance())
+            .sum::<f64>()
+            / correspondences.len() as f64;
+
+        let active_points = if mean_loss <= self.good_fit_threshold {
+            points
+                .iter()
+                .zip(correspondences.iter())
+                .filter_map(|(data, correspondence)| {
+                    (correspondence.distance() < self.outlier_threshold).then_some(*data)
+                })
+                .collect()
+        } else {
+            points.to_vec()
+        };
+
+        (active_points, mean_loss)
+    }
+
+    /// Computes the cost and the normal equations at the pose.
+    ///
+    /// The correspondences of the points are left in the buffer.
+    fn linearize(
+        &self,
+        index: &CorrespondenceIndex,
+        pose: &na::Isometry3<f64>,
+        points: &[DataPoint<'_>],
+        correspondences: &mut Vec<Correspondence>,
+    ) -> Linearization {
+        index.find_correspondences_into(
+            pose,
+            points.iter().map(|data| data.point),
+            correspondences,
+        );
+
+        let mut lin = Linearization {
+            cost: 0.0,
+            jtj: na::Matrix6::zeros(),
+            jtr: na::Vector6::zeros(),
+            num_residuals: 0,
+        };
+        let mut add_residual = |residual: f64, jacobian: na::Vector6<f64>| {
+            lin.cost += residual * residual / 2.0;
+            lin.jtj += jacobian * jacobian.transpose();
+            lin.jtr += jacobian * residual;
+            lin.num_residuals += 1;
+        };
+
+        for (data, correspondence) in points.iter().zip(correspondences.iter()) {
+            // the point in the board frame
+            let local_point = correspondence.local_point;
+
+            // point-to-plane residual
+            {
+                let jacobian = na::Vector6::new(-local_point.y, local_point.x, 0.0, 0.0, 0.0, -1.0)
+                    * data.weight;
+                add_residual(local_point.z * data.weight, jacobian);
+            }
+
+            // edge residual
+            let target = if data.is_boundary {
+                index.closest_boundary_point(&local_point.xy())
+            } else {
+                correspondence.local_target
+            };
+            let in_plane =
+                na::Vector3::new(local_point.x - target.x, local_point.y - target.y, 0.0);
+            let distance = in_plane.norm();
+            if distance > 0.0 {
+                let dir = in_plane / distance;
+                let rot_part = dir.cross(&local_point.coords);
+                let jacobian =
+                    na::Vector6::new(rot_part.x, rot_part.y, rot_part.z, -dir.x, -dir.y, -dir.z)
+                        * (self.edge_weight * data.weight);
+                add_residual(distance * self.edge_weight * data.weight, jacobian);
+            }
+        }
+
+        lin
+    }
+}
+
+/// The result of [LmRefiner::refine].
+#[derive(Debug, Clone)]
+pub struct LmSolution {
+    pub pose: na::Isometry3<f64>,
+    /// The cost at the final pose, which is the half sum of squared
+    /// residuals.
+    pub final_cost: f64,
+    /// The cost at each iteration including the final pose.
+    pub costs: Vec<f64>,
+    /// The mean point loss at each iteration including the final pose.
+    pub mean_losses: Vec<f64>,
+    /// The pose at each iteration including the final pose.
+    pub poses: Vec<na::Isometry3<f64>>,
+    /// The covariance of the pose perturbation `delta`. It is `None`
+    /// if the pose is not fully constrained by the residuals.
+    pub pose_covariance: Option<na::Matrix6<f64>>,
+    pub iterations: usize,
+    pub converged: bool,
+    /// The (data_point, model_point) pairs at the final pose.
+    pub correspondences: Vec<(na::Point3<f64>, na::Point3<f64>)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DataPoint<'a> {
+    point: &'a na::Point3<f64>,
+    is_boundary: bool,
+    weight: f64,
+}
+
+struct Linearization {
+    cost: f64,
+    jtj: na::Matrix6<f64>,
+    jtr: na::Vector6<f64>,
+    num_residuals: usize,
+}
+
+fn perturb(pose: &na::Isometry3<f64>, delta: &na::Vector6<f64>) -> na::Isometry3<f64> {
+    let rotation = na::Vector3::new(delta[0], delta[1], delta[2]);
+    let translation = na::Vector3::new(delta[3], delta[4], delta[5]);
+    pose * na::Isometry3::new(translation, rotation)
+}
@@ -0,0 +1,156 @@
+//! Board orientation disambiguation.
+//!
+//! The target border is symmetric under some rotations, so the fitted
+//! pose may place its origin at any of the equivalent corners, or look
+//! at the target from behind. The marker paper covers the square of
+//! `marker_paper_size` at the origin corner and reflects the laser
+//! differently from the bare board, so LiDAR intensities tell which
+//! corner is the origin. Without intensities, the corner lowest in z is
+//! taken as the origin.
+
+use crate::config::Config;
+use hollow_board_config::{BoardModel, TargetGeometry};
+use nalgebra as na;
+use noisy_float::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::{PI, TAU};
+
+/// How the board orientation is decided.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OrientationSource {
+    /// The marker paper is located by LiDAR intensities.
+    Intensity {
+        /// The intensity contrast between the marker paper and the bare
+        /// board, `|paper - bare| / (paper + bare)`.
+        contrast: f64,
+    },
+    /// The lowest corner is taken as the origin.
+    LowestCorner,
+}
+
+/// Lists the poses that place the target border at the same place as
+/// the given pose, with the target normal facing the sensor at the
+/// origin.
+///
+/// The candidates are rotations about the target center by multiples
+/// of `2π / symmetry_order`, optionally flipped about the line through
+/// the center parallel to the x axis.
+pub fn orientation_candidates<S>(pose: &na::Isometry3<f64>, target: &S) -> Vec<na::Isometry3<f64>>
+where
+    S: TargetGeometry,
+{
+    let order = target.symmetry_order();
+    let center = {
+        let center = target.center();
+        na::Vector3::new(center.x, center.y, 0.0)
+    };
+    let flip = na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), PI);
+
+    let candidates: Vec<_> = (0..order)
+        .flat_map(|index| {
+            let angle = TAU * index as f64 / order as f64;
+            let rotation = na::UnitQuaternion::from_axis_angle(&na::Vector3::z_axis(), angle);
+            [rotation, rotation * flip]
+        })
+        .map(|rotation| {
+            *pose * na::Translation3::from(center) * rotation * na::Translation3::from(-center)
+        })
+        .collect();
+
+    let facing_sensor: Vec<_> = candidates
+        .iter()
+        .filter(|candidate| {
+            let normal = candidate.rotation * na::Vector3::z_axis();
+            let center = candidate.transform_point(&na::Point3::from(center));
+            (na::Point3::origin() - center).dot(&normal) >= 0.0
+        })
+        .copied()
+        .collect();
+
+    // the sensor lies on the target plane, so the facing is undefined
+    if facing_sensor.is_empty() {
+        candidates.into_iter().step_by(2).collect()
+    } else {
+        facing_sensor
+    }
+}
+
+/// Chooses the candidate whose origin corner is the lowest in z.
+pub fn orient_by_lowest_corner(candidates: &[na::Isometry3<f64>]) -> na::Isometry3<f64> {
+    *candidates
+        .iter()
+        .min_by_key(|pose| r64(pose.translation.vector.z))
+        .unwrap()
+}
+
+/// Chooses the candidate that places the marker paper at the
+/// brightest or darkest corner.
+///
+/// Points within `outlier_threshold` of the board material are used.
+/// For each candidate, the points in the square of `marker_paper_size`
+/// meters at the origin corner are on the paper, and the rest are on
+/// the bare board. It returns the candidate with the largest intensity
+/// contrast along with the contrast, or `None` if no candidate has
+/// `intensity_min_points` points on both parts and a contrast of at
+/// least `intensity_min_contrast`.
+pub fn orient_by_intensity<S>(
+    config: &Config,
+    board_model: &BoardModel<S>,
+    candidates: &[na::Isometry3<f64>],
+    marker_paper_size: f64,
+    points: &[na::Point3<f64>],
+    intensities: &[f64],
+) -> Option<(na::Isometry3<f64>, f64)>
+where
+    S: TargetGeometry,
+{
+    let Config {
+        intensity_min_contrast,
+        intensity_min_points,
+        ..
+    } = *config;
+    let outlier_threshold = config.sensor_params().outlier_threshold;
+
+    let board_points: Vec<(&na::Point3<f64>, f64)> = board_model
+        .find_correspondences(points)
+        .into_iter()
+        .zip(intensities)
+        .filter_map(|((point, corresponding_point), &intensity)| {
+            let on_board = (point - corresponding_point).norm() <= outlier_threshold;
+            (on_board && intensity.is_finite()).then_some((point, intensity))
+        })
+        .collect();
+
+    let in_paper = |coord: f64| (0.0..=marker_paper_size).contains(&coord);
+
+    candidates
+        .iter()
+        .filter_map(|candidate| {
+            let inverse_pose = candidate.inverse();
+            let (paper, bare) = board_points.iter().fold(
+                ((0.0, 0), (0.0, 0)),
+                |(paper, bare), &(point, intensity)| {
+                    let local = inverse_pose * point;
+                    if in_paper(local.x) && in_paper(local.y) {
+                        ((paper.0 + intensity, paper.1 + 1), bare)
+                    } else {
+                        (paper, (bare.0 + intensity, bare.1 + 1))
+                    }
+                },
+            );
+            if paper.1 < intensity_min_points || bare.1 < intensity_min_points {
+                return None;
+            }
+
+            let paper_mean = paper.0 / paper.1 as f64;
+            let bare_mean = bare.0 / bare.1 as f64;
+            let total = paper_mean + bare_mean;
+            if total <= 0.0 {
+                return None;
+            }
+            let contrast = (paper_mean - bare_mean).abs() / total;
+            Some((*candidate, contrast))
+        })
+        .max_by_key(|(_, contrast)| r64(*contrast))
+        .filter(|(_, contrast)| *contrast >= intensity_min_contrast)
+}
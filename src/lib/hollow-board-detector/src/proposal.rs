@@ -0,0 +1,248 @@
+//! Board candidate proposal.
+//!
+//! Board candidates are proposed without a manual bounding box. Points
+//! on the ground are removed first, and the rest are grouped into
+//! Euclidean clusters. A cluster is proposed if its dominant plane
+//! matches the target size, and the proposals are ranked by how likely
+//! they are to be the target.
+
+use crate::{
+    algo::{fit_plane_ransac, plane_matches_board},
+    config::Config,
+    consensus::Msac,
+};
+use hollow_board_config::TargetGeometry;
+use nalgebra as na;
+use noisy_float::prelude::*;
+use plane_estimator::{ExtractedPlane, MultiPlaneExtractor, PlaneQuality};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::collections::{HashMap, VecDeque};
+
+/// The maximum number of ground planes to remove.
+const MAX_GROUND_PLANES: usize = 3;
+
+/// A point cluster that may contain the target.
+#[derive(Debug, Clone)]
+pub struct BoardProposal {
+    /// Indices to the input points.
+    pub indices: Vec<usize>,
+    pub points: Vec<na::Point3<f64>>,
+    /// The quality of the dominant plane in the cluster.
+    pub quality: PlaneQuality,
+    /// The likelihood of being the target in range [0, 1].
+    pub score: f64,
+}
+
+/// The result of the proposal stage.
+#[derive(Debug, Clone)]
+pub struct Proposals {
+    /// The board candidates in descending order of score.
+    pub proposals: Vec<BoardProposal>,
+    /// The number of clusters before size and planarity gating.
+    pub num_clusters: usize,
+}
+
+/// Proposes board candidates in the point cloud.
+///
+/// Near-horizontal planes larger than the target diagonal are taken as
+/// the ground and removed. The remaining points are clustered with
+/// `proposal_cluster_tolerance`, and clusters with fewer than
+/// `proposal_min_cluster_points` points are dropped. A cluster is kept
+/// if its dominant plane passes [plane_matches_board]. The score is the
+/// product of the plane flatness, the plane inlier ratio and how well
+/// the plane extent fits the target size.
+pub fn propose_boards<S>(config: &Config, target: &S, points: &[na::Point3<f64>]) -> Proposals
+where
+    S: TargetGeometry,
+{
+    let Config {
+        proposal_cluster_tolerance,
+        proposal_min_cluster_points,
+        ..
+    } = *config;
+
+    let is_ground = find_ground_points(config, target, points);
+    let remaining_indices: Vec<usize> = (0..points.len()).filter(|&idx| !is_ground[idx]).collect();
+
+    let clusters: Vec<_> =
+        euclidean_clusters(points, &remaining_indices, proposal_cluster_tolerance)
+            .into_iter()
+            .filter(|cluster| cluster.len() >= proposal_min_cluster_points)
+            .collect();
+    let num_clusters = clusters.len();
+
+    let mut proposals: Vec<_> = clusters
+        .into_iter()
+        .filter_map(|indices| {
+            let cluster_points: Vec<_> = indices.iter().map(|&idx| points[idx]).collect();
+            let quality = fit_plane_ransac(config, &cluster_points).ok()?.quality;
+            if !plane_matches_board(config, target, &quality) {
+                return None;
+            }
+
+            let score = quality.flatness.clamp(0.0, 1.0)
+                * quality.inlier_ratio
+                * size_score(target, quality.extent.major_length);
+            Some(BoardProposal {
+                indices,
+                points: cluster_points,
+                quality,
+                score,
+            })
+        })
+        .collect();
+    proposals.sort_by_key(|proposal| -r64(proposal.score));
+
+    Proposals {
+        proposals,
+        num_clusters,
+    }
+}
+
+/// Marks the points on near-horizontal planes larger than the target.
+fn find_ground_points<S>(config: &Config, target: &S, points: &[na::Point3<f64>]) -> Vec<bool>
+where
+    S: TargetGeometry,
+{
+    let Config {
+        proposal_ground_threshold,
+        proposal_ground_max_tilt,
+        plane_ransac_max_iterations,
+        plane_ransac_seed,
+        plane_extent_tolerance,
+        ..
+    } = *config;
+
+    let rng = match plane_ransac_seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    };
+    let mut msac = Msac::new(proposal_ground_threshold, plane_ransac_max_iterations, rng);
+    let extractor = MultiPlaneExtractor {
+        max_planes: Some(MAX_GROUND_PLANES),
+        ..MultiPlaneExtractor::new(proposal_ground_threshold)
+    };
+
+    let max_tilt = proposal_ground_max_tilt.to_radians();
+    let diagonal = target.width().hypot(target.height());
+    let mut is_ground = vec![false; points.len()];
+
+    for plane in extractor.extract(&mut msac, points) {
+        let ExtractedPlane {
+            model,
+            inlier_indices,
+            ..
+        } = plane;
+
+        let tilt = model
+            .normal
+            .dot(&na::Vector3::z_axis())
+            .abs()
+            .min(1.0)
+            .acos();
+        if tilt > max_tilt {
+            continue;
+        }
+
+        let inlier_points: Vec<_> = inlier_indices.iter().map(|&idx| &points[idx]).collect();
+        let quality = match PlaneQuality::compute(&model, &inlier_points, points.len()) {
+            Some(quality) => quality,
+            None => continue,
+        };
+        if quality.extent.major_length <= diagonal * (1.0 + plane_extent_tolerance) {
+            continue;
+        }
+
+        inlier_indices
+            .into_iter()
+            .for_each(|idx| is_ground[idx] = true);
+    }
+
+    is_ground
+}
+
+/// Groups the selected points into clusters. Two points are in the same
+/// cluster if they are connected by a chain of points, each within
+/// `tolerance` of the next.
+fn euclidean_clusters(
+    points: &[na::Point3<f64>],
+    indices: &[usize],
+    tolerance: f64,
+) -> Vec<Vec<usize>> {
+    let cell_of = |point: &na::Point3<f64>| -> [i64; 3] {
+        [
+            (point.x / tolerance).floor() as i64,
+            (point.y / tolerance).floor() as i64,
+            (point.z / tolerance).floor() as i64,
+        ]
+    };
+
+    // the grid maps each cell to the positions in indices
+    let mut grid: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    for (pos, &idx) in indices.iter().enumerate() {
+        grid.entry(cell_of(&points[idx])).or_default().push(pos);
+    }
+
+    let mut visited = vec![false; indices.len()];
+    let mut clusters = vec![];
+
+    for seed in 0..indices.len() {
+        if visited[seed] {
+            continue;
+        }
+        visited[seed] = true;
+
+        let mut cluster = vec![];
+        let mut queue = VecDeque::from([seed]);
+
+        while let Some(pos) = queue.pop_front() {
+            let point = &points[indices[pos]];
+            cluster.push(indices[pos]);
+
+            let [cx, cy, cz] = cell_of(point);
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        let neighbors = match grid.get(&[cx + dx, cy + dy, cz + dz]) {
+                            Some(neighbors) => neighbors,
+                            None => continue,
+                        };
+                        for &other in neighbors {
+                            if !visited[other]
+                                && (points[indices[other]] - point).norm() <= tolerance
+                            {
+                                visited[other] = true;
+                                queue.push_back(other);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        cluster.sort_unstable();
+        clusters.push(cluster);
+    }
+
+    clusters
+}
+
+/// Scores how well the plane extent fits the target. It is 1 if the
+/// major length lies between the shorter target side and the diagonal,
+/// and decreases with the ratio to the nearer bound otherwise.
+fn size_score<S>(target: &S, major_length: f64) -> f64
+where
+    S: TargetGeometry,
+{
+    let min_length = target.width().min(target.height());
+    let max_length = target.width().hypot(target.height());
+
+    if major_length < min_length {
+        major_length / min_length
+    } else if major_length > max_length {
+        max_length / major_length
+    } else {
+        1.0
+    }
+}
@@ -0,0 +1,134 @@
+//! LiDAR sensor profiles that determine detection thresholds.
+
+use serde::{Deserialize, Serialize};
+
+/// A named LiDAR model with preset detection parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorProfile {
+    /// Velodyne VLP-16.
+    Vlp16,
+    /// Velodyne Puck Hi-Res, the VLP-16 with 20 degree vertical FoV.
+    PuckHiRes,
+    /// Velodyne VLP-32C, also known as the Ultra Puck or 32-MR.
+    #[default]
+    Vlp32c,
+    /// Velodyne HDL-32E.
+    Hdl32e,
+    /// Ouster OS1 with 64 channels.
+    Os1,
+}
+
+impl SensorProfile {
+    /// Gets the preset parameters of the sensor.
+    pub fn params(&self) -> SensorParams {
+        match self {
+            Self::Vlp16 => SensorParams {
+                good_fit_threshold: 0.02,
+                outlier_threshold: 0.1,
+                range_noise: 0.03,
+                vertical_beam_spacing: 2.0,
+                horizontal_resolution: 0.2,
+                beam_divergence: 3.0,
+            },
+            Self::PuckHiRes => SensorParams {
+                good_fit_threshold: 0.02,
+                outlier_threshold: 0.1,
+                range_noise: 0.03,
+                vertical_beam_spacing: 1.33,
+                horizontal_resolution: 0.2,
+                beam_divergence: 3.0,
+            },
+            Self::Vlp32c => SensorParams {
+                good_fit_threshold: 0.015,
+                outlier_threshold: 0.1,
+                range_noise: 0.03,
+                vertical_beam_spacing: 0.33,
+                horizontal_resolution: 0.2,
+                beam_divergence: 3.0,
+            },
+            Self::Hdl32e => SensorParams {
+                good_fit_threshold: 0.02,
+                outlier_threshold: 0.1,
+                range_noise: 0.02,
+                vertical_beam_spacing: 1.33,
+                horizontal_resolution: 0.16,
+                beam_divergence: 2.79,
+            },
+            Self::Os1 => SensorParams {
+                good_fit_threshold: 0.1,
+                outlier_threshold: 0.2,
+                range_noise: 0.05,
+                vertical_beam_spacing: 0.7,
+                horizontal_resolution: 0.35,
+                beam_divergence: 3.1,
+            },
+        }
+    }
+}
+
+/// Sensor dependent parameters of the detector.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorParams {
+    /// Outliers are rejected during board fitting only if the mean
+    /// point loss is below this value in meters.
+    pub good_fit_threshold: f64,
+    /// A point whose loss exceeds this value in meters is an outlier
+    /// during board fitting.
+    pub outlier_threshold: f64,
+    /// The standard deviation of range measurements in meters.
+    pub range_noise: f64,
+    /// The angle between adjacent beams in degrees. It is the densest
+    /// spacing if beams are not evenly distributed.
+    pub vertical_beam_spacing: f64,
+    /// The angle between adjacent firings in degrees.
+    pub horizontal_resolution: f64,
+    /// The full divergence angle of a beam in milliradians.
+    pub beam_divergence: f64,
+}
+
+impl SensorParams {
+    /// Computes the distance between adjacent scan lines at the range.
+    pub fn scan_line_spacing(&self, range: f64) -> f64 {
+        range * self.vertical_beam_spacing.to_radians()
+    }
+
+    /// Computes the diameter of the beam footprint at the range.
+    pub fn beam_footprint(&self, range: f64) -> f64 {
+        range * self.beam_divergence * 1e-3
+    }
+
+    /// Applies the overrides on the parameters.
+    pub fn with_overrides(self, overrides: &SensorOverrides) -> Self {
+        let SensorOverrides {
+            good_fit_threshold,
+            outlier_threshold,
+            range_noise,
+            vertical_beam_spacing,
+            horizontal_resolution,
+            beam_divergence,
+        } = *overrides;
+
+        Self {
+            good_fit_threshold: good_fit_threshold.unwrap_or(self.good_fit_threshold),
+            outlier_threshold: outlier_threshold.unwrap_or(self.outlier_threshold),
+            range_noise: range_noise.unwrap_or(self.range_noise),
+            vertical_beam_spacing: vertical_beam_spacing.unwrap_or(self.vertical_beam_spacing),
+            horizontal_resolution: horizontal_resolution.unwrap_or(self.horizontal_resolution),
+            beam_divergence: beam_divergence.unwrap_or(self.beam_divergence),
+        }
+    }
+}
+
+/// Overrides on individual fields of [SensorParams]. Fields left
+/// unset keep the values of the sensor profile.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SensorOverrides {
+    pub good_fit_threshold: Option<f64>,
+    pub outlier_threshold: Option<f64>,
+    pub range_noise: Option<f64>,
+    pub vertical_beam_spacing: Option<f64>,
+    pub horizontal_resolution: Option<f64>,
+    pub beam_divergence: Option<f64>,
+}
@@ -0,0 +1,55 @@
+//! Detection traces for offline debugging.
+//!
+//! A trace keeps the input points, every candidate plane tried by the
+//! detector and the detection result, including the board pose at each
+//! fitting iteration. It is saved as JSON or as compact MessagePack, so
+//! failed detections can be attached to bug reports and replayed.
+
+use crate::{detection::PlaneRansacData, failure::DetectionFailure, Detection};
+use hollow_board_config::BoardShape;
+use nalgebra as na;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// The record of a detection run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectionTrace<S = BoardShape> {
+    pub points: Vec<na::Point3<f64>>,
+    /// The per-point LiDAR intensities if they are given.
+    pub intensities: Option<Vec<f64>>,
+    /// The candidate planes in the order they are tried.
+    pub attempts: Vec<PlaneAttempt>,
+    pub result: Result<Detection<S>, DetectionFailure>,
+}
+
+/// A candidate plane tried by the detector.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaneAttempt {
+    pub plane: PlaneRansacData,
+    /// The reason why no board is located on the plane, or `None` if
+    /// the board is found on it.
+    pub failure: Option<DetectionFailure>,
+}
+
+impl<S> DetectionTrace<S>
+where
+    S: Serialize + DeserializeOwned,
+{
+    pub fn write_json<W: Write>(&self, writer: W) -> serde_json::Result<()> {
+        serde_json::to_writer(writer, self)
+    }
+
+    pub fn read_json<R: Read>(reader: R) -> serde_json::Result<Self> {
+        serde_json::from_reader(reader)
+    }
+
+    /// Writes the trace in MessagePack. Structs are written as maps so
+    /// that the trace is readable by other MessagePack tools.
+    pub fn write_msgpack<W: Write>(&self, mut writer: W) -> Result<(), rmp_serde::encode::Error> {
+        rmp_serde::encode::write_named(&mut writer, self)
+    }
+
+    pub fn read_msgpack<R: Read>(reader: R) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_read(reader)
+    }
+}
@@ -0,0 +1,313 @@
+//! Board tracking across consecutive frames.
+
+use crate::{
+    config::Config,
+    detector::{assert_intensities, retain_unmasked, Detector},
+    failure::DetectionFailure,
+    Detection,
+};
+use hollow_board_config::{BoardModel, BoardShape, TargetGeometry};
+use nalgebra as na;
+use std::mem;
+
+/// Tracks boards across consecutive frames.
+///
+/// Each board is fitted starting from its pose in the previous frame.
+/// If the fitting of any board fails or a board moves too far, the
+/// tracker falls back to the full detection on the frame.
+#[derive(Debug, Clone)]
+pub struct BoardTracker<S = BoardShape> {
+    detector: Detector<S>,
+    tracks: Vec<Track<S>>,
+}
+
+/// How a board is found in a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackSource {
+    /// The board is fitted starting from its previous pose.
+    Tracked,
+    /// The board is found by the full detection.
+    Detected,
+}
+
+/// A board found by [BoardTracker::track].
+#[derive(Debug, Clone)]
+pub struct TrackedBoard<S = BoardShape> {
+    pub detection: Detection<S>,
+    /// The exponentially smoothed board pose. It is reset to the
+    /// detected pose when a new board appears.
+    pub smoothed_pose: na::Isometry3<f64>,
+    /// The final fitting loss in this frame.
+    pub residual: f64,
+    pub source: TrackSource,
+}
+
+#[derive(Debug, Clone)]
+struct Track<S> {
+    board_model: BoardModel<S>,
+    smoothed_pose: na::Isometry3<f64>,
+}
+
+impl<S> BoardTracker<S>
+where
+    S: TargetGeometry + Clone + Send + Sync,
+{
+    pub fn new(detector: Detector<S>) -> Self {
+        Self {
+            detector,
+            tracks: vec![],
+        }
+    }
+
+    pub fn detector(&self) -> &Detector<S> {
+        &self.detector
+    }
+
+    /// Forgets all tracked boards. The next frame runs the full
+    /// detection.
+    pub fn reset(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// Finds boards in the next frame.
+    ///
+    /// It fails if the full detection finds no board, and all tracked
+    /// boards are forgotten in that case.
+    pub fn track(
+        &mut self,
+        points: &[na::Point3<f64>],
+    ) -> Result<Vec<TrackedBoard<S>>, DetectionFailure> {
+        self.track_points(points, None, false)
+    }
+
+    /// Finds boards in the next frame with per-point LiDAR intensities,
+    /// which are used to orient the boards. See [Self::track].
+    ///
+    /// # Panics
+    /// It panics if the numbers of points and intensities differ.
+    pub fn track_with_intensities(
+        &mut self,
+        points: &[na::Point3<f64>],
+        intensities: &[f64],
+    ) -> Result<Vec<TrackedBoard<S>>, DetectionFailure> {
+        assert_intensities(points, intensities);
+        self.track_points(points, Some(intensities), false)
+    }
+
+    /// Finds boards in the next frame like [Self::track], but the full
+    /// detection runs on the proposed board candidates, so the points
+    /// need not be cropped around the boards. See
+    /// [Detector::detect_proposed].
+    pub fn track_proposed(
+        &mut self,
+        points: &[na::Point3<f64>],
+    ) -> Result<Vec<TrackedBoard<S>>, DetectionFailure> {
+        self.track_points(points, None, true)
+    }
+
+    /// Finds boards in the next frame like [Self::track_proposed] with
+    /// per-point LiDAR intensities.
+    ///
+    /// # Panics
+    /// It panics if the numbers of points and intensities differ.
+    pub fn track_proposed_with_intensities(
+        &mut self,
+        points: &[na::Point3<f64>],
+        intensities: &[f64],
+    ) -> Result<Vec<TrackedBoard<S>>, DetectionFailure> {
+        assert_intensities(points, intensities);
+        self.track_points(points, Some(intensities), true)
+    }
+
+    fn track_points(
+        &mut self,
+        points: &[na::Point3<f64>],
+        intensities: Option<&[f64]>,
+        propose: bool,
+    ) -> Result<Vec<TrackedBoard<S>>, DetectionFailure> {
+        let (detections, source) = match self.track_boards(points, intensities) {
+            Some(detections) => (detections, TrackSource::Tracked),
+            None => match self.detect_all(points, intensities, propose) {
+                Ok(detections) => (detections, TrackSource::Detected),
+                Err(failure) => {
+                    self.tracks.clear();
+                    return Err(failure);
+                }
+            },
+        };
+
+        let config = self.detector.config();
+        let prev_tracks = mem::take(&mut self.tracks);
+        let matched_tracks = match_tracks(config, &prev_tracks, &detections);
+
+        let boards: Vec<TrackedBoard<S>> = detections
+            .into_iter()
+            .zip(matched_tracks)
+            .map(|(detection, track)| {
+                let pose = &detection.board_model.pose;
+
+                // continue smoothing if the board is matched to a previous one
+                let smoothed_pose = match track {
+                    Some(track) => smooth_pose(&track.smoothed_pose, pose, config.track_smoothing),
+                    None => *pose,
+                };
+                let residual = detection
+                    .icp_losses
+                    .last()
+                    .copied()
+                    .unwrap_or(f64::INFINITY);
+
+                TrackedBoard {
+                    detection,
+                    smoothed_pose,
+                    residual,
+                    source,
+                }
+            })
+            .collect();
+
+        self.tracks = boards
+            .iter()
+            .map(|board| Track {
+                board_model: board.detection.board_model.clone(),
+                smoothed_pose: board.smoothed_pose,
+            })
+            .collect();
+
+        Ok(boards)
+    }
+
+    /// Fits each tracked board starting from its previous pose. It
+    /// returns `None` if there are no tracked boards or any of them
+    /// diverges.
+    fn track_boards(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: Option<&[f64]>,
+    ) -> Option<Vec<Detection<S>>> {
+        if self.tracks.is_empty() {
+            return None;
+        }
+
+        let config = self.detector.config();
+        let mut remaining_points = points.to_vec();
+        let mut remaining_intensities = intensities.map(<[f64]>::to_vec);
+
+        self.tracks
+            .iter()
+            .map(|track| {
+                let prev_board = &track.board_model;
+                let detection = match &remaining_intensities {
+                    Some(intensities) => self.detector.detect_near_with_intensities(
+                        &remaining_points,
+                        intensities,
+                        prev_board,
+                    ),
+                    None => self.detector.detect_near(&remaining_points, prev_board),
+                }
+                .ok()?;
+                if !is_close(config, &prev_board.pose, &detection.board_model.pose) {
+                    return None;
+                }
+
+                // remove points on the found board
+                let is_on_board = self
+                    .detector
+                    .board_point_mask(&remaining_points, &detection.board_model);
+                remaining_points = retain_unmasked(&remaining_points, &is_on_board);
+                remaining_intensities = remaining_intensities
+                    .take()
+                    .map(|intensities| retain_unmasked(&intensities, &is_on_board));
+                Some(detection)
+            })
+            .collect()
+    }
+
+    /// Runs the full detection on the whole point cloud, or on the
+    /// proposed board candidates if `propose` is set.
+    fn detect_all(
+        &self,
+        points: &[na::Point3<f64>],
+        intensities: Option<&[f64]>,
+        propose: bool,
+    ) -> Result<Vec<Detection<S>>, DetectionFailure> {
+        match (intensities, propose) {
+            (Some(intensities), false) => self
+                .detector
+                .detect_all_with_intensities(points, intensities),
+            (None, false) => self.detector.detect_all(points),
+            (Some(intensities), true) => self
+                .detector
+                .detect_proposed_with_intensities(points, intensities),
+            (None, true) => self.detector.detect_proposed(points),
+        }
+    }
+}
+
+/// Matches each detection to at most one previous track, and each
+/// track to at most one detection.
+///
+/// Among the pairs within the tracking limits, the pair with the least
+/// translation is matched first, and its detection and track are not
+/// matched again.
+fn match_tracks<'a, S>(
+    config: &Config,
+    tracks: &'a [Track<S>],
+    detections: &[Detection<S>],
+) -> Vec<Option<&'a Track<S>>> {
+    let mut pairs: Vec<(f64, usize, usize)> = detections
+        .iter()
+        .enumerate()
+        .flat_map(|(detection_index, detection)| {
+            let pose = &detection.board_model.pose;
+            tracks
+                .iter()
+                .enumerate()
+                .filter(move |(_, track)| is_close(config, &track.board_model.pose, pose))
+                .map(move |(track_index, track)| {
+                    let distance = (pose.translation.vector
+                        - track.board_model.pose.translation.vector)
+                        .norm();
+                    (distance, detection_index, track_index)
+                })
+        })
+        .collect();
+    pairs.sort_by(|lhs, rhs| lhs.0.total_cmp(&rhs.0));
+
+    let mut matches = vec![None; detections.len()];
+    let mut is_matched = vec![false; tracks.len()];
+    for (_, detection_index, track_index) in pairs {
+        if matches[detection_index].is_some() || is_matched[track_index] {
+            continue;
+        }
+        matches[detection_index] = Some(&tracks[track_index]);
+        is_matched[track_index] = true;
+    }
+
+    matches
+}
+
+/// Checks if the pose change is within the tracking limits.
+fn is_close(config: &Config, prev: &na::Isometry3<f64>, curr: &na::Isometry3<f64>) -> bool {
+    let translation = (curr.translation.vector - prev.translation.vector).norm();
+    let rotation = prev.rotation.angle_to(&curr.rotation);
+    translation <= config.track_max_translation
+        && rotation <= config.track_max_rotation.to_radians()
+}
+
+/// Moves the smoothed pose towards the current pose by the weight.
+fn smooth_pose(
+    smoothed: &na::Isometry3<f64>,
+    curr: &na::Isometry3<f64>,
+    weight: f64,
+) -> na::Isometry3<f64> {
+    let translation = smoothed
+        .translation
+        .vector
+        .lerp(&curr.translation.vector, weight);
+    let rotation = smoothed
+        .rotation
+        .try_slerp(&curr.rotation, weight, 1e-9)
+        .unwrap_or(curr.rotation);
+    na::Isometry3::from_parts(translation.into(), rotation)
+}
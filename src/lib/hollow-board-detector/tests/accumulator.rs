@@ -0,0 +1,103 @@
+mod common;
+
+use common::{board_model, detector, diamond_pose};
+use hollow_board_config::BoardModel;
+use hollow_board_detector::{sensor::SensorProfile, Detection, FrameAccumulator};
+use lidar_simulator::{BeamModel, Scene, Simulator};
+use nalgebra as na;
+
+#[test]
+fn keep_latest_frames() {
+    let mut accumulator = FrameAccumulator::new(2, None);
+    for idx in 0..3 {
+        accumulator.push(&[na::Point3::new(idx as f64, 0.0, 0.0)]);
+    }
+
+    assert!(accumulator.is_full());
+    assert_eq!(accumulator.num_frames(), 2);
+    assert_eq!(
+        accumulator.points(),
+        vec![
+            na::Point3::new(1.0, 0.0, 0.0),
+            na::Point3::new(2.0, 0.0, 0.0)
+        ]
+    );
+}
+
+#[test]
+fn merge_points_in_voxels() {
+    let mut accumulator = FrameAccumulator::new(3, Some(0.1));
+    accumulator.push(&[na::Point3::new(0.01, 0.01, 0.01)]);
+    accumulator.push(&[na::Point3::new(0.03, 0.03, 0.03)]);
+    accumulator.push(&[na::Point3::new(0.25, 0.01, 0.01)]);
+
+    let points = accumulator.points();
+    assert_eq!(points.len(), 2);
+    assert!((points[0] - na::Point3::new(0.02, 0.02, 0.02)).norm() < 1e-12);
+    assert!((points[1] - na::Point3::new(0.25, 0.01, 0.01)).norm() < 1e-12);
+}
+
+/// Scans the board with every other ring of [common::scan], starting
+/// from the given ring offset, like a sparser LiDAR nodding between
+/// frames.
+fn sparse_scan(model: &BoardModel, ring_offset: usize) -> Vec<na::Point3<f64>> {
+    let params = SensorProfile::Vlp32c.params();
+    let beam_model = BeamModel {
+        ring_elevations: (-20..20)
+            .map(|idx| (idx * 2 + ring_offset as i32) as f64 * params.vertical_beam_spacing)
+            .collect(),
+        azimuth_resolution: params.horizontal_resolution,
+        azimuth_range: [
+            -300.0 * params.horizontal_resolution,
+            299.0 * params.horizontal_resolution,
+        ],
+        range_noise: 0.0,
+        ..BeamModel::vlp32c()
+    };
+    Simulator::new(beam_model, 0)
+        .scan(&Scene::new(vec![model.clone()]))
+        .points
+}
+
+fn num_hole_boundary_points(detection: &Detection) -> usize {
+    detection
+        .hole_fits
+        .iter()
+        .map(|fit| fit.boundary_points.len())
+        .sum()
+}
+
+#[test]
+fn detect_on_accumulated_points() {
+    let center = na::Point3::new(5.0, 0.5, 0.2);
+    let model = board_model(diamond_pose(center));
+    let clutter = na::Point3::new(20.0, -3.0, 1.0);
+    let detector = detector();
+
+    let single_detection = detector.detect(&sparse_scan(&model, 0)).unwrap();
+
+    let mut accumulator = FrameAccumulator::new(2, Some(0.005));
+    accumulator.push(&[clutter]);
+    accumulator.focus_on(model.clone(), 0.1);
+
+    // the frames hit the board on interleaved rings
+    for ring_offset in 0..2 {
+        let mut points = sparse_scan(&model, ring_offset);
+        points.push(clutter);
+        accumulator.push(&points);
+    }
+
+    let points = accumulator.points();
+    assert!(points.iter().all(|point| *point != clutter));
+
+    let detection = detector.detect(&points).unwrap();
+    assert!((detection.board_model.board_center() - center).norm() < 0.05);
+
+    // the interleaved rings cross the holes at more places
+    let num_single = num_hole_boundary_points(&single_detection);
+    let num_accumulated = num_hole_boundary_points(&detection);
+    assert!(
+        num_accumulated > num_single,
+        "{num_accumulated} accumulated hole boundary points, {num_single} in a single frame"
+    );
+}
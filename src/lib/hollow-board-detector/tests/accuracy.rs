@@ -0,0 +1,66 @@
+mod common;
+
+use common::{board_model, detector, diamond_pose};
+use lidar_simulator::{BeamModel, Scene, Simulator};
+use nalgebra as na;
+
+/// Places the board at the range in the direction of the azimuth,
+/// turned by the yaw about the vertical axis through its center.
+fn place_board(range: f64, azimuth: f64, yaw: f64) -> na::Isometry3<f64> {
+    let (azimuth, yaw) = (azimuth.to_radians(), yaw.to_radians());
+    let center = na::Point3::new(range * azimuth.cos(), range * azimuth.sin(), 0.0);
+    let turn = na::UnitQuaternion::from_axis_angle(&na::Vector3::z_axis(), azimuth + yaw);
+    na::Translation3::from(center.coords)
+        * turn
+        * na::Translation3::from(-center.coords)
+        * diamond_pose(center)
+}
+
+/// Detects boards in noisy scans and checks the center and normal
+/// errors against the ground truth.
+fn assert_accuracy(beam_model: BeamModel, ranges: &[f64], max_center_error: f64) {
+    let detector = detector();
+    let mut simulator = Simulator::new(beam_model, 3);
+
+    for &range in ranges {
+        for (azimuth, yaw) in [(0.0, 0.0), (20.0, -30.0), (-15.0, 30.0)] {
+            let truth = board_model(place_board(range, azimuth, yaw));
+            let scan = simulator.scan(&Scene::new(vec![truth.clone()]));
+
+            let detection = detector
+                .detect_with_intensities(&scan.points, &scan.intensities)
+                .unwrap_or_else(|failure| {
+                    panic!("range {range} azimuth {azimuth} yaw {yaw}: {failure}")
+                });
+            let board_model = &detection.board_model;
+
+            let center_error = (board_model.board_center() - truth.board_center()).norm();
+            assert!(
+                center_error < max_center_error,
+                "range {range} azimuth {azimuth} yaw {yaw}: center error {center_error}"
+            );
+
+            let normal_error = board_model
+                .board_z_axis()
+                .dot(&truth.board_z_axis())
+                .abs()
+                .min(1.0)
+                .acos()
+                .to_degrees();
+            assert!(
+                normal_error < 3.0,
+                "range {range} azimuth {azimuth} yaw {yaw}: normal error {normal_error}"
+            );
+        }
+    }
+}
+
+#[test]
+fn vlp32c_accuracy() {
+    assert_accuracy(BeamModel::vlp32c(), &[4.0, 8.0, 12.0], 0.05);
+}
+
+#[test]
+fn puck_hi_res_accuracy() {
+    assert_accuracy(BeamModel::puck_hi_res(), &[3.0, 6.0], 0.08);
+}
@@ -0,0 +1,134 @@
+#![allow(dead_code)]
+
+use aruco_config::{ArucoDictionary, MultiArucoPattern};
+use hollow_board_config::{BoardModel, BoardShape, TargetGeometry};
+use hollow_board_detector::{
+    config::{BoardRefineMethod, EdgeCompensation},
+    sensor::SensorProfile,
+    Config, Detector,
+};
+use lidar_simulator::{BeamModel, Scene, Simulator};
+use measurements::Length;
+use nalgebra as na;
+use noisy_float::prelude::*;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+pub fn board_model(pose: na::Isometry3<f64>) -> BoardModel {
+    BoardModel {
+        pose,
+        marker_paper_size: Length::from_millimeters(500.0),
+        board_shape: BoardShape {
+            board_width: Length::from_millimeters(1000.0),
+            hole_radius: Length::from_millimeters(150.0),
+            hole_center_shift: Length::from_millimeters(200.0),
+        },
+    }
+}
+
+/// Places the board as a diamond facing the -x direction with its
+/// center at the given point.
+pub fn diamond_pose(center: na::Point3<f64>) -> na::Isometry3<f64> {
+    target_pose(&board_model(na::Isometry3::identity()).board_shape, center)
+}
+
+/// Places the target rotated like [diamond_pose] with its center at
+/// the given point.
+pub fn target_pose(target: &impl TargetGeometry, center: na::Point3<f64>) -> na::Isometry3<f64> {
+    let rotation = na::UnitQuaternion::from_euler_angles(0.0, -FRAC_PI_2, 0.0)
+        * na::UnitQuaternion::from_euler_angles(0.0, 0.0, -FRAC_PI_4);
+    let target_center = target.center();
+    let translation = center - rotation * na::Point3::new(target_center.x, target_center.y, 0.0);
+    na::Isometry3::from_parts(translation.into(), rotation)
+}
+
+/// Casts rays from the origin in the VLP-32C resolution and keeps hits
+/// on the target material. The beams are evenly spaced and noiseless.
+pub fn scan<S>(model: &BoardModel<S>) -> Vec<na::Point3<f64>>
+where
+    S: TargetGeometry + Clone,
+{
+    let params = SensorProfile::Vlp32c.params();
+    let beam_model = BeamModel {
+        ring_elevations: (-40..40)
+            .map(|idx| idx as f64 * params.vertical_beam_spacing)
+            .collect(),
+        azimuth_resolution: params.horizontal_resolution,
+        azimuth_range: [
+            -300.0 * params.horizontal_resolution,
+            299.0 * params.horizontal_resolution,
+        ],
+        range_noise: 0.0,
+        ..BeamModel::vlp32c()
+    };
+    Simulator::new(beam_model, 0)
+        .scan(&Scene::new(vec![model.clone()]))
+        .points
+}
+
+/// Builds a reproducible detector using Levenberg–Marquardt fitting
+/// that tries up to 3 planes.
+pub fn detector() -> Detector {
+    Detector::new(config(), pattern())
+}
+
+/// Builds a detector like [detector] that fits the board by ICP.
+pub fn icp_detector() -> Detector {
+    let config = Config {
+        board_refine_method: BoardRefineMethod::Icp,
+        max_icp_iterations: 500,
+        ..config()
+    };
+    Detector::new(config, pattern())
+}
+
+/// Builds a detector like [detector] with the edge compensation and
+/// the board fitting method.
+pub fn edge_detector(
+    edge_compensation: EdgeCompensation,
+    board_refine_method: BoardRefineMethod,
+) -> Detector {
+    let config = Config {
+        edge_compensation,
+        board_refine_method,
+        max_icp_iterations: 500,
+        ..config()
+    };
+    Detector::new(config, pattern())
+}
+
+/// Builds a detector like [detector] for the given target.
+pub fn target_detector<S>(target: S) -> Detector<S>
+where
+    S: TargetGeometry + Clone + Send + Sync,
+{
+    Detector::with_target(config(), pattern(), target)
+}
+
+/// Loads the example configuration with reproducible plane fitting
+/// and Levenberg–Marquardt board fitting.
+pub fn config() -> Config {
+    let config = include_str!("../../examples/board_detector.json5")
+        .replace(
+            r#""board_refine_method": "icp""#,
+            r#""board_refine_method": "lm""#,
+        )
+        .replace(
+            r#""plane_ransac_strategy": "arrsac""#,
+            r#""plane_ransac_strategy": "msac""#,
+        )
+        .replace(r#""plane_ransac_seed": null"#, r#""plane_ransac_seed": 1"#);
+    json5::from_str(&config).unwrap()
+}
+
+pub fn pattern() -> MultiArucoPattern {
+    MultiArucoPattern {
+        marker_ids: vec![696, 64, 306, 195],
+        dictionary: ArucoDictionary::DICT_5X5_1000,
+        board_size: Length::from_millimeters(500.0),
+        board_border_size: Length::from_millimeters(10.0),
+        marker_square_size_ratio: r64(0.8),
+        num_squares_per_side: 2,
+        marker_grid: None,
+        border_bits: 1,
+    }
+}
@@ -0,0 +1,74 @@
+use hollow_board_detector::consensus::{LoRansac, Msac, Prosac};
+use nalgebra as na;
+use plane_estimator::{PlaneEstimator, PlaneModel};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sample_consensus::Consensus;
+
+/// Generates a noisy board plane x = 5 mixed with uniform clutter.
+fn board_with_clutter() -> Vec<na::Point3<f64>> {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+
+    let board = (0..400).map(|_| {
+        na::Point3::new(
+            5.0 + rng.gen_range(-0.01..0.01),
+            rng.gen_range(-0.5..0.5),
+            rng.gen_range(0.0..1.0),
+        )
+    });
+    let board: Vec<_> = board.collect();
+    let clutter = (0..200).map(|_| {
+        na::Point3::new(
+            rng.gen_range(2.0..8.0),
+            rng.gen_range(-3.0..3.0),
+            rng.gen_range(-1.0..2.0),
+        )
+    });
+
+    board.into_iter().chain(clutter).collect()
+}
+
+fn fit<C>(consensus: &mut C, points: &[na::Point3<f64>]) -> (PlaneModel, Vec<usize>)
+where
+    C: Consensus<PlaneEstimator, na::Point3<f64>, Inliers = Vec<usize>>,
+{
+    consensus
+        .model_inliers(&PlaneEstimator::new(), points.iter().cloned())
+        .expect("no plane found")
+}
+
+fn assert_board_plane(model: &PlaneModel, inliers: &[usize]) {
+    assert!(model.normal.x.abs() > 0.99);
+    assert!((model.center.x - 5.0).abs() < 0.05);
+    assert!(inliers.len() >= 390, "only {} inliers", inliers.len());
+}
+
+#[test]
+fn strategies_find_board_plane() {
+    let points = board_with_clutter();
+    let rng = || ChaCha8Rng::seed_from_u64(0);
+
+    let (model, inliers) = fit(&mut Msac::new(0.03, 500, rng()), &points);
+    assert_board_plane(&model, &inliers);
+
+    let (model, inliers) = fit(&mut LoRansac::new(0.03, 500, rng()), &points);
+    assert_board_plane(&model, &inliers);
+
+    let (model, inliers) = fit(&mut Prosac::new(0.03, 500, rng()), &points);
+    assert_board_plane(&model, &inliers);
+}
+
+#[test]
+fn same_seed_same_result() {
+    let points = board_with_clutter();
+
+    let run = |seed| {
+        let (model, inliers) = fit(
+            &mut LoRansac::new(0.03, 500, ChaCha8Rng::seed_from_u64(seed)),
+            &points,
+        );
+        (model.center, model.normal, inliers)
+    };
+
+    assert_eq!(run(42), run(42));
+}
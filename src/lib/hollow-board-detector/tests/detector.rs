@@ -0,0 +1,210 @@
+mod common;
+
+use common::{
+    board_model, config, detector, diamond_pose, icp_detector, pattern, scan, target_pose,
+};
+use hollow_board_config::{BoardModel, Rectangle};
+use hollow_board_detector::{
+    algo::fit_board_icp, orientation::OrientationSource, Config, DetectionFailure, Detector,
+};
+use measurements::Length;
+use nalgebra as na;
+use std::f64::consts::PI;
+
+#[test]
+fn detect_all_boards() {
+    let centers = [
+        na::Point3::new(5.0, 1.2, 0.3),
+        na::Point3::new(6.0, -1.2, 0.0),
+    ];
+    let points: Vec<_> = centers
+        .iter()
+        .flat_map(|center| scan(&board_model(diamond_pose(*center))))
+        .collect();
+
+    let detections = detector().detect_all(&points).unwrap();
+    assert_eq!(detections.len(), 2);
+
+    for center in centers {
+        let found = detections
+            .iter()
+            .any(|detection| (detection.board_model.board_center() - center).norm() < 0.05);
+        assert!(found, "board at {center} is not detected");
+    }
+}
+
+#[test]
+fn report_no_plane_failure() {
+    let failure = detector().detect(&[]).unwrap_err();
+    assert_eq!(failure, DetectionFailure::NoPlane { num_points: 0 });
+    assert!(!failure.is_board_fitting_failure());
+}
+
+#[test]
+fn report_plane_mismatch_failure() {
+    // a wall far larger than the board diagonal
+    let points: Vec<_> = (0..=80)
+        .flat_map(|iy| {
+            (0..=40).map(move |iz| {
+                na::Point3::new(6.0, -2.0 + iy as f64 * 0.05, -1.0 + iz as f64 * 0.05)
+            })
+        })
+        .collect();
+
+    let failure = detector().detect(&points).unwrap_err();
+    assert!(
+        matches!(
+            failure,
+            DetectionFailure::PlaneMismatch {
+                num_inliers,
+                major_length,
+                ..
+            } if num_inliers == points.len() && major_length > 3.9
+        ),
+        "unexpected failure {failure:?}"
+    );
+    assert!(!failure.is_board_fitting_failure());
+}
+
+#[test]
+fn report_loss_too_large_failure() {
+    let config = Config {
+        icp_rejection_threshold: 0.02,
+        ..config()
+    };
+    let detector = Detector::new(config, pattern());
+
+    // the real board is accepted under the tight threshold
+    let center = na::Point3::new(5.0, 0.2, 0.1);
+    let points = scan(&board_model(diamond_pose(center)));
+    detector.detect(&points).unwrap();
+
+    // a solid plank passes the plane extent check but does not fit the
+    // board border and holes
+    let plank = Rectangle {
+        width: Length::from_millimeters(1600.0),
+        height: Length::from_millimeters(800.0),
+    };
+    let plank_model = BoardModel {
+        pose: target_pose(&plank, center),
+        marker_paper_size: Length::from_millimeters(500.0),
+        board_shape: plank,
+    };
+    let points = scan(&plank_model);
+
+    let failure = detector.detect(&points).unwrap_err();
+    assert!(
+        matches!(
+            failure,
+            DetectionFailure::LossTooLarge {
+                min_loss,
+                threshold,
+                ..
+            } if min_loss > threshold
+        ),
+        "unexpected failure {failure:?}"
+    );
+    assert!(failure.is_board_fitting_failure());
+}
+
+#[test]
+fn report_pose_covariance_and_score() {
+    let center = na::Point3::new(5.0, 1.2, 0.3);
+    let points = scan(&board_model(diamond_pose(center)));
+
+    let detection = detector().detect(&points).unwrap();
+    let covariance = detection.pose_covariance.expect("pose is not constrained");
+    assert!(covariance.diagonal().iter().all(|&var| var > 0.0));
+    assert!((covariance - covariance.transpose()).norm() < 1e-12);
+    assert!(detection.score > 0.5 && detection.score <= 1.0);
+}
+
+#[test]
+fn orient_board_by_intensity() {
+    // the board hangs upside down, so the paper corner is the highest
+    let upside_down = {
+        let center = na::Vector3::new(0.5, 0.5, 0.0);
+        na::Translation3::from(center)
+            * na::UnitQuaternion::from_axis_angle(&na::Vector3::z_axis(), PI)
+            * na::Translation3::from(-center)
+    };
+    let truth = board_model(diamond_pose(na::Point3::new(5.0, 0.4, 0.3)) * upside_down);
+    let points = scan(&truth);
+    let intensities: Vec<f64> = points
+        .iter()
+        .map(|point| {
+            let local = truth.pose.inverse() * point;
+            if local.x <= 0.5 && local.y <= 0.5 {
+                80.0
+            } else {
+                20.0
+            }
+        })
+        .collect();
+    let detector = detector();
+
+    let detection = detector
+        .detect_with_intensities(&points, &intensities)
+        .unwrap();
+    assert!(matches!(
+        detection.orientation,
+        OrientationSource::Intensity { contrast } if contrast > 0.3
+    ));
+    let origin_error = (detection.board_model.bottom_corner() - truth.bottom_corner()).norm();
+    assert!(origin_error < 0.05, "origin error {origin_error}");
+
+    // without intensities, the board stands on its lowest corner
+    let detection = detector.detect(&points).unwrap();
+    assert_eq!(detection.orientation, OrientationSource::LowestCorner);
+    let origin_error = (detection.board_model.bottom_corner() - truth.top_corner()).norm();
+    assert!(origin_error < 0.05, "origin error {origin_error}");
+}
+
+#[test]
+fn fit_board_icp_from_multiple_starts() {
+    // the board lies on its side, away from the upright initial guess
+    let center = na::Point3::new(5.0, 0.4, 0.1);
+    let pose = diamond_pose(center)
+        * na::Translation3::new(0.5, 0.5, 0.0)
+        * na::UnitQuaternion::from_euler_angles(0.0, 0.0, PI / 2.0)
+        * na::Translation3::new(-0.5, -0.5, 0.0);
+    let truth = board_model(pose);
+    let points = scan(&truth);
+
+    let detector = icp_detector();
+    let detection = detector.detect(&points).unwrap();
+    let center_error = (detection.board_model.board_center() - center).norm();
+    assert!(center_error < 0.05, "center error {center_error}");
+
+    // The detection re-orients the board by its lowest corner, so the
+    // ICP fit is checked directly. The holes tell the origin corner up
+    // to the mirror about the board diagonal, which keeps the origin.
+    let plane = &detection.plane_ransac_data;
+    let fit = fit_board_icp(
+        detector.config(),
+        &pattern(),
+        detector.target(),
+        &plane.plane_model,
+        &plane.inlier_points,
+        None,
+    )
+    .unwrap();
+    let fitted = board_model(fit.board_pose);
+
+    let origin_error = (fitted.bottom_corner() - truth.bottom_corner()).norm();
+    assert!(origin_error < 0.05, "origin error {origin_error}");
+    for index in 0..3 {
+        let hole_center = truth.hole_center(index).unwrap();
+        let found =
+            (0..3).any(|other| (fitted.hole_center(other).unwrap() - hole_center).norm() < 0.05);
+        assert!(found, "hole {index} at {hole_center} is not matched");
+    }
+
+    // a start rotated to misplace a hole ends well above the best loss
+    let best_loss = fit.icp_losses.iter().copied().fold(f64::INFINITY, f64::min);
+    let margin = fit.ambiguity_margin.unwrap();
+    assert!(
+        margin > 1e-3 && margin > best_loss,
+        "ambiguity margin {margin}, best loss {best_loss}"
+    );
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::{board_model, diamond_pose, edge_detector, scan, target_pose};
+use hollow_board_config::{BoardModel, BoardShape, TargetGeometry};
+use hollow_board_detector::{
+    config::{BoardRefineMethod, EdgeCompensation},
+    edge::{edge_point_weights, find_edge_points, shrink_edge_points},
+    sensor::SensorProfile,
+};
+use measurements::Length;
+use nalgebra as na;
+
+#[test]
+fn find_edge_points_on_board_boundary() {
+    let truth = board_model(diamond_pose(na::Point3::new(5.0, 0.1, 0.2)));
+    let points = scan(&truth);
+    let params = SensorProfile::Vlp32c.params();
+
+    let edges = find_edge_points(&points, &params);
+    assert!(!edges.is_empty());
+    assert!(edges.len() < points.len() / 2);
+
+    // edge points are within two firings of the border or a hole edge
+    let tolerance = 5.0 * (2.0 * params.horizontal_resolution).to_radians();
+    let inverse_pose = truth.pose.inverse();
+    for edge in &edges {
+        let local = (inverse_pose * points[edge.index]).xy();
+        let distance = (local - truth.board_shape.closest_boundary_point(&local)).norm();
+        assert!(
+            distance < tolerance,
+            "edge point {distance} m off the boundary"
+        );
+    }
+
+    let weights = edge_point_weights(&points, &params, 0.3);
+    let num_down_weighted = weights.iter().filter(|&&weight| weight < 1.0).count();
+    assert_eq!(num_down_weighted, edges.len());
+}
+
+#[test]
+fn shrink_edge_points_into_board() {
+    let center = na::Point3::new(5.0, 0.1, 0.2);
+    let truth = board_model(diamond_pose(center));
+    let points = blurred_scan(&truth);
+    let params = SensorProfile::Vlp32c.params();
+
+    let shrunk = shrink_edge_points(&points, &params);
+    let edges = find_edge_points(&points, &params);
+    let half_footprint = params.beam_footprint(center.coords.norm()) / 2.0;
+
+    for (index, (point, shrunk_point)) in points.iter().zip(&shrunk).enumerate() {
+        let shift = (shrunk_point - point).norm();
+        if edges.iter().any(|edge| edge.index == index) {
+            assert!(shift <= half_footprint * 1.01);
+        } else {
+            assert_eq!(shift, 0.0);
+        }
+    }
+
+    let num_outside = |points: &[na::Point3<f64>]| {
+        let inverse_pose = truth.pose.inverse();
+        points
+            .iter()
+            .filter(|point| {
+                let local = (inverse_pose * *point).xy();
+                (local - truth.board_shape.corresponding_point(&local)).norm() > 1e-3
+            })
+            .count()
+    };
+    let (before, after) = (num_outside(&points), num_outside(&shrunk));
+    assert!(
+        after < before,
+        "{after} points outside after shrinking, {before} before"
+    );
+}
+
+#[test]
+fn detect_with_edge_compensation() {
+    let center = na::Point3::new(5.0, -0.2, 0.1);
+    let truth = board_model(diamond_pose(center));
+    let points = blurred_scan(&truth);
+
+    for edge_compensation in [EdgeCompensation::DownWeight, EdgeCompensation::Shrink] {
+        for board_refine_method in [BoardRefineMethod::Icp, BoardRefineMethod::Lm] {
+            let detection = edge_detector(edge_compensation, board_refine_method)
+                .detect(&points)
+                .unwrap();
+
+            let error = (detection.board_model.board_center() - center).norm();
+            assert!(
+                error < 0.02,
+                "{edge_compensation:?} with {board_refine_method:?}: center error {error}"
+            );
+            let normal_error = detection
+                .board_model
+                .board_z_axis()
+                .dot(&truth.board_z_axis())
+                .abs()
+                .min(1.0)
+                .acos();
+            assert!(normal_error < 0.02);
+        }
+    }
+}
+
+/// Scans a board whose border is pushed out and whose holes are shrunk
+/// by half the beam footprint, like the mixed pixels at its edges.
+fn blurred_scan(truth: &BoardModel) -> Vec<na::Point3<f64>> {
+    let params = SensorProfile::Vlp32c.params();
+    let half_footprint = params.beam_footprint(truth.board_center().coords.norm()) / 2.0;
+    let BoardShape {
+        board_width,
+        hole_radius,
+        hole_center_shift,
+    } = truth.board_shape.clone();
+
+    let board_shape = BoardShape {
+        board_width: Length::from_meters(board_width.as_meters() + 2.0 * half_footprint),
+        hole_radius: Length::from_meters(hole_radius.as_meters() - half_footprint),
+        hole_center_shift,
+    };
+    let blurred = BoardModel {
+        pose: target_pose(&board_shape, truth.board_center()),
+        board_shape,
+        marker_paper_size: truth.marker_paper_size,
+    };
+    scan(&blurred)
+}
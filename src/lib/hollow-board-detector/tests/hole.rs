@@ -0,0 +1,40 @@
+mod common;
+
+use common::{board_model, diamond_pose, scan};
+use hollow_board_detector::{
+    hole::{find_hole_boundary_points, fit_hole_circle, refine_pose_with_holes},
+    sensor::SensorProfile,
+};
+use nalgebra as na;
+
+#[test]
+fn fit_hole_circles() {
+    let true_pose = diamond_pose(na::Point3::new(5.0, 0.1, 0.2));
+    let truth = board_model(true_pose);
+    let points = scan(&truth);
+
+    // a slightly misplaced model
+    let model = board_model(
+        true_pose * na::Isometry3::new(na::Vector3::new(0.02, -0.01, 0.0), na::Vector3::z() * 0.02),
+    );
+    let params = SensorProfile::Vlp32c.params();
+
+    let fits: Vec<_> = find_hole_boundary_points(&model, &params, &points)
+        .into_iter()
+        .filter_map(|(hole, points)| fit_hole_circle(&model, hole, points))
+        .collect();
+    assert_eq!(fits.len(), 3);
+
+    for fit in &fits {
+        let error = (fit.center - truth.hole_center(fit.hole).unwrap()).norm();
+        assert!(error < 0.01, "hole {} center error {}", fit.hole, error);
+        assert!(fit.rms_residual < 0.02);
+        assert!(fit.center_offset > 0.0);
+    }
+
+    let fits: Vec<_> = fits.iter().collect();
+    let pose = refine_pose_with_holes(&model, &fits).unwrap();
+    let error = pose.inverse() * truth.pose;
+    assert!(error.translation.vector.norm() < 0.01);
+    assert!(error.rotation.angle() < 0.01);
+}
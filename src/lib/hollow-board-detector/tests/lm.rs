@@ -0,0 +1,95 @@
+mod common;
+
+use common::board_model;
+use hollow_board_config::{BoardModel, TargetGeometry};
+use hollow_board_detector::lm::LmRefiner;
+use nalgebra as na;
+
+/// Samples points on the board material in a 2cm grid.
+fn board_points(model: &BoardModel) -> Vec<na::Point3<f64>> {
+    let holes = model.board_shape.holes();
+
+    (0..50)
+        .flat_map(|xi| (0..50).map(move |yi| (xi, yi)))
+        .map(|(xi, yi)| na::Point2::new(0.01 + xi as f64 * 0.02, 0.01 + yi as f64 * 0.02))
+        .filter(|point| {
+            holes
+                .iter()
+                .all(|hole| (point - hole.center).norm() > hole.radius)
+        })
+        .map(|point| model.pose * na::Point3::new(point.x, point.y, 0.0))
+        .collect()
+}
+
+#[test]
+fn lm_recovers_perturbed_pose() {
+    let truth = na::Isometry3::new(
+        na::Vector3::new(5.0, -0.5, 0.2),
+        na::Vector3::new(0.1, -1.4, 0.3),
+    );
+    let points = board_points(&board_model(truth));
+
+    let init = {
+        let delta = na::Isometry3::new(
+            na::Vector3::new(0.03, -0.02, 0.01),
+            na::Vector3::new(0.02, 0.01, -0.04),
+        );
+        board_model(truth * delta)
+    };
+
+    let solution = LmRefiner::new(50, 0.015, 0.1)
+        .refine(&init, &points)
+        .expect("unable to refine the pose");
+
+    let error = solution.pose.inverse() * truth;
+    // the error is bounded by the 2cm sampling resolution
+    assert!(error.translation.vector.norm() < 5e-3);
+    assert!(error.rotation.angle() < 5e-3);
+    assert!(solution.converged);
+    assert!(solution.iterations < 30);
+    assert!(*solution.mean_losses.last().unwrap() < 5e-3);
+    assert!(solution.costs.first().unwrap() > solution.costs.last().unwrap());
+}
+
+#[test]
+fn lm_reports_pose_covariance() {
+    let truth = na::Isometry3::new(
+        na::Vector3::new(5.0, -0.5, 0.2),
+        na::Vector3::new(0.1, -1.4, 0.3),
+    );
+
+    // add deterministic noise along the board normal and scatter some
+    // points beyond the border as if blurred by the beam divergence
+    let normal = truth * na::Vector3::z();
+    let mut points: Vec<_> = board_points(&board_model(truth))
+        .into_iter()
+        .enumerate()
+        .map(|(idx, point)| point + normal * (0.005 * (idx as f64 * 1.3).sin()))
+        .collect();
+    points.extend((0..50).map(|idx| truth * na::Point3::new(-0.005, 0.02 * idx as f64, 0.0)));
+    points.extend((0..50).map(|idx| truth * na::Point3::new(0.02 * idx as f64, 1.005, 0.0)));
+
+    let solution = LmRefiner::new(50, 0.015, 0.1)
+        .refine(&board_model(truth), &points)
+        .unwrap();
+
+    let covariance = solution.pose_covariance.expect("pose is not constrained");
+    assert!(covariance.diagonal().iter().all(|&var| var > 0.0));
+    assert!(covariance.diagonal().iter().all(|&var| var < 1e-3));
+}
+
+#[test]
+fn lm_covariance_scales_with_range_noise() {
+    let truth = na::Isometry3::new(
+        na::Vector3::new(5.0, -0.5, 0.2),
+        na::Vector3::new(0.1, -1.4, 0.3),
+    );
+    let model = board_model(truth);
+    let points = board_points(&model);
+
+    // the residuals are far below the range noise on noise-free points
+    let refiner = LmRefiner::new(50, 0.015, 0.1);
+    let covariance = refiner.pose_covariance(&model, &points, 0.02).unwrap();
+    let doubled = refiner.pose_covariance(&model, &points, 0.04).unwrap();
+    assert!((doubled - covariance * 4.0).norm() <= 1e-9 * doubled.norm());
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use common::{board_model, detector, diamond_pose, scan};
+use hollow_board_detector::{tracker::TrackSource, BoardTracker, DetectionFailure};
+use nalgebra as na;
+
+/// Samples a grid of points on the ground below the board.
+fn ground() -> Vec<na::Point3<f64>> {
+    (0..=80)
+        .flat_map(|ix| {
+            (0..=80)
+                .map(move |iy| na::Point3::new(2.0 + ix as f64 * 0.1, -4.0 + iy as f64 * 0.1, -1.5))
+        })
+        .collect()
+}
+
+/// Samples a grid of points on a wall larger than the board.
+fn wall() -> Vec<na::Point3<f64>> {
+    (0..=40)
+        .flat_map(|iy| {
+            (0..=40).map(move |iz| {
+                na::Point3::new(9.0, -3.0 + iy as f64 * 0.05, -1.0 + iz as f64 * 0.05)
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn propose_board_above_ground() {
+    let center = na::Point3::new(5.0, 0.3, 0.2);
+    let board_points = scan(&board_model(diamond_pose(center)));
+    let points: Vec<_> = board_points
+        .iter()
+        .copied()
+        .chain(ground())
+        .chain(wall())
+        .collect();
+
+    let proposals = detector().propose(&points);
+    assert!(proposals.num_clusters >= 2);
+    assert_eq!(proposals.proposals.len(), 1);
+
+    let proposal = &proposals.proposals[0];
+    assert!(proposal.score > 0.0 && proposal.score <= 1.0);
+    assert!(proposal.indices.iter().all(|&idx| idx < board_points.len()));
+    assert!(proposal.indices.len() * 10 >= board_points.len() * 9);
+}
+
+#[test]
+fn detect_proposed_board() {
+    let center = na::Point3::new(5.0, -0.4, 0.1);
+    let points: Vec<_> = scan(&board_model(diamond_pose(center)))
+        .into_iter()
+        .chain(ground())
+        .chain(wall())
+        .collect();
+
+    let detections = detector().detect_proposed(&points).unwrap();
+    assert_eq!(detections.len(), 1);
+
+    let center_error = (detections[0].board_model.board_center() - center).norm();
+    assert!(center_error < 0.05, "center error {center_error}");
+}
+
+#[test]
+fn track_proposed_board() {
+    let mut tracker = BoardTracker::new(detector());
+    let mut center = na::Point3::new(5.0, -0.4, 0.1);
+
+    for source in [TrackSource::Detected, TrackSource::Tracked] {
+        let points: Vec<_> = scan(&board_model(diamond_pose(center)))
+            .into_iter()
+            .chain(ground())
+            .chain(wall())
+            .collect();
+
+        let boards = tracker.track_proposed(&points).unwrap();
+        assert_eq!(boards.len(), 1);
+        assert_eq!(boards[0].source, source);
+
+        let center_error = (boards[0].detection.board_model.board_center() - center).norm();
+        assert!(center_error < 0.05, "center error {center_error}");
+
+        center += na::Vector3::new(0.0, 0.03, 0.01);
+    }
+}
+
+#[test]
+fn report_no_candidate_failure() {
+    let points: Vec<_> = ground().into_iter().chain(wall()).collect();
+
+    let failure = detector().detect_proposed(&points).unwrap_err();
+    assert!(matches!(
+        failure,
+        DetectionFailure::NoCandidate {
+            num_clusters: 1,
+            ..
+        }
+    ));
+    assert!(!failure.is_board_fitting_failure());
+}
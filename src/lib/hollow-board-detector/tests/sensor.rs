@@ -0,0 +1,34 @@
+use hollow_board_detector::{sensor::SensorProfile, Config};
+
+const CONFIG: &str = include_str!("../examples/board_detector.json5");
+
+#[test]
+fn default_profile_params() {
+    let config: Config = json5::from_str(CONFIG).unwrap();
+    assert_eq!(config.sensor_profile, SensorProfile::Vlp32c);
+
+    let params = config.sensor_params();
+    assert_eq!(params.good_fit_threshold, 0.015);
+    assert_eq!(params.outlier_threshold, 0.1);
+}
+
+#[test]
+fn profile_with_overrides() {
+    let text = CONFIG
+        .replace(
+            r#""sensor_profile": "vlp32c""#,
+            r#""sensor_profile": "os1""#,
+        )
+        .replace(
+            r#""sensor_overrides": {}"#,
+            r#""sensor_overrides": {"outlier_threshold": 0.3}"#,
+        );
+    let config: Config = json5::from_str(&text).unwrap();
+    assert_eq!(config.sensor_profile, SensorProfile::Os1);
+
+    let params = config.sensor_params();
+    let preset = SensorProfile::Os1.params();
+    assert_eq!(params.outlier_threshold, 0.3);
+    assert_eq!(params.good_fit_threshold, preset.good_fit_threshold);
+    assert_eq!(params.vertical_beam_spacing, preset.vertical_beam_spacing);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{scan, target_detector, target_pose};
+use hollow_board_config::{
+    BoardModel, HoleShape, HoledRectangle, Rectangle, TargetGeometry, TargetShape,
+};
+use measurements::Length;
+use nalgebra as na;
+
+fn target_model<S>(target: S, center: na::Point3<f64>) -> BoardModel<S>
+where
+    S: TargetGeometry,
+{
+    BoardModel {
+        pose: target_pose(&target, center),
+        marker_paper_size: Length::from_millimeters(500.0),
+        board_shape: target,
+    }
+}
+
+/// Checks that the detected target is at the true place. The corners
+/// are compared regardless of order because the target border is
+/// symmetric.
+fn assert_same_place<S>(detected: &BoardModel<S>, truth: &BoardModel<S>)
+where
+    S: TargetGeometry,
+{
+    let center_error = (detected.board_center() - truth.board_center()).norm();
+    assert!(center_error < 0.05, "center error {center_error}");
+
+    for corner in truth.corner_points() {
+        let found = detected
+            .corner_points()
+            .iter()
+            .any(|point| (point - corner).norm() < 0.05);
+        assert!(found, "corner {corner} is not matched");
+    }
+}
+
+#[test]
+fn detect_rectangle() {
+    let target = Rectangle {
+        width: Length::from_millimeters(1200.0),
+        height: Length::from_millimeters(800.0),
+    };
+    let truth = target_model(target.clone(), na::Point3::new(5.0, 0.3, 0.1));
+    let points = scan(&truth);
+
+    let detection = target_detector(target).detect(&points).unwrap();
+    assert_same_place(&detection.board_model, &truth);
+    assert!(detection.hole_fits.is_empty());
+}
+
+#[test]
+fn detect_four_hole_board() {
+    let hole = |x: f64, y: f64| HoleShape {
+        x: Length::from_millimeters(x),
+        y: Length::from_millimeters(y),
+        radius: Length::from_millimeters(120.0),
+    };
+    let target = TargetShape::HoledRectangle(HoledRectangle {
+        width: Length::from_millimeters(1000.0),
+        height: Length::from_millimeters(1000.0),
+        holes: vec![
+            hole(250.0, 250.0),
+            hole(750.0, 250.0),
+            hole(750.0, 750.0),
+            hole(250.0, 750.0),
+        ],
+    });
+    let truth = target_model(target.clone(), na::Point3::new(5.0, -0.2, 0.2));
+    let points = scan(&truth);
+
+    let detection = target_detector(target).detect(&points).unwrap();
+    assert_same_place(&detection.board_model, &truth);
+    assert!(detection.hole_fits.iter().all(|fit| fit.hole < 4));
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::{board_model, detector, diamond_pose, scan};
+use hollow_board_detector::{DetectionFailure, DetectionTrace};
+use nalgebra as na;
+
+#[test]
+fn trace_detection_steps() {
+    let center = na::Point3::new(5.0, 0.2, 0.1);
+    let points = scan(&board_model(diamond_pose(center)));
+    let trace = detector().detect_traced(&points);
+
+    assert_eq!(trace.points, points);
+    let detection = trace.result.as_ref().unwrap();
+    let last_attempt = trace.attempts.last().unwrap();
+    assert!(last_attempt.failure.is_none());
+    assert_eq!(
+        last_attempt.plane.inlier_points,
+        detection.plane_ransac_data.inlier_points
+    );
+
+    // the history starts from the initial guess and ends at the fitted
+    // pose, which is at the true place
+    let icp_data = &detection.icp_data;
+    let history = icp_data.board_model_history();
+    assert!(history.len() >= 2);
+    let first_center = history.first().unwrap().board_center();
+    let final_center = history.last().unwrap().board_center();
+    assert!((final_center - center).norm() < 0.05);
+    assert!((first_center - final_center).norm() > 1e-6);
+    assert!((final_center - icp_data.board_model.board_center()).norm() < 1e-9);
+}
+
+#[test]
+fn restore_trace_from_json() {
+    let truth = board_model(diamond_pose(na::Point3::new(5.0, -0.3, 0.2)));
+    let trace = detector().detect_traced(&scan(&truth));
+
+    let mut json = vec![];
+    trace.write_json(&mut json).unwrap();
+    let restored: DetectionTrace = DetectionTrace::read_json(json.as_slice()).unwrap();
+
+    assert_eq!(restored.points, trace.points);
+    assert_eq!(restored.attempts.len(), trace.attempts.len());
+    let (lhs, rhs) = (restored.result.unwrap(), trace.result.unwrap());
+    let center_error = (lhs.board_model.board_center() - rhs.board_model.board_center()).norm();
+    // the pose is stored as Euler angles, which loses some precision
+    assert!(center_error < 1e-6, "center error {center_error}");
+    assert_eq!(
+        lhs.icp_data.pose_history.len(),
+        rhs.icp_data.pose_history.len()
+    );
+}
+
+#[test]
+fn restore_failed_trace_from_msgpack() {
+    // a wall far larger than the board is rejected
+    let points: Vec<_> = (0..=80)
+        .flat_map(|iy| {
+            (0..=40).map(move |iz| {
+                na::Point3::new(6.0, -2.0 + iy as f64 * 0.05, -1.0 + iz as f64 * 0.05)
+            })
+        })
+        .collect();
+    let trace = detector().detect_traced(&points);
+    let failure = trace.result.as_ref().unwrap_err();
+    assert!(matches!(failure, DetectionFailure::PlaneMismatch { .. }));
+    assert!(!trace.attempts.is_empty());
+    assert_eq!(trace.attempts[0].failure.as_ref(), Some(failure));
+
+    let mut bytes = vec![];
+    trace.write_msgpack(&mut bytes).unwrap();
+    let restored: DetectionTrace = DetectionTrace::read_msgpack(bytes.as_slice()).unwrap();
+
+    assert_eq!(restored.points, trace.points);
+    assert_eq!(restored.attempts.len(), trace.attempts.len());
+    for (lhs, rhs) in restored.attempts.iter().zip(&trace.attempts) {
+        assert_eq!(lhs.failure, rhs.failure);
+        assert_eq!(lhs.plane.inlier_points, rhs.plane.inlier_points);
+        assert_eq!(lhs.plane.quality.num_inliers, rhs.plane.quality.num_inliers);
+    }
+    assert_eq!(restored.result.unwrap_err(), *failure);
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use common::{board_model, config, detector, diamond_pose, pattern, scan};
+use hollow_board_detector::{tracker::TrackSource, BoardTracker, Config, Detector};
+use nalgebra as na;
+
+#[test]
+fn track_moving_board() {
+    let mut tracker = BoardTracker::new(detector());
+    let mut center = na::Point3::new(5.0, 0.5, 0.2);
+
+    let boards = tracker
+        .track(&scan(&board_model(diamond_pose(center))))
+        .unwrap();
+    assert_eq!(boards.len(), 1);
+    assert_eq!(boards[0].source, TrackSource::Detected);
+
+    // move the board slowly
+    for _ in 0..3 {
+        center += na::Vector3::new(0.0, 0.03, 0.01);
+        let boards = tracker
+            .track(&scan(&board_model(diamond_pose(center))))
+            .unwrap();
+        assert_eq!(boards.len(), 1);
+
+        let board = &boards[0];
+        assert_eq!(board.source, TrackSource::Tracked);
+        assert!((board.detection.board_model.board_center() - center).norm() < 0.05);
+        assert!(board.residual < 0.01);
+
+        // the smoothed pose lags behind the detected pose
+        let lag = board.smoothed_pose.translation.vector
+            - board.detection.board_model.pose.translation.vector;
+        assert!(lag.norm() > 1e-4 && lag.norm() < 0.05);
+    }
+}
+
+#[test]
+fn redetect_after_jump() {
+    let mut tracker = BoardTracker::new(detector());
+    let first = na::Point3::new(5.0, 0.5, 0.2);
+    let second = na::Point3::new(6.0, -1.2, 0.0);
+
+    tracker
+        .track(&scan(&board_model(diamond_pose(first))))
+        .unwrap();
+    let boards = tracker
+        .track(&scan(&board_model(diamond_pose(second))))
+        .unwrap();
+    assert_eq!(boards.len(), 1);
+
+    let board = &boards[0];
+    assert_eq!(board.source, TrackSource::Detected);
+    assert!((board.detection.board_model.board_center() - second).norm() < 0.05);
+    assert_eq!(board.smoothed_pose, board.detection.board_model.pose);
+}
+
+#[test]
+fn match_each_track_once() {
+    // the tracking limits are wide enough that either board could
+    // continue either track
+    let config = Config {
+        track_max_translation: 3.0,
+        ..config()
+    };
+    let mut tracker = BoardTracker::new(Detector::new(config, pattern()));
+    let mut centers = [
+        na::Point3::new(5.0, 1.2, 0.3),
+        na::Point3::new(6.0, -1.2, 0.0),
+    ];
+    let scan_boards = |centers: &[na::Point3<f64>]| -> Vec<na::Point3<f64>> {
+        centers
+            .iter()
+            .flat_map(|center| scan(&board_model(diamond_pose(*center))))
+            .collect()
+    };
+
+    let boards = tracker.track(&scan_boards(&centers)).unwrap();
+    assert_eq!(boards.len(), 2);
+
+    for center in &mut centers {
+        *center += na::Vector3::new(0.0, 0.03, 0.01);
+    }
+    let boards = tracker.track(&scan_boards(&centers)).unwrap();
+    assert_eq!(boards.len(), 2);
+
+    // each board is smoothed with its own track, not with the other board
+    for board in &boards {
+        assert_eq!(board.source, TrackSource::Tracked);
+        let lag = board.smoothed_pose.translation.vector
+            - board.detection.board_model.pose.translation.vector;
+        assert!(lag.norm() < 0.05, "smoothed pose lags {}", lag.norm());
+    }
+}
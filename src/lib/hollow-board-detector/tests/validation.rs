@@ -0,0 +1,19 @@
+mod common;
+
+use aruco_config::MultiArucoPattern;
+use common::{board_model, diamond_pose};
+use hollow_board_detector::Config;
+use nalgebra as na;
+
+const CONFIG: &str = include_str!("../examples/board_detector.json5");
+const ARUCO_PATTERN: &str = include_str!("../examples/aruco_pattern.json5");
+
+#[test]
+fn example_configs_are_valid() {
+    let config: Config = json5::from_str(CONFIG).unwrap();
+    let aruco_pattern: MultiArucoPattern = json5::from_str(ARUCO_PATTERN).unwrap();
+    config.validate(&aruco_pattern).unwrap();
+
+    let model = board_model(diamond_pose(na::Point3::new(5.0, 0.0, 0.0)));
+    model.validate(&aruco_pattern).unwrap();
+}
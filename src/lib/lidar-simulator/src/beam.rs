@@ -0,0 +1,84 @@
+//! Beam models of spinning LiDARs.
+
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+const EPS_ANGLE: f64 = 1e-6;
+
+/// The beam layout and measurement errors of a spinning LiDAR.
+///
+/// The sensor looks along the x axis with the z axis up. The azimuth is
+/// measured counterclockwise from the x axis, and the elevation is
+/// measured from the xy-plane. Angles are in degrees.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BeamModel {
+    /// The elevation of each ring, indexed by the ring number.
+    pub ring_elevations: Vec<f64>,
+    /// The angle between adjacent firings.
+    pub azimuth_resolution: f64,
+    /// The start and the end azimuth of the scan.
+    pub azimuth_range: [f64; 2],
+    /// The maximum range in meters.
+    pub max_range: f64,
+    /// The standard deviation of range measurements in meters.
+    pub range_noise: f64,
+    /// The probability that a return is lost, in `[0, 1]`.
+    pub dropout: f64,
+}
+
+impl BeamModel {
+    /// Velodyne VLP-32C, also known as the Ultra Puck.
+    pub fn vlp32c() -> Self {
+        Self {
+            ring_elevations: vec![
+                -25.0, -15.639, -11.31, -8.843, -7.254, -6.148, -5.333, -4.667, -4.0, -3.667,
+                -3.333, -3.0, -2.667, -2.333, -2.0, -1.667, -1.333, -1.0, -0.667, -0.333, 0.0,
+                0.333, 0.667, 1.0, 1.333, 1.667, 2.333, 3.333, 4.667, 7.0, 10.333, 15.0,
+            ],
+            azimuth_resolution: 0.2,
+            azimuth_range: [-180.0, 179.8],
+            max_range: 200.0,
+            range_noise: 0.03,
+            dropout: 0.0,
+        }
+    }
+
+    /// Velodyne Puck Hi-Res, the VLP-16 with 20 degree vertical FoV.
+    pub fn puck_hi_res() -> Self {
+        Self {
+            ring_elevations: (0..16)
+                .map(|ring| -10.0 + ring as f64 * 20.0 / 15.0)
+                .collect(),
+            azimuth_resolution: 0.2,
+            azimuth_range: [-180.0, 179.8],
+            max_range: 100.0,
+            range_noise: 0.03,
+            dropout: 0.0,
+        }
+    }
+
+    /// Lists the ring number and the unit direction of each beam in a
+    /// scan.
+    pub fn rays(&self) -> impl Iterator<Item = (usize, na::UnitVector3<f64>)> + '_ {
+        let [start, end] = self.azimuth_range;
+        // the tolerance keeps the end azimuth despite rounding errors
+        let num_firings =
+            ((end - start) / self.azimuth_resolution + EPS_ANGLE).floor() as usize + 1;
+
+        (0..num_firings).flat_map(move |firing| {
+            let azimuth = (start + firing as f64 * self.azimuth_resolution).to_radians();
+            self.ring_elevations
+                .iter()
+                .enumerate()
+                .map(move |(ring, elevation)| {
+                    let elevation = elevation.to_radians();
+                    let dir = na::Vector3::new(
+                        elevation.cos() * azimuth.cos(),
+                        elevation.cos() * azimuth.sin(),
+                        elevation.sin(),
+                    );
+                    (ring, na::Unit::new_unchecked(dir))
+                })
+        })
+    }
+}
@@ -0,0 +1,99 @@
+//! Synthetic LiDAR scans of calibration targets.
+//!
+//! The simulator casts the beams of a [BeamModel] from the sensor
+//! origin into a [Scene] and reports the closest hits with ring numbers
+//! and intensities. The scene poses are the ground truth of the scan.
+
+pub mod beam;
+pub mod scene;
+
+pub use crate::{
+    beam::BeamModel,
+    scene::{Reflectivity, Scene},
+};
+
+use hollow_board_config::TargetGeometry;
+use nalgebra as na;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::Normal;
+
+/// A simulated point cloud. The fields are indexed by point.
+#[derive(Debug, Clone, Default)]
+pub struct Scan {
+    pub points: Vec<na::Point3<f64>>,
+    pub rings: Vec<usize>,
+    pub intensities: Vec<f64>,
+}
+
+impl Scan {
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+}
+
+/// Scans scenes with a beam model.
+///
+/// Range noise and dropout are drawn from a seeded random generator,
+/// so the same seed reproduces the same scans.
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    beam_model: BeamModel,
+    rng: ChaCha8Rng,
+}
+
+impl Simulator {
+    pub fn new(beam_model: BeamModel, seed: u64) -> Self {
+        Self {
+            beam_model,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn beam_model(&self) -> &BeamModel {
+        &self.beam_model
+    }
+
+    /// Casts every beam into the scene and keeps the hits within the
+    /// maximum range.
+    pub fn scan<S>(&mut self, scene: &Scene<S>) -> Scan
+    where
+        S: TargetGeometry,
+    {
+        let BeamModel {
+            max_range,
+            range_noise,
+            dropout,
+            ..
+        } = self.beam_model;
+        let noise = Normal::new(0.0, range_noise)
+            .ok()
+            .filter(|_| range_noise > 0.0);
+        let surfaces = scene.surfaces();
+        let mut scan = Scan::default();
+
+        for (ring, dir) in self.beam_model.rays() {
+            let hit = match surfaces.cast(&dir) {
+                Some(hit) if hit.range <= max_range => hit,
+                _ => continue,
+            };
+            if dropout > 0.0 && self.rng.gen_bool(dropout.min(1.0)) {
+                continue;
+            }
+
+            let range = match &noise {
+                Some(noise) => hit.range + self.rng.sample(noise),
+                None => hit.range,
+            };
+            scan.points.push(na::Point3::from(dir.scale(range)));
+            scan.rings.push(ring);
+            scan.intensities.push(hit.intensity);
+        }
+
+        scan
+    }
+}
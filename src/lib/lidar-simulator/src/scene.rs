@@ -0,0 +1,149 @@
+//! Scenes of calibration targets on the ground.
+
+use hollow_board_config::{BoardModel, BoardShape, TargetCircle, TargetGeometry};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+/// The surface reflectivity that determines point intensities.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reflectivity {
+    /// The bare board material.
+    pub board: f64,
+    /// The marker paper at the origin corner of each board.
+    pub marker_paper: f64,
+    pub ground: f64,
+}
+
+impl Default for Reflectivity {
+    fn default() -> Self {
+        Self {
+            board: 20.0,
+            marker_paper: 80.0,
+            ground: 10.0,
+        }
+    }
+}
+
+/// Calibration targets and an optional ground plane in the sensor
+/// frame.
+#[derive(Debug, Clone)]
+pub struct Scene<S = BoardShape> {
+    pub boards: Vec<BoardModel<S>>,
+    /// The z coordinate of the horizontal ground plane, or `None`
+    /// without a ground.
+    pub ground_height: Option<f64>,
+    pub reflectivity: Reflectivity,
+}
+
+/// The closest surface hit by a ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// The distance from the sensor in meters.
+    pub range: f64,
+    pub intensity: f64,
+}
+
+impl<S> Scene<S>
+where
+    S: TargetGeometry,
+{
+    /// Creates a scene of the boards without a ground.
+    pub fn new(boards: Vec<BoardModel<S>>) -> Self {
+        Self {
+            boards,
+            ground_height: None,
+            reflectivity: Reflectivity::default(),
+        }
+    }
+
+    /// Prepares the scene for casting rays.
+    pub(crate) fn surfaces(&self) -> Surfaces<'_, S> {
+        let boards = self
+            .boards
+            .iter()
+            .map(|board| BoardSurface {
+                inverse_pose: board.pose.inverse(),
+                holes: board.board_shape.holes(),
+                board,
+            })
+            .collect();
+        Surfaces {
+            scene: self,
+            boards,
+        }
+    }
+}
+
+/// A scene with the board holes computed once for all rays.
+pub(crate) struct Surfaces<'a, S> {
+    scene: &'a Scene<S>,
+    boards: Vec<BoardSurface<'a, S>>,
+}
+
+struct BoardSurface<'a, S> {
+    board: &'a BoardModel<S>,
+    inverse_pose: na::Isometry3<f64>,
+    holes: Vec<TargetCircle>,
+}
+
+impl<S> Surfaces<'_, S>
+where
+    S: TargetGeometry,
+{
+    /// Finds the closest surface hit by the ray from the sensor origin.
+    pub fn cast(&self, dir: &na::UnitVector3<f64>) -> Option<Hit> {
+        let Reflectivity {
+            board: board_reflectivity,
+            marker_paper,
+            ground,
+        } = self.scene.reflectivity;
+
+        let ground_hit = self.scene.ground_height.and_then(|height| {
+            let range = height / dir.z;
+            (range.is_finite() && range > 0.0).then_some(Hit {
+                range,
+                intensity: ground,
+            })
+        });
+
+        let board_hits = self.boards.iter().filter_map(|surface| {
+            let BoardSurface {
+                board,
+                inverse_pose,
+                holes,
+            } = surface;
+            let normal = board.board_z_axis();
+            let origin = board.pose.translation.vector;
+            let range = origin.dot(&normal) / dir.dot(&normal);
+            if !range.is_finite() || range <= 0.0 {
+                return None;
+            }
+
+            let local = (inverse_pose * na::Point3::from(dir.scale(range))).xy();
+            let shape = &board.board_shape;
+            let on_board = (0.0..=shape.width()).contains(&local.x)
+                && (0.0..=shape.height()).contains(&local.y);
+            let in_hole = holes
+                .iter()
+                .any(|hole| (local - hole.center).norm() < hole.radius);
+            if !on_board || in_hole {
+                return None;
+            }
+
+            let paper_size = board.marker_paper_size.as_meters();
+            let on_paper = local.x <= paper_size && local.y <= paper_size;
+            Some(Hit {
+                range,
+                intensity: if on_paper {
+                    marker_paper
+                } else {
+                    board_reflectivity
+                },
+            })
+        });
+
+        board_hits
+            .chain(ground_hit)
+            .min_by(|lhs, rhs| lhs.range.total_cmp(&rhs.range))
+    }
+}
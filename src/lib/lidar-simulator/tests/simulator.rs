@@ -0,0 +1,113 @@
+use hollow_board_config::{BoardModel, BoardShape, TargetGeometry};
+use lidar_simulator::{BeamModel, Reflectivity, Scene, Simulator};
+use measurements::Length;
+use nalgebra as na;
+use std::f64::consts::FRAC_PI_2;
+
+/// Places the board 5 meters ahead, facing the sensor.
+fn board_model() -> BoardModel {
+    let rotation = na::UnitQuaternion::from_euler_angles(0.0, -FRAC_PI_2, 0.0);
+    let translation = na::Translation3::new(5.0, -0.5, 0.5);
+    BoardModel {
+        pose: na::Isometry3::from_parts(translation, rotation),
+        marker_paper_size: Length::from_millimeters(500.0),
+        board_shape: BoardShape {
+            board_width: Length::from_millimeters(1000.0),
+            hole_radius: Length::from_millimeters(150.0),
+            hole_center_shift: Length::from_millimeters(200.0),
+        },
+    }
+}
+
+fn noiseless(beam_model: BeamModel) -> BeamModel {
+    BeamModel {
+        range_noise: 0.0,
+        ..beam_model
+    }
+}
+
+#[test]
+fn presets_have_sensor_rings() {
+    let vlp32c = BeamModel::vlp32c();
+    assert_eq!(vlp32c.ring_elevations.len(), 32);
+    assert_eq!(vlp32c.rays().count(), 1800 * 32);
+
+    let puck_hi_res = BeamModel::puck_hi_res();
+    assert_eq!(puck_hi_res.ring_elevations.len(), 16);
+    assert!((puck_hi_res.ring_elevations[15] - 10.0).abs() < 1e-9);
+}
+
+#[test]
+fn scan_board_surface() {
+    let truth = board_model();
+    let mut simulator = Simulator::new(noiseless(BeamModel::vlp32c()), 1);
+    let scan = simulator.scan(&Scene::new(vec![truth.clone()]));
+    assert!(!scan.is_empty());
+    assert_eq!(scan.rings.len(), scan.len());
+    assert_eq!(scan.intensities.len(), scan.len());
+
+    let shape = &truth.board_shape;
+    let holes = shape.holes();
+    for point in &scan.points {
+        let local = truth.pose.inverse() * point;
+        assert!(local.z.abs() < 1e-9);
+        assert!((0.0..=shape.width()).contains(&local.x));
+        assert!((0.0..=shape.height()).contains(&local.y));
+        assert!(holes
+            .iter()
+            .all(|hole| (local.xy() - hole.center).norm() >= hole.radius));
+    }
+
+    // the marker paper is brighter than the bare board
+    let reflectivity = Reflectivity::default();
+    assert!(scan
+        .intensities
+        .iter()
+        .any(|&intensity| intensity == reflectivity.marker_paper));
+    assert!(scan
+        .intensities
+        .iter()
+        .any(|&intensity| intensity == reflectivity.board));
+}
+
+#[test]
+fn scan_ground_behind_board() {
+    let scene = Scene {
+        ground_height: Some(-1.5),
+        ..Scene::new(vec![board_model()])
+    };
+    let mut simulator = Simulator::new(noiseless(BeamModel::puck_hi_res()), 1);
+    let scan = simulator.scan(&scene);
+
+    let num_ground = scan
+        .points
+        .iter()
+        .zip(&scan.intensities)
+        .filter(|(point, &intensity)| {
+            intensity == scene.reflectivity.ground && (point.z + 1.5).abs() < 1e-9
+        })
+        .count();
+    assert!(num_ground > 0);
+    assert!(scan.points.iter().all(|point| point.z >= -1.5 - 1e-9));
+}
+
+#[test]
+fn reproduce_noisy_scans() {
+    let scene = Scene::new(vec![board_model()]);
+    let beam_model = BeamModel {
+        dropout: 0.2,
+        ..BeamModel::vlp32c()
+    };
+    let lhs = Simulator::new(beam_model.clone(), 7).scan(&scene);
+    let rhs = Simulator::new(beam_model.clone(), 7).scan(&scene);
+    assert_eq!(lhs.points, rhs.points);
+
+    let full = Simulator::new(noiseless(BeamModel::vlp32c()), 7).scan(&scene);
+    assert!(lhs.len() < full.len());
+
+    let lost = BeamModel {
+        dropout: 1.0,
+        ..beam_model
+    };
+    assert!(Simulator::new(lost, 7).scan(&scene).is_empty());
+}
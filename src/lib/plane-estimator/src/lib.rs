@@ -27,3 +27,12 @@ mod model;
 
 pub use estimator::*;
 mod estimator;
+
+pub use refiner::*;
+mod refiner;
+
+pub use multi_plane::*;
+mod multi_plane;
+
+pub use quality::*;
+mod quality;
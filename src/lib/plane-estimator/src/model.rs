@@ -1,22 +1,170 @@
 use na::coordinates::XYZ;
 use nalgebra as na;
 use sample_consensus::Model;
+use serde::{Deserialize, Serialize};
 use simba::scalar::SubsetOf;
 
-#[derive(Debug, Clone)]
+/// The sine of the angle below which two directions are considered
+/// parallel.
+const EPS_PARALLEL: f64 = 1e-6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlaneModel {
     pub center: na::Point3<f64>,
     pub normal: na::Unit<na::Vector3<f64>>,
 }
 
 impl PlaneModel {
+    /// Computes the local frame pose of the plane. The origin is at
+    /// `center` and the z axis is along `normal`. The x and y axes are
+    /// given by [tangent_basis](Self::tangent_basis).
     pub fn pose(&self) -> na::Isometry3<f64> {
-        let rotation = na::UnitQuaternion::from_axis_angle(&self.normal, 0.0);
         let translation = {
             let XYZ { x, y, z } = *self.center;
             na::Translation3::new(x, y, z)
         };
-        na::Isometry3::from_parts(translation, rotation)
+        na::Isometry3::from_parts(translation, self.rotation())
+    }
+
+    /// Computes the rotation from the plane frame to the world frame.
+    pub fn rotation(&self) -> na::UnitQuaternion<f64> {
+        let (x_axis, y_axis) = self.tangent_basis();
+        let matrix = na::Matrix3::from_columns(&[
+            x_axis.into_inner(),
+            y_axis.into_inner(),
+            self.normal.into_inner(),
+        ]);
+        na::UnitQuaternion::from_rotation_matrix(&na::Rotation3::from_matrix_unchecked(matrix))
+    }
+
+    /// Computes two unit vectors spanning the plane. Together with the
+    /// normal they form a right-handed frame.
+    ///
+    /// The first vector is the projection of the world x axis on the
+    /// plane. The world y axis is used instead if the normal is nearly
+    /// parallel to the x axis.
+    pub fn tangent_basis(&self) -> (na::UnitVector3<f64>, na::UnitVector3<f64>) {
+        let normal = &self.normal;
+        let reference = if normal.x.abs() < 0.9 {
+            na::Vector3::x()
+        } else {
+            na::Vector3::y()
+        };
+        let x_axis = na::Unit::new_normalize(reference - normal.scale(reference.dot(normal)));
+        let y_axis = na::Unit::new_normalize(normal.cross(&x_axis));
+        (x_axis, y_axis)
+    }
+
+    /// Computes the signed distance from the point to the plane. It is
+    /// positive on the side that the normal points to.
+    pub fn signed_distance(&self, point: &na::Point3<f64>) -> f64 {
+        (point - self.center).dot(&self.normal)
+    }
+
+    /// Computes the orthogonal projection of the point on the plane.
+    pub fn project_point(&self, point: &na::Point3<f64>) -> na::Point3<f64> {
+        point - self.normal.scale(self.signed_distance(point))
+    }
+
+    /// Computes the 2D coordinates of the point projected on the plane,
+    /// expressed in the plane frame.
+    pub fn to_plane_coords(&self, point: &na::Point3<f64>) -> na::Point2<f64> {
+        let (x_axis, y_axis) = self.tangent_basis();
+        let vec = point - self.center;
+        na::Point2::new(vec.dot(&x_axis), vec.dot(&y_axis))
+    }
+
+    /// Computes the 3D point from the 2D coordinates in the plane frame.
+    pub fn from_plane_coords(&self, coords: &na::Point2<f64>) -> na::Point3<f64> {
+        let (x_axis, y_axis) = self.tangent_basis();
+        self.center + x_axis.scale(coords.x) + y_axis.scale(coords.y)
+    }
+
+    /// Computes the intersection line of two planes.
+    ///
+    /// It returns `None` if the planes are nearly parallel.
+    pub fn intersect_plane(&self, other: &PlaneModel) -> Option<Line3> {
+        let direction = self.normal.cross(&other.normal);
+        let direction = na::Unit::try_new(direction, EPS_PARALLEL)?;
+
+        // Find the point on both planes nearest to the origin by
+        // solving n1.p = d1, n2.p = d2 and dir.p = 0.
+        let matrix = na::Matrix3::from_rows(&[
+            self.normal.transpose(),
+            other.normal.transpose(),
+            direction.transpose(),
+        ]);
+        let rhs = na::Vector3::new(self.offset(), other.offset(), 0.0);
+        let point = matrix.lu().solve(&rhs)?;
+
+        Some(Line3 {
+            point: point.into(),
+            direction,
+        })
+    }
+
+    /// Computes the common point of three planes.
+    ///
+    /// It returns `None` if any two of the planes are nearly parallel
+    /// or the three planes share a line.
+    pub fn intersect_planes(
+        &self,
+        second: &PlaneModel,
+        third: &PlaneModel,
+    ) -> Option<na::Point3<f64>> {
+        let matrix = na::Matrix3::from_rows(&[
+            self.normal.transpose(),
+            second.normal.transpose(),
+            third.normal.transpose(),
+        ]);
+        if matrix.determinant().abs() < EPS_PARALLEL {
+            return None;
+        }
+        let rhs = na::Vector3::new(self.offset(), second.offset(), third.offset());
+        let point = matrix.lu().solve(&rhs)?;
+        Some(point.into())
+    }
+
+    /// Computes the intersection point of the line and the plane.
+    ///
+    /// It returns `None` if the line is nearly parallel to the plane.
+    pub fn intersect_line(&self, line: &Line3) -> Option<na::Point3<f64>> {
+        let cos = line.direction.dot(&self.normal);
+        if cos.abs() < EPS_PARALLEL {
+            return None;
+        }
+        let dist = -self.signed_distance(&line.point) / cos;
+        Some(line.point_at(dist))
+    }
+
+    /// The plane offset `d` in the plane equation `n.p = d`.
+    pub fn offset(&self) -> f64 {
+        self.normal.dot(&self.center.coords)
+    }
+}
+
+/// A line in 3D space.
+#[derive(Debug, Clone)]
+pub struct Line3 {
+    pub point: na::Point3<f64>,
+    pub direction: na::UnitVector3<f64>,
+}
+
+impl Line3 {
+    /// Computes the point at the signed distance from `point` along
+    /// the line direction.
+    pub fn point_at(&self, distance: f64) -> na::Point3<f64> {
+        self.point + self.direction.scale(distance)
+    }
+
+    /// Computes the orthogonal projection of the point on the line.
+    pub fn project_point(&self, point: &na::Point3<f64>) -> na::Point3<f64> {
+        self.point_at((point - self.point).dot(&self.direction))
+    }
+
+    /// Computes the distance from the point to the line.
+    pub fn distance_to(&self, point: &na::Point3<f64>) -> f64 {
+        (point - self.project_point(point)).norm()
     }
 }
 
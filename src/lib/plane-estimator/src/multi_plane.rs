@@ -0,0 +1,203 @@
+use crate::{
+    estimator::PlaneEstimator,
+    model::PlaneModel,
+    refiner::{PlaneRefiner, RefinedPlane},
+};
+use nalgebra as na;
+use sample_consensus::{Consensus, Estimator};
+
+const MIN_SAMPLES: usize = <PlaneEstimator as Estimator<na::Point3<f64>>>::MIN_SAMPLES;
+
+/// Extracts planes from a point cloud one after another.
+///
+/// Each round runs the consensus algorithm on the points not yet
+/// claimed by previous planes, and removes the inliers of the found
+/// plane. The extraction stops when the found plane has fewer inliers
+/// than the minimum support, or when `max_planes` planes are found.
+#[derive(Debug, Clone)]
+pub struct MultiPlaneExtractor {
+    /// The distance in meters within which a point belongs to a plane.
+    /// It should agree with the inlier threshold of the consensus
+    /// algorithm.
+    pub inlier_threshold: f64,
+    /// The minimum number of inliers a plane must have.
+    pub min_inliers: usize,
+    /// The minimum ratio of inliers to the total number of points a
+    /// plane must have.
+    pub min_inlier_ratio: f64,
+    /// The maximum number of planes to extract.
+    pub max_planes: Option<usize>,
+    /// If set, each plane is refined against its inliers and the
+    /// inliers are recollected with the refined plane.
+    pub refiner: Option<PlaneRefiner>,
+}
+
+impl MultiPlaneExtractor {
+    pub fn new(inlier_threshold: f64) -> Self {
+        Self {
+            inlier_threshold,
+            min_inliers: MIN_SAMPLES,
+            min_inlier_ratio: 0.0,
+            max_planes: None,
+            refiner: None,
+        }
+    }
+
+    /// Extracts planes from the points in the order they are found.
+    pub fn extract<C>(&self, consensus: &mut C, points: &[na::Point3<f64>]) -> Vec<ExtractedPlane>
+    where
+        C: Consensus<PlaneEstimator, na::Point3<f64>>,
+    {
+        let estimator = PlaneEstimator::new();
+        let min_support = {
+            let ratio_support = (self.min_inlier_ratio * points.len() as f64).ceil() as usize;
+            self.min_inliers.max(ratio_support).max(MIN_SAMPLES)
+        };
+
+        let mut remaining_indices: Vec<usize> = (0..points.len()).collect();
+        let mut planes = vec![];
+
+        loop {
+            if let Some(max_planes) = self.max_planes {
+                if planes.len() >= max_planes {
+                    break;
+                }
+            }
+            if remaining_indices.len() < min_support {
+                break;
+            }
+
+            let remaining_points = remaining_indices.iter().map(|&idx| points[idx]);
+            let (model, inliers) = match consensus.model_inliers(&estimator, remaining_points) {
+                Some(ret) => ret,
+                None => break,
+            };
+
+            // the inliers index into remaining_indices
+            let mut local_inliers: Vec<usize> = inliers.into_iter().collect();
+
+            let (model, normal_covariance) = match &self.refiner {
+                Some(refiner) => {
+                    let inlier_points: Vec<_> = local_inliers
+                        .iter()
+                        .map(|&idx| &points[remaining_indices[idx]])
+                        .collect();
+
+                    match refiner.refine(&model, &inlier_points) {
+                        Some(RefinedPlane {
+                            model,
+                            normal_covariance,
+                            ..
+                        }) => {
+                            local_inliers = remaining_indices
+                                .iter()
+                                .enumerate()
+                                .filter(|(_, &idx)| {
+                                    model.signed_distance(&points[idx]).abs()
+                                        <= self.inlier_threshold
+                                })
+                                .map(|(local_idx, _)| local_idx)
+                                .collect();
+                            (model, Some(normal_covariance))
+                        }
+                        None => (model, None),
+                    }
+                }
+                None => (model, None),
+            };
+
+            local_inliers.sort_unstable();
+            local_inliers.dedup();
+
+            if local_inliers.len() < min_support {
+                break;
+            }
+
+            let inlier_indices: Vec<usize> = local_inliers
+                .iter()
+                .map(|&local_idx| remaining_indices[local_idx])
+                .collect();
+            let residual_stats = ResidualStats::from_residuals(
+                inlier_indices
+                    .iter()
+                    .map(|&idx| model.signed_distance(&points[idx])),
+            );
+
+            // remove the inliers from remaining points
+            {
+                let mut is_inlier = vec![false; remaining_indices.len()];
+                local_inliers
+                    .iter()
+                    .for_each(|&local_idx| is_inlier[local_idx] = true);
+                remaining_indices = remaining_indices
+                    .into_iter()
+                    .zip(is_inlier)
+                    .filter_map(|(idx, is_inlier)| (!is_inlier).then_some(idx))
+                    .collect();
+            }
+
+            planes.push(ExtractedPlane {
+                model,
+                inlier_indices,
+                residual_stats,
+                normal_covariance,
+            });
+        }
+
+        planes
+    }
+}
+
+/// A plane found by [MultiPlaneExtractor].
+#[derive(Debug, Clone)]
+pub struct ExtractedPlane {
+    pub model: PlaneModel,
+    /// Indices to the input points in ascending order.
+    pub inlier_indices: Vec<usize>,
+    pub residual_stats: ResidualStats,
+    /// The covariance of the plane normal. It is available only if the
+    /// plane is refined.
+    pub normal_covariance: Option<na::Matrix3<f64>>,
+}
+
+/// Statistics of point-to-plane distances.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResidualStats {
+    pub count: usize,
+    pub mean_abs: f64,
+    pub rms: f64,
+    pub max_abs: f64,
+}
+
+impl ResidualStats {
+    pub fn from_residuals(residuals: impl IntoIterator<Item = f64>) -> Self {
+        let (count, abs_sum, sq_sum, max_abs) = residuals.into_iter().fold(
+            (0, 0.0, 0.0, 0.0f64),
+            |(count, abs_sum, sq_sum, max_abs), residual| {
+                let abs = residual.abs();
+                (
+                    count + 1,
+                    abs_sum + abs,
+                    sq_sum + abs * abs,
+                    max_abs.max(abs),
+                )
+            },
+        );
+
+        if count == 0 {
+            return Self {
+                count,
+                mean_abs: 0.0,
+                rms: 0.0,
+                max_abs: 0.0,
+            };
+        }
+
+        Self {
+            count,
+            mean_abs: abs_sum / count as f64,
+            rms: (sq_sum / count as f64).sqrt(),
+            max_abs,
+        }
+    }
+}
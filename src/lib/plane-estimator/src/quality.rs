@@ -0,0 +1,143 @@
+use crate::model::PlaneModel;
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+
+/// Quality metrics of a fitted plane.
+///
+/// The eigenvalue features are computed from the scatter matrix of
+/// inlier points, with eigenvalues `l1 >= l2 >= l3`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaneQuality {
+    pub num_inliers: usize,
+    /// The ratio of inliers to all input points.
+    pub inlier_ratio: f64,
+    /// The root mean square of inlier point-to-plane distances.
+    pub rms_residual: f64,
+    /// `(l2 - l3) / l1`. It approaches 1 for a patch spread evenly in
+    /// two directions, and 0 for a line or a blob.
+    pub planarity: f64,
+    /// `1 - l3 / l2`. It approaches 1 for a thin patch.
+    pub flatness: f64,
+    /// The extent of the inliers projected on the plane.
+    pub extent: PlaneExtent,
+}
+
+/// The oriented 2D bounding box of points projected on a plane.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaneExtent {
+    /// The center of the bounding box.
+    pub center: na::Point3<f64>,
+    /// The in-plane direction along which points spread the most.
+    pub major_axis: na::UnitVector3<f64>,
+    /// The in-plane direction perpendicular to `major_axis`.
+    pub minor_axis: na::UnitVector3<f64>,
+    /// The box length along the major axis.
+    pub major_length: f64,
+    /// The box length along the minor axis.
+    pub minor_length: f64,
+}
+
+impl PlaneExtent {
+    /// The length of the box diagonal.
+    pub fn diagonal(&self) -> f64 {
+        self.major_length.hypot(self.minor_length)
+    }
+}
+
+impl PlaneQuality {
+    /// Computes the quality of the plane from its inlier points.
+    ///
+    /// The `num_points` is the number of all points the plane was
+    /// fitted against. It returns `None` if there are no inliers.
+    pub fn compute<P>(model: &PlaneModel, inlier_points: &[P], num_points: usize) -> Option<Self>
+    where
+        P: Borrow<na::Point3<f64>>,
+    {
+        let num_inliers = inlier_points.len();
+        if num_inliers == 0 {
+            return None;
+        }
+
+        let rms_residual = {
+            let sq_sum: f64 = inlier_points
+                .iter()
+                .map(|point| model.signed_distance(point.borrow()).powi(2))
+                .sum();
+            (sq_sum / num_inliers as f64).sqrt()
+        };
+
+        let mean: na::Point3<f64> = {
+            let sum = inlier_points
+                .iter()
+                .fold(na::Vector3::zeros(), |sum, point| {
+                    sum + point.borrow().coords
+                });
+            (sum / num_inliers as f64).into()
+        };
+        let scatter = inlier_points
+            .iter()
+            .fold(na::Matrix3::zeros(), |sum, point| {
+                let vec = point.borrow() - mean;
+                sum + vec * vec.transpose()
+            })
+            / num_inliers as f64;
+
+        let na::SymmetricEigen {
+            eigenvalues,
+            eigenvectors,
+        } = scatter.symmetric_eigen();
+        let mut order = [0, 1, 2];
+        order.sort_by(|&lhs, &rhs| eigenvalues[rhs].total_cmp(&eigenvalues[lhs]));
+        let [l1, l2, l3] = order.map(|idx| eigenvalues[idx].max(0.0));
+
+        let planarity = if l1 > 0.0 { (l2 - l3) / l1 } else { 0.0 };
+        let flatness = if l2 > 0.0 { 1.0 - l3 / l2 } else { 0.0 };
+
+        // The major axis is the principal direction projected on the
+        // plane, so that the box stays on the plane.
+        let (major_axis, minor_axis) = {
+            let principal = eigenvectors.column(order[0]).into_owned();
+            let in_plane = principal - model.normal.scale(principal.dot(&model.normal));
+            let major_axis = match na::Unit::try_new(in_plane, 1e-9) {
+                Some(axis) => axis,
+                None => model.tangent_basis().0,
+            };
+            let minor_axis = na::Unit::new_normalize(model.normal.cross(&major_axis));
+            (major_axis, minor_axis)
+        };
+
+        let extent = {
+            let origin = model.project_point(&mean);
+            let init = (f64::INFINITY, f64::NEG_INFINITY);
+            let ((min_u, max_u), (min_v, max_v)) = inlier_points.iter().fold(
+                (init, init),
+                |((min_u, max_u), (min_v, max_v)), point| {
+                    let vec = point.borrow() - origin;
+                    let u = vec.dot(&major_axis);
+                    let v = vec.dot(&minor_axis);
+                    ((min_u.min(u), max_u.max(u)), (min_v.min(v), max_v.max(v)))
+                },
+            );
+
+            PlaneExtent {
+                center: origin
+                    + major_axis.scale((min_u + max_u) / 2.0)
+                    + minor_axis.scale((min_v + max_v) / 2.0),
+                major_axis,
+                minor_axis,
+                major_length: max_u - min_u,
+                minor_length: max_v - min_v,
+            }
+        };
+
+        Some(Self {
+            num_inliers,
+            inlier_ratio: num_inliers as f64 / num_points.max(num_inliers) as f64,
+            rms_residual,
+            planarity,
+            flatness,
+            extent,
+        })
+    }
+}
@@ -0,0 +1,249 @@
+use crate::model::PlaneModel;
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+
+/// The loss function used to weight point residuals during plane
+/// refinement.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RobustLoss {
+    /// Ordinary least squares. Every point has unit weight.
+    #[default]
+    LeastSquares,
+    /// Huber loss. Residuals beyond `threshold` meters are down-weighted
+    /// linearly.
+    Huber { threshold: f64 },
+    /// Tukey's biweight loss. Residuals beyond `threshold` meters are
+    /// ignored.
+    Tukey { threshold: f64 },
+}
+
+impl RobustLoss {
+    /// Computes the IRLS weight of a residual.
+    pub fn weight(&self, residual: f64) -> f64 {
+        let residual = residual.abs();
+
+        match *self {
+            Self::LeastSquares => 1.0,
+            Self::Huber { threshold } => {
+                if residual <= threshold {
+                    1.0
+                } else {
+                    threshold / residual
+                }
+            }
+            Self::Tukey { threshold } => {
+                if residual < threshold {
+                    let ratio = residual / threshold;
+                    let tmp = 1.0 - ratio * ratio;
+                    tmp * tmp
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Refines a plane against all of its inlier points.
+///
+/// The plane is re-fitted by principal component analysis on the
+/// weighted point scatter. Robust losses are solved by iteratively
+/// reweighted least squares (IRLS).
+#[derive(Debug, Clone)]
+pub struct PlaneRefiner {
+    pub loss: RobustLoss,
+    pub max_iterations: usize,
+    /// The iteration stops when both the normal angle change in
+    /// radians and the center shift in meters are below this value.
+    pub tolerance: f64,
+}
+
+impl PlaneRefiner {
+    pub fn new(loss: RobustLoss) -> Self {
+        Self {
+            loss,
+            ..Self::default()
+        }
+    }
+
+    /// Refines the initial plane against the points.
+    ///
+    /// It returns `None` if the points are too few or degenerate to
+    /// determine a plane.
+    pub fn refine<P>(&self, init: &PlaneModel, points: &[P]) -> Option<RefinedPlane>
+    where
+        P: Borrow<na::Point3<f64>>,
+    {
+        if points.len() < 3 {
+            return None;
+        }
+
+        let max_iterations = match self.loss {
+            RobustLoss::LeastSquares => 1,
+            _ => self.max_iterations.max(1),
+        };
+
+        let mut model = init.clone();
+        let mut fit = None;
+
+        for iteration in 1..=max_iterations {
+            let weights: Vec<f64> = points
+                .iter()
+                .map(|point| self.loss.weight(model.signed_distance(point.borrow())))
+                .collect();
+            let curr_fit = weighted_pca(points, &weights, &model.normal)?;
+
+            let angle_change = curr_fit.model.normal.angle(&model.normal);
+            let center_shift = (curr_fit.model.center - model.center)
+                .dot(&curr_fit.model.normal)
+                .abs();
+            model = curr_fit.model.clone();
+            fit = Some((curr_fit, iteration));
+
+            if angle_change <= self.tolerance && center_shift <= self.tolerance {
+                break;
+            }
+        }
+
+        let (fit, iterations) = fit?;
+        let WeightedFit {
+            model,
+            eigenvalues,
+            eigenvectors,
+            weight_sum,
+        } = fit;
+
+        let (weighted_sq_sum, sq_sum) = points
+            .iter()
+            .map(|point| {
+                let residual = model.signed_distance(point.borrow());
+                let weight = self.loss.weight(residual);
+                (weight * residual.powi(2), residual.powi(2))
+            })
+            .fold((0.0, 0.0), |(lw, l), (rw, r)| (lw + rw, l + r));
+        let rms_residual = (sq_sum / points.len() as f64).sqrt();
+
+        // First-order covariance of the normal. The normal is perturbed
+        // only within the span of the two in-plane principal axes.
+        let normal_covariance = {
+            let dof = weight_sum - 3.0;
+            let variance = if dof > 0.0 {
+                weighted_sq_sum / dof
+            } else {
+                0.0
+            };
+
+            [1, 2]
+                .into_iter()
+                .filter(|&idx| eigenvalues[idx] > 0.0)
+                .map(|idx| {
+                    let axis = eigenvectors.column(idx);
+                    axis * axis.transpose() * (variance / eigenvalues[idx])
+                })
+                .fold(na::Matrix3::zeros(), |sum, cov| sum + cov)
+        };
+
+        Some(RefinedPlane {
+            model,
+            normal_covariance,
+            rms_residual,
+            iterations,
+        })
+    }
+}
+
+impl Default for PlaneRefiner {
+    fn default() -> Self {
+        Self {
+            loss: RobustLoss::LeastSquares,
+            max_iterations: 20,
+            tolerance: 1e-9,
+        }
+    }
+}
+
+/// The result of [PlaneRefiner::refine].
+#[derive(Debug, Clone)]
+pub struct RefinedPlane {
+    pub model: PlaneModel,
+    /// The 3x3 covariance of the unit normal vector.
+    pub normal_covariance: na::Matrix3<f64>,
+    /// The root mean square of point-to-plane distances.
+    pub rms_residual: f64,
+    /// The number of performed IRLS iterations.
+    pub iterations: usize,
+}
+
+struct WeightedFit {
+    model: PlaneModel,
+    /// Eigenvalues of the weighted scatter matrix in ascending order.
+    eigenvalues: na::Vector3<f64>,
+    /// Eigenvectors in columns, sorted along with `eigenvalues`.
+    eigenvectors: na::Matrix3<f64>,
+    weight_sum: f64,
+}
+
+fn weighted_pca<P>(
+    points: &[P],
+    weights: &[f64],
+    prev_normal: &na::UnitVector3<f64>,
+) -> Option<WeightedFit>
+where
+    P: Borrow<na::Point3<f64>>,
+{
+    let weight_sum: f64 = weights.iter().sum();
+    if weight_sum <= 0.0 {
+        return None;
+    }
+
+    let center: na::Point3<f64> = {
+        let sum = points
+            .iter()
+            .zip(weights)
+            .fold(na::Vector3::zeros(), |sum, (point, &weight)| {
+                sum + point.borrow().coords * weight
+            });
+        (sum / weight_sum).into()
+    };
+
+    let scatter = points
+        .iter()
+        .zip(weights)
+        .fold(na::Matrix3::zeros(), |sum, (point, &weight)| {
+            let vec = point.borrow() - center;
+            sum + vec * vec.transpose() * weight
+        });
+
+    let na::SymmetricEigen {
+        eigenvalues,
+        eigenvectors,
+    } = scatter.symmetric_eigen();
+
+    let mut order = [0, 1, 2];
+    order.sort_by(|&lhs, &rhs| eigenvalues[lhs].total_cmp(&eigenvalues[rhs]));
+    let eigenvalues = na::Vector3::from_fn(|idx, _| eigenvalues[order[idx]]);
+    let eigenvectors = na::Matrix3::from_columns(&order.map(|idx| eigenvectors.column(idx)));
+
+    // The plane is degenerate if points lie on a line.
+    if eigenvalues[1] <= eigenvalues[2] * 1e-12 {
+        return None;
+    }
+
+    let normal = {
+        let normal = na::Unit::new_normalize(eigenvectors.column(0).into_owned());
+        if normal.dot(prev_normal) < 0.0 {
+            -normal
+        } else {
+            normal
+        }
+    };
+
+    Some(WeightedFit {
+        model: PlaneModel { center, normal },
+        eigenvalues,
+        eigenvectors,
+        weight_sum,
+    })
+}
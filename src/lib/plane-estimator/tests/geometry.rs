@@ -0,0 +1,90 @@
+use approx::assert_abs_diff_eq;
+use nalgebra as na;
+use plane_estimator::{Line3, PlaneModel};
+
+fn plane(center: [f64; 3], normal: [f64; 3]) -> PlaneModel {
+    PlaneModel {
+        center: center.into(),
+        normal: na::Unit::new_normalize(normal.into()),
+    }
+}
+
+#[test]
+fn pose_z_axis_follows_normal() {
+    for normal in [
+        [0.0, 0.0, 1.0],
+        [1.0, 0.0, 0.0],
+        [0.3, -0.4, 0.8],
+        [-1.0, 0.0, -1e-3],
+    ] {
+        let plane = plane([1.0, 2.0, 3.0], normal);
+        let pose = plane.pose();
+
+        let z_axis = pose * na::Vector3::z_axis();
+        assert_abs_diff_eq!(
+            z_axis.into_inner(),
+            plane.normal.into_inner(),
+            epsilon = 1e-9
+        );
+        assert_abs_diff_eq!(pose * na::Point3::origin(), plane.center, epsilon = 1e-9);
+
+        let (x_axis, y_axis) = plane.tangent_basis();
+        assert_abs_diff_eq!(
+            (pose * na::Vector3::x_axis()).into_inner(),
+            x_axis.into_inner(),
+            epsilon = 1e-9
+        );
+        assert_abs_diff_eq!(
+            x_axis.cross(&y_axis),
+            plane.normal.into_inner(),
+            epsilon = 1e-9
+        );
+    }
+}
+
+#[test]
+fn projection_round_trip() {
+    let plane = plane([0.5, -1.0, 2.0], [0.2, 0.3, 1.0]);
+    let point = na::Point3::new(1.3, 0.4, 5.0);
+
+    let projected = plane.project_point(&point);
+    assert_abs_diff_eq!(plane.signed_distance(&projected), 0.0, epsilon = 1e-9);
+    assert_abs_diff_eq!(
+        (point - projected).norm(),
+        plane.signed_distance(&point).abs(),
+        epsilon = 1e-9
+    );
+
+    let coords = plane.to_plane_coords(&point);
+    assert_abs_diff_eq!(plane.from_plane_coords(&coords), projected, epsilon = 1e-9);
+}
+
+#[test]
+fn plane_intersections() {
+    let xy = plane([0.0, 0.0, 1.0], [0.0, 0.0, 1.0]);
+    let yz = plane([2.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+    let xz = plane([0.0, -3.0, 0.0], [0.0, 1.0, 0.0]);
+
+    let line = xy.intersect_plane(&yz).unwrap();
+    assert_abs_diff_eq!(line.direction.x, 0.0, epsilon = 1e-9);
+    assert_abs_diff_eq!(line.direction.z, 0.0, epsilon = 1e-9);
+    assert_abs_diff_eq!(
+        line.distance_to(&na::Point3::new(2.0, 7.0, 1.0)),
+        0.0,
+        epsilon = 1e-9
+    );
+
+    let point = xy.intersect_planes(&yz, &xz).unwrap();
+    assert_abs_diff_eq!(point, na::Point3::new(2.0, -3.0, 1.0), epsilon = 1e-9);
+    assert_abs_diff_eq!(xz.intersect_line(&line).unwrap(), point, epsilon = 1e-9);
+
+    let parallel = plane([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]);
+    assert!(xy.intersect_plane(&parallel).is_none());
+    assert!(xy.intersect_planes(&parallel, &yz).is_none());
+
+    let in_plane = Line3 {
+        point: na::Point3::new(0.0, 0.0, 1.0),
+        direction: na::Vector3::x_axis(),
+    };
+    assert!(xy.intersect_line(&in_plane).is_none());
+}
@@ -0,0 +1,79 @@
+use nalgebra as na;
+use plane_estimator::{MultiPlaneExtractor, PlaneEstimator, PlaneModel, PlaneRefiner};
+use sample_consensus::{Consensus, Estimator, Model};
+
+/// A deterministic consensus that tries a fixed set of point triples.
+struct ExhaustiveConsensus {
+    inlier_threshold: f64,
+}
+
+impl Consensus<PlaneEstimator, na::Point3<f64>> for ExhaustiveConsensus {
+    type Inliers = Vec<usize>;
+
+    fn model<I>(&mut self, estimator: &PlaneEstimator, data: I) -> Option<PlaneModel>
+    where
+        I: Iterator<Item = na::Point3<f64>> + Clone,
+    {
+        self.model_inliers(estimator, data).map(|(model, _)| model)
+    }
+
+    fn model_inliers<I>(
+        &mut self,
+        estimator: &PlaneEstimator,
+        data: I,
+    ) -> Option<(PlaneModel, Vec<usize>)>
+    where
+        I: Iterator<Item = na::Point3<f64>> + Clone,
+    {
+        let points: Vec<_> = data.collect();
+        let len = points.len();
+
+        (0..len)
+            .step_by(7)
+            .flat_map(|i| [(i, (i + 5) % len, (i + 29) % len)])
+            .filter_map(|(i, j, k)| {
+                let model = estimator.estimate([points[i], points[j], points[k]].into_iter())?;
+                let inliers: Vec<usize> = points
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, point)| model.residual(*point) <= self.inlier_threshold)
+                    .map(|(idx, _)| idx)
+                    .collect();
+                Some((model, inliers))
+            })
+            .max_by_key(|(_, inliers)| inliers.len())
+    }
+}
+
+#[test]
+fn extract_ground_and_board() {
+    // a large ground plane z = 0
+    let ground = (0..30).flat_map(|xi| {
+        (0..30).map(move |yi| na::Point3::new(xi as f64 * 0.2, yi as f64 * 0.2, 0.0))
+    });
+    // a smaller board plane x = 3
+    let board = (0..12).flat_map(|yi| {
+        (0..12).map(move |zi| na::Point3::new(3.0, 1.0 + yi as f64 * 0.08, 0.5 + zi as f64 * 0.08))
+    });
+    // a few scattered points
+    let clutter =
+        (0..5).map(|idx| na::Point3::new(10.0 + idx as f64, -5.0, 1.0 + 7.0 * idx as f64));
+    let points: Vec<_> = ground.chain(board).chain(clutter).collect();
+
+    let mut consensus = ExhaustiveConsensus {
+        inlier_threshold: 0.01,
+    };
+    let extractor = MultiPlaneExtractor {
+        min_inliers: 50,
+        refiner: Some(PlaneRefiner::default()),
+        ..MultiPlaneExtractor::new(0.01)
+    };
+    let planes = extractor.extract(&mut consensus, &points);
+
+    assert_eq!(planes.len(), 2);
+    assert_eq!(planes[0].inlier_indices.len(), 900);
+    assert!(planes[0].model.normal.z.abs() > 0.999);
+    assert_eq!(planes[1].inlier_indices.len(), 144);
+    assert!(planes[1].model.normal.x.abs() > 0.999);
+    assert!(planes.iter().all(|plane| plane.residual_stats.rms < 1e-6));
+}
@@ -0,0 +1,50 @@
+use approx::assert_abs_diff_eq;
+use nalgebra as na;
+use plane_estimator::{PlaneModel, PlaneQuality};
+
+fn vertical_plane() -> PlaneModel {
+    PlaneModel {
+        center: na::Point3::new(5.0, 0.0, 0.0),
+        normal: na::Vector3::x_axis(),
+    }
+}
+
+#[test]
+fn square_patch_quality() {
+    // a 1m x 1m grid on the plane x = 5
+    let points: Vec<_> = (0..=20)
+        .flat_map(|yi| (0..=20).map(move |zi| (yi, zi)))
+        .map(|(yi, zi)| na::Point3::new(5.0, yi as f64 * 0.05, zi as f64 * 0.05))
+        .collect();
+
+    let quality = PlaneQuality::compute(&vertical_plane(), &points, points.len() * 2).unwrap();
+
+    assert_eq!(quality.num_inliers, points.len());
+    assert_abs_diff_eq!(quality.inlier_ratio, 0.5);
+    assert_abs_diff_eq!(quality.rms_residual, 0.0);
+    assert!(quality.planarity > 0.95);
+    assert!(quality.flatness > 0.99);
+    assert_abs_diff_eq!(quality.extent.major_length, 1.0, epsilon = 1e-6);
+    assert_abs_diff_eq!(quality.extent.minor_length, 1.0, epsilon = 1e-6);
+    assert_abs_diff_eq!(
+        quality.extent.center,
+        na::Point3::new(5.0, 0.5, 0.5),
+        epsilon = 1e-6
+    );
+}
+
+#[test]
+fn elongated_patch_quality() {
+    // a 4m long strip of two scan lines on the plane x = 5
+    let points: Vec<_> = (0..=80)
+        .flat_map(|yi| [(yi, 0.0), (yi, 0.05)])
+        .map(|(yi, z)| na::Point3::new(5.0, yi as f64 * 0.05, z))
+        .collect();
+
+    let quality = PlaneQuality::compute(&vertical_plane(), &points, points.len()).unwrap();
+
+    assert!(quality.planarity < 0.01);
+    assert_abs_diff_eq!(quality.extent.major_length, 4.0, epsilon = 1e-6);
+    assert_abs_diff_eq!(quality.extent.minor_length, 0.05, epsilon = 1e-6);
+    assert_abs_diff_eq!(quality.extent.major_axis.y.abs(), 1.0, epsilon = 1e-6);
+}
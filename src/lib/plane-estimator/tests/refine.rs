@@ -0,0 +1,79 @@
+use nalgebra as na;
+use plane_estimator::{PlaneModel, PlaneRefiner, RobustLoss};
+
+/// Generates points on the plane z = 0.1x + 0.2y + 3 with
+/// deterministic noise along z.
+fn noisy_plane_points(noise: f64) -> Vec<na::Point3<f64>> {
+    (0..20)
+        .flat_map(|xi| (0..20).map(move |yi| (xi, yi)))
+        .enumerate()
+        .map(|(idx, (xi, yi))| {
+            let x = xi as f64 * 0.05;
+            let y = yi as f64 * 0.05;
+            let z = 0.1 * x + 0.2 * y + 3.0 + noise * (idx as f64 * 1.7).sin();
+            na::Point3::new(x, y, z)
+        })
+        .collect()
+}
+
+fn true_normal() -> na::UnitVector3<f64> {
+    na::Unit::new_normalize(na::Vector3::new(-0.1, -0.2, 1.0))
+}
+
+#[test]
+fn least_squares_refinement() {
+    let points = noisy_plane_points(0.005);
+
+    // a poor initial guess as if it came from three noisy samples
+    let init = PlaneModel {
+        center: points[0],
+        normal: na::Unit::new_normalize(na::Vector3::new(0.0, -0.3, 1.0)),
+    };
+
+    let refined = PlaneRefiner::new(RobustLoss::LeastSquares)
+        .refine(&init, &points)
+        .expect("unable to refine the plane");
+
+    assert!(refined.model.normal.angle(&true_normal()) < 0.01);
+    assert!(refined.rms_residual < 0.005);
+    assert!(refined.normal_covariance.trace() > 0.0);
+    assert!(refined.normal_covariance.trace() < 1e-4);
+}
+
+#[test]
+fn tukey_refinement_rejects_outliers() {
+    let mut points = noisy_plane_points(0.002);
+    points.extend((0..40).map(|idx| {
+        let x = idx as f64 * 0.02;
+        na::Point3::new(x, 0.5, 3.0 + 0.1 * x + 0.1 + 0.5 * (idx % 3) as f64)
+    }));
+
+    let init = PlaneModel {
+        center: points[0],
+        normal: true_normal(),
+    };
+
+    let least_squares = PlaneRefiner::new(RobustLoss::LeastSquares)
+        .refine(&init, &points)
+        .unwrap();
+    let tukey = PlaneRefiner::new(RobustLoss::Tukey { threshold: 0.05 })
+        .refine(&init, &points)
+        .unwrap();
+
+    let ls_error = least_squares.model.normal.angle(&true_normal());
+    let tukey_error = tukey.model.normal.angle(&true_normal());
+    assert!(tukey_error < ls_error);
+    assert!(tukey_error < 0.005);
+}
+
+#[test]
+fn degenerate_points() {
+    let points: Vec<_> = (0..10)
+        .map(|idx| na::Point3::new(idx as f64, 0.0, 0.0))
+        .collect();
+    let init = PlaneModel {
+        center: points[0],
+        normal: na::Vector3::z_axis(),
+    };
+    assert!(PlaneRefiner::default().refine(&init, &points).is_none());
+}